@@ -0,0 +1,189 @@
+//! Desktop notifications of significant module state changes
+
+use std::{
+    collections::HashMap,
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::config::{self, AlertsConfig};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Urgency {
+    Normal,
+    Critical,
+}
+
+/// Significant module state change, to notify
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Alert {
+    /// Rule that raised it, ie. `overheat`, to disable it from config
+    pub rule: &'static str,
+    pub urgency: Urgency,
+    pub summary: String,
+    pub body: String,
+}
+
+/// Sender of the alerts of a module, which drops the ones already sent during the cooldown period
+pub(crate) struct Alerter {
+    /// Module name, as in config
+    module: String,
+    cfg: AlertsConfig,
+    /// When alerts were last sent, by rule and summary
+    last_sent: HashMap<(&'static str, String), Instant>,
+}
+
+impl Alerter {
+    pub(crate) fn new(module: &str, cfg: AlertsConfig) -> Self {
+        Self {
+            module: config::module_config_name(module),
+            cfg,
+            last_sent: HashMap::new(),
+        }
+    }
+
+    /// Apply changed config, keeping the cooldown periods already started
+    pub(crate) fn set_config(&mut self, cfg: AlertsConfig) {
+        self.cfg = cfg;
+    }
+
+    pub(crate) fn send(&mut self, alerts: Vec<Alert>) {
+        let now = Instant::now();
+        for alert in alerts {
+            if !self.should_send(&alert, now) {
+                log::debug!("Dropping {alert:?}");
+                continue;
+            }
+            if let Err(err) = notify(&alert) {
+                log::warn!("Failed to send notification for {alert:?}: {err:#}");
+            }
+        }
+    }
+
+    /// Return whether `alert` is enabled and out of its cooldown period, and if so start a new one
+    fn should_send(&mut self, alert: &Alert, now: Instant) -> bool {
+        if !self.cfg.enabled
+            || self
+                .cfg
+                .disabled
+                .iter()
+                .any(|d| d == &self.module || *d == format!("{}.{}", self.module, alert.rule))
+        {
+            return false;
+        }
+        let key = (alert.rule, alert.summary.clone());
+        let cooldown = Duration::from_secs(self.cfg.cooldown_s);
+        if self
+            .last_sent
+            .get(&key)
+            .is_some_and(|t| now.duration_since(*t) < cooldown)
+        {
+            return false;
+        }
+        self.last_sent.insert(key, now);
+        true
+    }
+}
+
+/// Show `alert` as a desktop notification, without waiting for it to be shown
+fn notify(alert: &Alert) -> anyhow::Result<()> {
+    let urgency = match alert.urgency {
+        Urgency::Normal => "normal",
+        Urgency::Critical => "critical",
+    };
+    let mut child = Command::new("notify-send")
+        .args([
+            "--app-name",
+            env!("CARGO_PKG_NAME"),
+            "--urgency",
+            urgency,
+            &alert.summary,
+            &alert.body,
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // Notification server may be slow to answer, ie. while being started, so reap in the background
+    let summary = alert.summary.clone();
+    thread::Builder::new()
+        .name("notify".to_owned())
+        .spawn(move || {
+            if let Err(err) = child
+                .wait()
+                .map_err(anyhow::Error::from)
+                .and_then(|status| Ok(status.exit_ok()?))
+            {
+                log::warn!("Failed to send notification {summary:?}: {err:#}");
+            }
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+#[expect(clippy::shadow_unrelated)]
+mod tests {
+    use super::*;
+
+    fn alert(rule: &'static str, summary: &str) -> Alert {
+        Alert {
+            rule,
+            urgency: Urgency::Normal,
+            summary: summary.to_owned(),
+            body: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_cooldown() {
+        let mut alerter = Alerter::new(
+            "network-status",
+            AlertsConfig {
+                cooldown_s: 60,
+                ..AlertsConfig::default()
+            },
+        );
+        let now = Instant::now();
+        let down = alert("host_unreachable", "router is unreachable");
+        assert!(alerter.should_send(&down, now));
+        assert!(!alerter.should_send(&down, now + Duration::from_secs(59)));
+        assert!(alerter.should_send(
+            &alert("host_unreachable", "nas is unreachable"),
+            now + Duration::from_secs(59)
+        ));
+        assert!(alerter.should_send(&down, now + Duration::from_mins(1)));
+    }
+
+    #[test]
+    fn test_disabled() {
+        let now = Instant::now();
+        let mut alerter = Alerter::new(
+            "gpu-nvidia",
+            AlertsConfig {
+                disabled: vec!["gpu_nvidia.overheat".to_owned()],
+                ..AlertsConfig::default()
+            },
+        );
+        assert!(!alerter.should_send(&alert("overheat", "GPU overheating"), now));
+        assert!(alerter.should_send(&alert("other", "Other"), now));
+
+        let mut alerter = Alerter::new(
+            "gpu-nvidia",
+            AlertsConfig {
+                disabled: vec!["gpu_nvidia".to_owned()],
+                ..AlertsConfig::default()
+            },
+        );
+        assert!(!alerter.should_send(&alert("other", "Other"), now));
+
+        let mut alerter = Alerter::new(
+            "gpu-nvidia",
+            AlertsConfig {
+                enabled: false,
+                ..AlertsConfig::default()
+            },
+        );
+        assert!(!alerter.should_send(&alert("other", "Other"), now));
+    }
+}
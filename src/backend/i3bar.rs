@@ -0,0 +1,221 @@
+//! i3bar/swaybar JSON protocol, see <https://i3wm.org/docs/i3bar-protocol.html>
+
+use std::{
+    io::{self, BufRead as _},
+    sync::{
+        Arc, Mutex,
+        mpsc::{Receiver, Sender, channel},
+    },
+    thread,
+};
+
+use super::{Actions, pango, run_action, severity, span_actions};
+use crate::{markup, theme};
+
+#[derive(Debug, Eq, PartialEq, serde::Serialize)]
+struct Block {
+    full_text: String,
+    name: String,
+    instance: String,
+    color: String,
+    markup: &'static str,
+    urgent: bool,
+    separator: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    separator_block_width: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ClickEvent {
+    name: Option<String>,
+    instance: Option<String>,
+    button: u8,
+}
+
+/// Click actions of each module, and of each of its blocks
+type ModuleActions = Arc<Mutex<Vec<Vec<Actions>>>>;
+
+/// Split rendered module output in blocks, one for each region with distinct click actions
+fn blocks(name: &str, output: &str) -> (Vec<Block>, Vec<Actions>) {
+    let spans = markup::parse(output);
+    let mut span_groups: Vec<(Actions, Vec<markup::Span>)> = Vec::new();
+    for span in spans {
+        let actions = span_actions([&span]);
+        match span_groups.last_mut() {
+            Some((group_actions, group_spans)) if *group_actions == actions => {
+                group_spans.push(span);
+            }
+            _ => span_groups.push((actions, vec![span])),
+        }
+    }
+
+    let block_count = span_groups.len();
+    span_groups
+        .into_iter()
+        .enumerate()
+        .map(|(i, (actions, group_spans))| {
+            let is_last = i + 1 == block_count;
+            let block = Block {
+                full_text: pango(&group_spans),
+                name: name.to_owned(),
+                instance: i.to_string(),
+                color: format!("#{:06x}", theme::Color::Foreground.rgb()),
+                markup: "pango",
+                urgent: severity(&group_spans) == Some(theme::Color::Critical),
+                // Blocks of a module are glued together
+                separator: is_last,
+                separator_block_width: (!is_last).then_some(0),
+            };
+            (block, actions)
+        })
+        .unzip()
+}
+
+/// Status line of module `name` alone, as a JSON array of its blocks
+pub(crate) fn status_line(name: &str, output: &str) -> String {
+    let (blocks, _actions) = blocks(name, output);
+    serde_json::to_string(&blocks).unwrap_or_default()
+}
+
+/// Spawn the threads writing the status line of modules `names` to stdout, and handling click events from stdin
+///
+/// Outputs are sent to the returned channel, along with the index of their module in `names`.
+pub(crate) fn spawn_status_line(names: Vec<String>) -> anyhow::Result<Sender<(usize, String)>> {
+    let (outputs_tx, outputs_rx) = channel();
+    let actions: ModuleActions = Arc::new(Mutex::new(vec![Vec::new(); names.len()]));
+
+    let click_names = names.clone();
+    let click_actions = Arc::clone(&actions);
+    thread::Builder::new()
+        .name("i3bar-clicks".to_owned())
+        .spawn(move || {
+            if let Err(err) = read_clicks(&click_names, &click_actions) {
+                log::error!("Failed to read click events: {err}");
+            }
+        })?;
+
+    thread::Builder::new()
+        .name("i3bar-status".to_owned())
+        .spawn(move || write_status_line(&names, &outputs_rx, &actions))?;
+
+    Ok(outputs_tx)
+}
+
+fn write_status_line(
+    names: &[String],
+    outputs: &Receiver<(usize, String)>,
+    actions: &ModuleActions,
+) {
+    println!("{{\"version\":1,\"click_events\":true}}");
+    // Start of the infinite array of status lines
+    println!("[");
+    let mut module_blocks: Vec<Vec<Block>> = names.iter().map(|_| Vec::new()).collect();
+    for (index, output) in outputs {
+        let (Some(name), Some(cur_blocks)) = (names.get(index), module_blocks.get_mut(index))
+        else {
+            continue;
+        };
+        let (new_blocks, new_actions) = blocks(name, &output);
+        *cur_blocks = new_blocks;
+        if let Some(cur_actions) = actions.lock().unwrap().get_mut(index) {
+            *cur_actions = new_actions;
+        }
+        let status_line: Vec<&Block> = module_blocks.iter().flatten().collect();
+        match serde_json::to_string(&status_line) {
+            Ok(json) => println!("{json},"),
+            Err(err) => log::error!("Failed to serialize status line: {err}"),
+        }
+    }
+}
+
+fn read_clicks(names: &[String], actions: &ModuleActions) -> anyhow::Result<()> {
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        // Events are elements of an infinite array, one per line
+        let event_json = line.trim().trim_start_matches(['[', ',']);
+        if event_json.is_empty() {
+            continue;
+        }
+        let event: ClickEvent = match serde_json::from_str(event_json) {
+            Ok(event) => event,
+            Err(err) => {
+                log::warn!("Invalid click event {event_json:?}: {err}");
+                continue;
+            }
+        };
+        log::debug!("{event:?}");
+        let Some(module_index) = names.iter().position(|n| Some(n) == event.name.as_ref()) else {
+            continue;
+        };
+        let Some(block_index) = event.instance.and_then(|i| i.parse::<usize>().ok()) else {
+            continue;
+        };
+        let block_actions = actions
+            .lock()
+            .unwrap()
+            .get(module_index)
+            .and_then(|a| a.get(block_index))
+            .cloned();
+        if let Some(block_actions) = block_actions {
+            run_action(&block_actions, event.button);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markup::{Markup, PolybarActionType};
+
+    #[test]
+    fn test_blocks() {
+        assert_eq!(blocks("m", ""), (vec![], vec![]));
+
+        let output = format!(
+            "{} {}",
+            Markup::new("I").fg(theme::Color::MainIcon).into_string(),
+            Markup::new("hot")
+                .fg(theme::Color::Critical)
+                .action(PolybarActionType::ClickLeft, "cmd")
+                .into_string()
+        );
+        let (blocks, actions) = blocks("m", &output);
+        assert_eq!(
+            blocks,
+            vec![
+                Block {
+                    full_text: "<span foreground=\"#f1e9d2\">I</span> ".to_owned(),
+                    name: "m".to_owned(),
+                    instance: "0".to_owned(),
+                    color: "#8faaab".to_owned(),
+                    markup: "pango",
+                    urgent: false,
+                    separator: false,
+                    separator_block_width: Some(0),
+                },
+                Block {
+                    full_text: "<span foreground=\"#f23749\">hot</span>".to_owned(),
+                    name: "m".to_owned(),
+                    instance: "1".to_owned(),
+                    color: "#8faaab".to_owned(),
+                    markup: "pango",
+                    urgent: true,
+                    separator: true,
+                    separator_block_width: None,
+                },
+            ]
+        );
+        assert_eq!(
+            actions,
+            vec![
+                vec![],
+                vec![(PolybarActionType::ClickLeft, "cmd".to_owned())]
+            ]
+        );
+        assert_eq!(
+            serde_json::to_string(blocks.last().unwrap()).unwrap(),
+            "{\"full_text\":\"<span foreground=\\\"#f23749\\\">hot</span>\",\"name\":\"m\",\"instance\":\"1\",\"color\":\"#8faaab\",\"markup\":\"pango\",\"urgent\":true,\"separator\":true}"
+        );
+    }
+}
@@ -0,0 +1,338 @@
+//! Output backends, converting the markup rendered by modules for each bar
+
+use std::{
+    fmt::Write as _,
+    process::{Command, Stdio},
+    sync::{
+        Arc, Mutex,
+        mpsc::{Sender, channel},
+    },
+    thread,
+};
+
+use anyhow::Context as _;
+
+use crate::{
+    config::Backend,
+    daemon,
+    markup::{self, MarkupOp, PolybarActionType},
+    theme,
+};
+
+pub(crate) mod i3bar;
+pub(crate) mod polybar_ipc;
+pub(crate) mod waybar;
+
+/// Function publishing the rendered output of a module, and its detail
+pub(crate) type Publisher = Box<dyn FnMut(String, Option<String>) + Send>;
+
+/// Click actions of an output region
+pub(crate) type Actions = Vec<(PolybarActionType, String)>;
+
+/// Build publishers for modules, for a given backend
+pub(crate) struct Publishers {
+    backend: Backend,
+    daemon: bool,
+    status_line: Option<Sender<(usize, String)>>,
+}
+
+impl Publishers {
+    /// Set up publishing for modules `names`, several of them running in the same process if `daemon` is true
+    pub(crate) fn new(backend: Backend, names: &[String], daemon: bool) -> anyhow::Result<Self> {
+        let status_line = if let Backend::I3bar = backend {
+            Some(i3bar::spawn_status_line(names.to_vec())?)
+        } else {
+            None
+        };
+        Ok(Self {
+            backend,
+            daemon,
+            status_line,
+        })
+    }
+
+    /// Get publisher for module `name`, at position `index` in the module list
+    pub(crate) fn publisher(&self, index: usize, name: &str) -> anyhow::Result<Publisher> {
+        let publisher: Publisher = match self.backend {
+            Backend::Polybar => {
+                if self.daemon {
+                    let fifo_tx = spawn_fifo_publisher(name)?;
+                    Box::new(move |output, _detail| {
+                        // Only fails if the publishing thread is gone, and it has logged why
+                        let _ = fifo_tx.send(markup::to_polybar(&output));
+                    })
+                } else {
+                    Box::new(|output, _detail| println!("{}", markup::to_polybar(&output)))
+                }
+            }
+            Backend::PolybarIpc => {
+                let (ipc_tx, ipc_rx) = channel();
+                let ipc_name = name.to_owned();
+                thread::Builder::new()
+                    .name(format!("{name}-publish"))
+                    .spawn(move || polybar_ipc::publish(&ipc_name, &ipc_rx))?;
+                Box::new(move |output, _detail| {
+                    let _ = ipc_tx.send(markup::to_polybar(&output));
+                })
+            }
+            Backend::I3bar => {
+                let status_line_tx = self
+                    .status_line
+                    .clone()
+                    .ok_or_else(|| anyhow::anyhow!("Status line is not running"))?;
+                // i3bar has no tooltips
+                Box::new(move |output, _detail| {
+                    let _ = status_line_tx.send((index, output));
+                })
+            }
+            Backend::Waybar => {
+                let actions = Arc::new(Mutex::new(Actions::new()));
+                waybar::spawn_click_reader(name, Arc::clone(&actions))?;
+                let sink: Box<dyn Fn(String) + Send> = if self.daemon {
+                    let fifo_tx = spawn_fifo_publisher(name)?;
+                    Box::new(move |output| {
+                        let _ = fifo_tx.send(output);
+                    })
+                } else {
+                    Box::new(|output| println!("{output}"))
+                };
+                Box::new(move |output, detail| {
+                    let (json, output_actions) = waybar::convert(&output, detail.as_deref());
+                    *actions.lock().unwrap() = output_actions;
+                    sink(json);
+                })
+            }
+        };
+        Ok(publisher)
+    }
+}
+
+/// Format output of module `name` and its detail published a single time, ie. with `--once`, like the publishers
+/// of `backend` do
+pub(crate) fn format_once(
+    backend: Backend,
+    name: &str,
+    output: &str,
+    detail: Option<&str>,
+) -> String {
+    match backend {
+        Backend::Polybar | Backend::PolybarIpc => markup::to_polybar(output),
+        Backend::I3bar => i3bar::status_line(name, output),
+        Backend::Waybar => waybar::convert(output, detail).0,
+    }
+}
+
+/// Create the named pipe for module `name`, and spawn the thread publishing to it
+fn spawn_fifo_publisher(name: &str) -> anyhow::Result<Sender<String>> {
+    let xdg_dirs = xdg::BaseDirectories::with_prefix(env!("CARGO_PKG_NAME"));
+    let fifo_filepath = xdg_dirs.place_runtime_file(format!("{name}.fifo"))?;
+    daemon::create_fifo(&fifo_filepath)
+        .with_context(|| format!("Failed to create named pipe {fifo_filepath:?}"))?;
+    log::info!("Module {name:?} publishes to {fifo_filepath:?}");
+    let (fifo_tx, fifo_rx) = channel();
+    thread::Builder::new()
+        .name(format!("{name}-publish"))
+        .spawn(move || {
+            if let Err(err) = daemon::publish_fifo(&fifo_filepath, &fifo_rx) {
+                log::error!("Failed to publish to {fifo_filepath:?}: {err:#}");
+            }
+        })?;
+    Ok(fifo_tx)
+}
+
+/// Render spans as Pango markup
+fn pango(spans: &[markup::Span]) -> String {
+    let mut r = String::new();
+    for span in spans {
+        let mut attrs = String::new();
+        for op in &span.ops {
+            match op {
+                MarkupOp::Foreground(color) => {
+                    let _ = write!(attrs, " foreground=\"#{:06x}\"", color.rgb());
+                }
+                MarkupOp::Background(color) => {
+                    let _ = write!(attrs, " background=\"#{:06x}\"", color.rgb());
+                }
+                MarkupOp::Underline(color) => {
+                    let _ = write!(
+                        attrs,
+                        " underline=\"single\" underline_color=\"#{:06x}\"",
+                        color.rgb()
+                    );
+                }
+                MarkupOp::Overline(color) => {
+                    let _ = write!(
+                        attrs,
+                        " overline=\"single\" overline_color=\"#{:06x}\"",
+                        color.rgb()
+                    );
+                }
+                // Font indexes are specific to the polybar configuration, and actions are handled at the block level
+                MarkupOp::Font(_) | MarkupOp::Action { .. } => {}
+            }
+        }
+        let text = escape_pango(&span.text);
+        if attrs.is_empty() {
+            r.push_str(&text);
+        } else {
+            let _ = write!(r, "<span{attrs}>{text}</span>");
+        }
+    }
+    r
+}
+
+fn escape_pango(s: &str) -> String {
+    let mut r = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => r.push_str("&amp;"),
+            '<' => r.push_str("&lt;"),
+            '>' => r.push_str("&gt;"),
+            '\'' => r.push_str("&apos;"),
+            '"' => r.push_str("&quot;"),
+            c => r.push(c),
+        }
+    }
+    r
+}
+
+/// Most severe status color used in spans, if any
+fn severity(spans: &[markup::Span]) -> Option<theme::Color> {
+    const SEVERITIES: [theme::Color; 4] = [
+        theme::Color::Good,
+        theme::Color::Notice,
+        theme::Color::Attention,
+        theme::Color::Critical,
+    ];
+    let max_severity = spans
+        .iter()
+        .flat_map(|s| &s.ops)
+        .filter_map(|op| match op {
+            MarkupOp::Foreground(color)
+            | MarkupOp::Background(color)
+            | MarkupOp::Underline(color)
+            | MarkupOp::Overline(color) => SEVERITIES.iter().position(|s| s == color),
+            MarkupOp::Font(_) | MarkupOp::Action { .. } => None,
+        })
+        .max()?;
+    SEVERITIES.get(max_severity).copied()
+}
+
+/// Click actions of spans, innermost first, keeping only one for each button
+fn span_actions<'a, I>(spans: I) -> Actions
+where
+    I: IntoIterator<Item = &'a markup::Span>,
+{
+    let mut actions = Actions::new();
+    for span in spans {
+        for op in span.ops.iter().rev() {
+            if let MarkupOp::Action { type_, command } = op
+                && !actions.iter().any(|(t, _)| t == type_)
+            {
+                actions.push((*type_, command.to_owned()));
+            }
+        }
+    }
+    actions
+}
+
+/// Run the action bound to mouse `button` in the background, if any
+fn run_action(actions: &Actions, button: u8) {
+    let Ok(type_) = PolybarActionType::try_from(button) else {
+        log::warn!("Unsupported mouse button {button}");
+        return;
+    };
+    let Some((_, command)) = actions.iter().find(|(t, _)| *t == type_) else {
+        log::debug!("No action for {type_:?}");
+        return;
+    };
+    log::debug!("Running {command:?}");
+    // Standard output may be the bar protocol stream, so it must not be inherited
+    match Command::new("sh")
+        .args(["-c", command])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .spawn()
+    {
+        Ok(mut child) => {
+            // Reap in the background, some actions (ie. opening a browser) run for long
+            let _ = thread::Builder::new().spawn(move || child.wait());
+        }
+        Err(err) => log::error!("Failed to run {command:?}: {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markup::Markup;
+
+    #[test]
+    fn test_pango() {
+        let rendered = format!(
+            "{} <&> {}",
+            Markup::new("I").fg(theme::Color::MainIcon).into_string(),
+            Markup::new("hot")
+                .fg(theme::Color::Critical)
+                .underline(theme::Color::Critical)
+                .into_string()
+        );
+        assert_eq!(
+            pango(&markup::parse(&rendered)),
+            "<span foreground=\"#f1e9d2\">I</span> &lt;&amp;&gt; <span foreground=\"#f23749\" underline=\"single\" underline_color=\"#f23749\">hot</span>"
+        );
+    }
+
+    #[test]
+    fn test_severity() {
+        assert_eq!(severity(&markup::parse("a")), None);
+        assert_eq!(
+            severity(&markup::parse(
+                &Markup::new("a").fg(theme::Color::MainIcon).into_string()
+            )),
+            None
+        );
+        assert_eq!(
+            severity(&markup::parse(&format!(
+                "{}{}",
+                Markup::new("a")
+                    .underline(theme::Color::Attention)
+                    .into_string(),
+                Markup::new("b").fg(theme::Color::Good).into_string()
+            ))),
+            Some(theme::Color::Attention)
+        );
+    }
+
+    #[test]
+    fn test_span_actions() {
+        let rendered = Markup::new(format!(
+            "{}b",
+            Markup::new("a")
+                .action(PolybarActionType::ClickLeft, "inner")
+                .into_string()
+        ))
+        .action(PolybarActionType::ClickLeft, "outer")
+        .action(PolybarActionType::ScrollUp, "up")
+        .into_string();
+        assert_eq!(
+            span_actions(&markup::parse(&rendered)),
+            vec![
+                (PolybarActionType::ClickLeft, "inner".to_owned()),
+                (PolybarActionType::ScrollUp, "up".to_owned()),
+            ]
+        );
+    }
+    #[test]
+    fn test_format_once() {
+        assert_eq!(format_once(Backend::Polybar, "test", "a<b", None), "a<b");
+        assert_eq!(
+            format_once(Backend::Waybar, "test", "a<b", Some("detail")),
+            r#"{"text":"a&lt;b","tooltip":"detail"}"#
+        );
+        assert!(
+            format_once(Backend::I3bar, "test", "a<b", None)
+                .starts_with(r#"[{"full_text":"a&lt;b","name":"test","#)
+        );
+    }
+}
@@ -0,0 +1,58 @@
+//! Polybar IPC output, for `custom/ipc` modules
+
+use std::{
+    process::{Command, Stdio},
+    sync::mpsc::{Receiver, RecvTimeoutError},
+    time::Duration,
+};
+
+use backon::BackoffBuilder as _;
+
+/// Publish outputs received from a module to the polybar `custom/ipc` module `name` of all running bars
+///
+/// If no bar can be reached (ie. not started yet), the last output is sent again later.
+pub(crate) fn publish(name: &str, outputs: &Receiver<String>) {
+    let backoff_builder = backon::ExponentialBuilder::new()
+        .with_factor(2.0)
+        .with_min_delay(Duration::from_millis(500))
+        .with_max_delay(Duration::from_secs(30))
+        .without_max_times();
+    let mut backoff = backoff_builder.build();
+    let mut pending: Option<(String, Duration)> = None;
+    loop {
+        let output = match pending.take() {
+            None => match outputs.recv() {
+                Ok(output) => output,
+                // Module is gone
+                Err(_) => return,
+            },
+            Some((output, retry_delay)) => match outputs.recv_timeout(retry_delay) {
+                Ok(new_output) => new_output,
+                Err(RecvTimeoutError::Timeout) => output,
+                Err(RecvTimeoutError::Disconnected) => return,
+            },
+        };
+        if let Err(err) = send(name, &output) {
+            let retry_delay = backoff.next().unwrap();
+            log::warn!(
+                "Failed to send output to polybar module {name:?}, retrying in {}ms: {err}",
+                retry_delay.as_millis()
+            );
+            pending = Some((output, retry_delay));
+        } else {
+            backoff = backoff_builder.build();
+        }
+    }
+}
+
+/// Set the content of a polybar `custom/ipc` module
+fn send(name: &str, output: &str) -> anyhow::Result<()> {
+    Command::new("polybar-msg")
+        .args(["action", name, "send", output])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?
+        .exit_ok()?;
+    Ok(())
+}
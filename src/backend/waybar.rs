@@ -0,0 +1,117 @@
+//! Waybar custom module JSON output, see <https://man.archlinux.org/man/waybar-custom.5>
+
+use std::{
+    fs,
+    io::{BufRead as _, BufReader},
+    path::Path,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use anyhow::Context as _;
+
+use super::{Actions, escape_pango, pango, run_action, severity, span_actions};
+use crate::{daemon, markup, theme};
+
+#[derive(Debug, serde::Serialize)]
+struct Output {
+    text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tooltip: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    class: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    percentage: Option<u8>,
+}
+
+/// Convert rendered module output and its detail to a Waybar JSON line, and get its click actions
+pub(crate) fn convert(output: &str, detail: Option<&str>) -> (String, Actions) {
+    let spans = markup::parse(output);
+    let waybar_output = Output {
+        text: pango(&spans),
+        // Tooltips are Pango markup too
+        tooltip: detail.map(escape_pango),
+        class: severity(&spans).map(|color| match color {
+            theme::Color::Good => "good",
+            theme::Color::Notice => "notice",
+            theme::Color::Attention => "attention",
+            _ => "critical",
+        }),
+        percentage: percentage(&spans),
+    };
+    let json = serde_json::to_string(&waybar_output).unwrap_or_default();
+    (json, span_actions(&spans))
+}
+
+/// Level of the first ramp in spans, as a percentage, for Waybar format icons
+fn percentage(spans: &[markup::Span]) -> Option<u8> {
+    spans.iter().find_map(|s| {
+        let level = markup::RAMP_ICONS.iter().position(|i| *i == s.text)? + 1;
+        u8::try_from(level * 100 / markup::RAMP_ICONS.len()).ok()
+    })
+}
+
+/// Spawn thread reading mouse button numbers written to the `<name>.click` named pipe, and running the matching
+/// action
+///
+/// Waybar does not forward clicks to custom modules, so its `on-click*` settings need to write there, ie.
+/// `echo 1 > $XDG_RUNTIME_DIR/polybar-modules/<name>.click`.
+pub(crate) fn spawn_click_reader(name: &str, actions: Arc<Mutex<Actions>>) -> anyhow::Result<()> {
+    let xdg_dirs = xdg::BaseDirectories::with_prefix(env!("CARGO_PKG_NAME"));
+    let click_filepath = xdg_dirs.place_runtime_file(format!("{name}.click"))?;
+    daemon::create_fifo(&click_filepath)
+        .with_context(|| format!("Failed to create named pipe {click_filepath:?}"))?;
+    thread::Builder::new()
+        .name(format!("{name}-clicks"))
+        .spawn(move || {
+            if let Err(err) = read_clicks(&click_filepath, &actions) {
+                log::error!("Failed to read clicks from {click_filepath:?}: {err}");
+            }
+        })?;
+    Ok(())
+}
+
+fn read_clicks(path: &Path, actions: &Mutex<Actions>) -> anyhow::Result<()> {
+    loop {
+        // Blocks until a writer shows up, and we get EOF when it is done
+        let reader = BufReader::new(fs::File::open(path)?);
+        for line in reader.lines() {
+            let line = line?;
+            match line.trim().parse::<u8>() {
+                Ok(button) => run_action(&actions.lock().unwrap(), button),
+                Err(err) => log::warn!("Invalid mouse button {line:?}: {err}"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markup::{Markup, PolybarActionType};
+
+    #[test]
+    fn test_convert() {
+        assert_eq!(convert("", None), ("{\"text\":\"\"}".to_owned(), vec![]));
+        assert_eq!(
+            convert("a", Some("<b> & c")).0,
+            "{\"text\":\"a\",\"tooltip\":\"&lt;b&gt; &amp; c\"}"
+        );
+
+        let output = format!(
+            "{} 50% {}",
+            Markup::new("C")
+                .fg(theme::Color::MainIcon)
+                .action(PolybarActionType::ClickLeft, "cmd")
+                .into_string(),
+            markup::ramp(0.5, theme::Color::Notice)
+        );
+        assert_eq!(
+            convert(&output, None),
+            (
+                "{\"text\":\"<span foreground=\\\"#f1e9d2\\\">C</span> 50% <span foreground=\\\"#ac8300\\\">▄</span>\",\"class\":\"notice\",\"percentage\":50}".to_owned(),
+                vec![(PolybarActionType::ClickLeft, "cmd".to_owned())]
+            )
+        );
+    }
+}
@@ -0,0 +1,243 @@
+//! Module control, commands sent by `ctl` subcommand clients to running modules over a Unix socket
+
+use std::{
+    fmt, fs,
+    io::{self, BufRead as _, BufReader, Read as _, Write as _},
+    iter,
+    net::Shutdown,
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+};
+
+use anyhow::Context as _;
+use clap::Parser as _;
+
+/// Command handled by a running module
+#[derive(Clone, Debug, Eq, PartialEq, clap::Parser)]
+#[expect(non_camel_case_types)]
+pub(crate) enum ControlCommand {
+    /// Update module now
+    refresh,
+    /// Toggle what the module controls, ie. the autolock service
+    toggle,
+    /// Set default audio output
+    select_sink { id: u32 },
+    /// Set default audio input
+    select_source { id: u32 },
+    /// Connect bluetooth device
+    connect_device { addr: macaddr::MacAddr6 },
+    /// Disconnect bluetooth device
+    disconnect_device { addr: macaddr::MacAddr6 },
+}
+
+impl fmt::Display for ControlCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::refresh => write!(f, "refresh"),
+            Self::toggle => write!(f, "toggle"),
+            Self::select_sink { id } => write!(f, "select-sink {id}"),
+            Self::select_source { id } => write!(f, "select-source {id}"),
+            Self::connect_device { addr } => write!(f, "connect-device {addr}"),
+            Self::disconnect_device { addr } => write!(f, "disconnect-device {addr}"),
+        }
+    }
+}
+
+/// Function handling the control commands of a module
+pub(crate) type Handler = Box<dyn FnMut(&ControlCommand) -> anyhow::Result<()> + Send>;
+
+/// Shell command sending `command` to running module `name`, to use as a click action
+pub(crate) fn action(name: &str, command: &ControlCommand) -> String {
+    format!("{} ctl {name} {command}", env!("CARGO_PKG_NAME"))
+}
+
+/// Error for a command a module does not handle
+pub(crate) fn unsupported(command: &ControlCommand) -> anyhow::Error {
+    anyhow::anyhow!("Unsupported command {:?}", command.to_string())
+}
+
+fn socket_filepath(name: &str) -> anyhow::Result<PathBuf> {
+    let xdg_dirs = xdg::BaseDirectories::with_prefix(env!("CARGO_PKG_NAME"));
+    Ok(xdg_dirs.place_runtime_file(format!("{name}.control.socket"))?)
+}
+
+/// Server of the control commands of a module, kept when the module is rebuilt
+pub(crate) struct Server {
+    name: String,
+    handler: Arc<Mutex<Option<Handler>>>,
+    /// Whether the socket is bound and served
+    listening: AtomicBool,
+}
+
+impl Server {
+    /// Server of module `name`, that only binds its socket once a module has a handler
+    pub(crate) fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            handler: Arc::new(Mutex::new(None)),
+            listening: AtomicBool::new(false),
+        }
+    }
+
+    /// Handle commands with `handler` from now on, replacing the one of the previous module instance
+    pub(crate) fn set_handler(&self, handler: Option<Handler>) -> anyhow::Result<()> {
+        let has_handler = handler.is_some();
+        *self.handler.lock().unwrap() = handler;
+        if has_handler && !self.listening.swap(true, Ordering::SeqCst) {
+            self.spawn()
+                .inspect_err(|_| self.listening.store(false, Ordering::SeqCst))?;
+        }
+        Ok(())
+    }
+
+    /// Spawn thread serving the control commands
+    fn spawn(&self) -> anyhow::Result<()> {
+        let socket_filepath = socket_filepath(&self.name)?;
+        let listener = bind(&socket_filepath)
+            .with_context(|| format!("Failed to bind socket {socket_filepath:?}"))?;
+        let handler = Arc::clone(&self.handler);
+        thread::Builder::new()
+            .name(format!("{}-control", self.name))
+            .spawn(move || serve(&listener, &handler))?;
+        Ok(())
+    }
+}
+
+/// Bind listening socket at `path`, replacing any stale one
+fn bind(path: &Path) -> anyhow::Result<UnixListener> {
+    if let Err(err) = fs::remove_file(path)
+        && err.kind() != io::ErrorKind::NotFound
+    {
+        return Err(err.into());
+    }
+    Ok(UnixListener::bind(path)?)
+}
+
+/// Handle commands, one per connection, replying with an error message, or nothing if it succeeded
+fn serve(listener: &UnixListener, handler: &Mutex<Option<Handler>>) {
+    for stream in listener.incoming() {
+        let res = stream.and_then(|stream| {
+            let mut line = String::new();
+            BufReader::new(&stream).read_line(&mut line)?;
+            let res =
+                ControlCommand::try_parse_from(iter::once("ctl").chain(line.split_whitespace()))
+                    .map_err(anyhow::Error::from)
+                    .and_then(|command| {
+                        log::debug!("Control command: {command:?}");
+                        match handler.lock().unwrap().as_mut() {
+                            Some(handler) => handler(&command),
+                            // Module instance rebuilt without one
+                            None => Err(anyhow::anyhow!("Module does not handle commands")),
+                        }
+                    });
+            match res {
+                Ok(()) => Ok(()),
+                Err(err) => (&stream).write_all(format!("{err:#}").as_bytes()),
+            }
+        });
+        if let Err(err) = res {
+            log::warn!("Failed to serve control command: {err}");
+        }
+    }
+}
+
+/// Send `command` to running module `name`
+pub(crate) fn send(name: &str, command: &ControlCommand) -> anyhow::Result<()> {
+    let socket_filepath = socket_filepath(name)?;
+    let mut stream = UnixStream::connect(&socket_filepath)
+        .with_context(|| format!("Module {name:?} is not running"))?;
+    stream.write_all(format!("{command}\n").as_bytes())?;
+    stream.shutdown(Shutdown::Write)?;
+    let mut err = String::new();
+    stream.read_to_string(&mut err)?;
+    anyhow::ensure!(
+        err.is_empty(),
+        "Module {name:?} failed to handle command: {err}"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr as _, sync::mpsc::channel};
+
+    use super::*;
+
+    #[test]
+    fn test_command_roundtrip() {
+        for command in [
+            ControlCommand::refresh,
+            ControlCommand::toggle,
+            ControlCommand::select_sink { id: 42 },
+            ControlCommand::select_source { id: 0 },
+            ControlCommand::connect_device {
+                addr: macaddr::MacAddr6::from_str("01:23:45:67:89:AB").unwrap(),
+            },
+            ControlCommand::disconnect_device {
+                addr: macaddr::MacAddr6::from_str("01:23:45:67:89:AB").unwrap(),
+            },
+        ] {
+            let s = command.to_string();
+            assert_eq!(
+                ControlCommand::try_parse_from(iter::once("ctl").chain(s.split_whitespace()))
+                    .unwrap(),
+                command
+            );
+        }
+        assert!(ControlCommand::try_parse_from(["ctl", "select-sink", "speakers"]).is_err());
+        assert!(ControlCommand::try_parse_from(["ctl", "connect-device", "01:23"]).is_err());
+        assert!(ControlCommand::try_parse_from(["ctl", "explode"]).is_err());
+    }
+
+    #[test]
+    fn test_action() {
+        assert_eq!(
+            action("pulseaudio", &ControlCommand::select_sink { id: 3 }),
+            "polybar-modules ctl pulseaudio select-sink 3"
+        );
+    }
+
+    #[test]
+    fn test_serve() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_filepath = dir.path().join("module.control.socket");
+        let listener = bind(&socket_filepath).unwrap();
+        let (commands_tx, commands_rx) = channel();
+        let handler: Handler = Box::new(move |command| match command {
+            ControlCommand::refresh => {
+                commands_tx.send(command.clone()).unwrap();
+                Ok(())
+            }
+            _ => Err(unsupported(command)),
+        });
+        let handler = Arc::new(Mutex::new(Some(handler)));
+        thread::spawn({
+            let handler = Arc::clone(&handler);
+            move || serve(&listener, &handler)
+        });
+
+        let request = |line: &str| {
+            let mut stream = UnixStream::connect(&socket_filepath).unwrap();
+            stream.write_all(line.as_bytes()).unwrap();
+            stream.shutdown(Shutdown::Write).unwrap();
+            let mut s = String::new();
+            stream.read_to_string(&mut s).unwrap();
+            s
+        };
+        assert_eq!(request("refresh\n"), "");
+        assert_eq!(commands_rx.recv().unwrap(), ControlCommand::refresh);
+        assert_eq!(request("toggle\n"), "Unsupported command \"toggle\"");
+        assert!(!request("select-sink speakers\n").is_empty());
+        assert!(commands_rx.try_recv().is_err());
+
+        // Handler of a rebuilt module replaces the previous one
+        *handler.lock().unwrap() = None;
+        assert_eq!(request("refresh\n"), "Module does not handle commands");
+        assert!(commands_rx.try_recv().is_err());
+    }
+}
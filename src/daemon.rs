@@ -0,0 +1,134 @@
+//! Daemon mode, running several modules in a single process
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write as _},
+    iter,
+    path::Path,
+    sync::mpsc::Receiver,
+};
+
+use clap::Parser as _;
+
+use crate::config::PolybarModuleName;
+
+/// Parse a module specification as passed to the daemon, ie. a module subcommand with its arguments,
+/// and return its name along with it
+pub(crate) fn parse_module_spec(spec: &str) -> anyhow::Result<(String, PolybarModuleName)> {
+    let module = PolybarModuleName::try_parse_from(
+        iter::once(env!("CARGO_PKG_NAME")).chain(spec.split_whitespace()),
+    )?;
+    let name = spec
+        .split_whitespace()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Empty module specification"))?
+        .to_owned();
+    Ok((name, module))
+}
+
+/// Create a named pipe at `path`, replacing any stale one
+pub(crate) fn create_fifo(path: &Path) -> anyhow::Result<()> {
+    if let Err(err) = fs::remove_file(path)
+        && err.kind() != io::ErrorKind::NotFound
+    {
+        return Err(err.into());
+    }
+    rustix::fs::mknodat(
+        rustix::fs::CWD,
+        path,
+        rustix::fs::FileType::Fifo,
+        rustix::fs::Mode::RUSR | rustix::fs::Mode::WUSR,
+        0,
+    )?;
+    Ok(())
+}
+
+/// Publish outputs received from a module to the named pipe at `path`, one per line
+///
+/// Opening the pipe blocks until a reader shows up. When the reader goes away (ie. the bar was restarted), the last
+/// output is published again to the next one.
+pub(crate) fn publish_fifo(path: &Path, outputs: &Receiver<String>) -> anyhow::Result<()> {
+    let mut last_output: Option<String> = None;
+    loop {
+        log::debug!("Waiting for reader on {path:?}");
+        let mut fifo = OpenOptions::new().write(true).open(path)?;
+        // Only the most recent output is relevant to a new reader
+        if let Some(output) = outputs.try_iter().last() {
+            last_output = Some(output);
+        }
+        loop {
+            if let Some(output) = &last_output {
+                // Single write, so a line is never split between two readers
+                match fifo.write_all(format!("{output}\n").as_bytes()) {
+                    Ok(()) => {}
+                    Err(err) if err.kind() == io::ErrorKind::BrokenPipe => break,
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            match outputs.recv() {
+                Ok(output) => last_output = Some(output),
+                // Module is gone
+                Err(_) => return Ok(()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::shadow_unrelated)]
+mod tests {
+    use std::{
+        io::{BufRead as _, BufReader},
+        sync::mpsc::channel,
+        thread,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_parse_module_spec() {
+        let (name, module) = parse_module_spec("cpu-freq").unwrap();
+        assert_eq!(name, "cpu-freq");
+        assert!(matches!(module, PolybarModuleName::cpu_freq));
+
+        let (name, module) = parse_module_spec(" cpu-top  30 ").unwrap();
+        assert_eq!(name, "cpu-top");
+        assert!(matches!(
+            module,
+            PolybarModuleName::cpu_top { max_len: Some(30) }
+        ));
+
+        assert!(parse_module_spec("").is_err());
+        assert!(parse_module_spec("not-a-module").is_err());
+        assert!(parse_module_spec("player not_a_number").is_err());
+    }
+
+    #[test]
+    fn test_publish_fifo() {
+        let dir = tempfile::tempdir().unwrap();
+        let fifo_filepath = dir.path().join("module.fifo");
+        create_fifo(&fifo_filepath).unwrap();
+
+        let (outputs_tx, outputs_rx) = channel();
+        outputs_tx.send("a".to_owned()).unwrap();
+        outputs_tx.send("b".to_owned()).unwrap();
+        let writer = thread::spawn({
+            let fifo_filepath = fifo_filepath.clone();
+            move || publish_fifo(&fifo_filepath, &outputs_rx)
+        });
+
+        // Only the latest output is published to a new reader
+        let mut reader = BufReader::new(fs::File::open(&fifo_filepath).unwrap());
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "b\n");
+
+        outputs_tx.send("c".to_owned()).unwrap();
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "c\n");
+
+        drop(outputs_tx);
+        writer.join().unwrap().unwrap();
+    }
+}
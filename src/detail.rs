@@ -0,0 +1,93 @@
+//! Detailed module output, served by running modules to `detail` subcommand clients over a Unix socket
+
+use std::{
+    fs,
+    io::{self, Read as _, Write as _},
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use anyhow::Context as _;
+
+/// Latest detail rendered by a module
+pub(crate) type Detail = Arc<Mutex<Option<String>>>;
+
+fn socket_filepath(name: &str) -> anyhow::Result<PathBuf> {
+    let xdg_dirs = xdg::BaseDirectories::with_prefix(env!("CARGO_PKG_NAME"));
+    Ok(xdg_dirs.place_runtime_file(format!("{name}.detail.socket"))?)
+}
+
+/// Spawn thread serving the detail of module `name`, and return the handle to update it
+pub(crate) fn spawn_server(name: &str) -> anyhow::Result<Detail> {
+    let socket_filepath = socket_filepath(name)?;
+    let listener = bind(&socket_filepath)
+        .with_context(|| format!("Failed to bind socket {socket_filepath:?}"))?;
+    let detail = Detail::default();
+    let server_detail = Arc::clone(&detail);
+    thread::Builder::new()
+        .name(format!("{name}-detail"))
+        .spawn(move || serve(&listener, &server_detail))?;
+    Ok(detail)
+}
+
+/// Bind listening socket at `path`, replacing any stale one
+fn bind(path: &Path) -> anyhow::Result<UnixListener> {
+    if let Err(err) = fs::remove_file(path)
+        && err.kind() != io::ErrorKind::NotFound
+    {
+        return Err(err.into());
+    }
+    Ok(UnixListener::bind(path)?)
+}
+
+fn serve(listener: &UnixListener, detail: &Mutex<Option<String>>) {
+    for stream in listener.incoming() {
+        let res = stream.and_then(|mut stream| {
+            let cur_detail = detail.lock().unwrap().clone();
+            stream.write_all(cur_detail.unwrap_or_default().as_bytes())
+        });
+        if let Err(err) = res {
+            log::warn!("Failed to serve detail: {err}");
+        }
+    }
+}
+
+/// Get the current detail of running module `name`
+pub(crate) fn query(name: &str) -> anyhow::Result<String> {
+    let socket_filepath = socket_filepath(name)?;
+    let mut stream = UnixStream::connect(&socket_filepath)
+        .with_context(|| format!("Module {name:?} is not running"))?;
+    let mut detail = String::new();
+    stream.read_to_string(&mut detail)?;
+    anyhow::ensure!(!detail.is_empty(), "Module {name:?} has no detail");
+    Ok(detail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serve() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_filepath = dir.path().join("module.detail.socket");
+        let listener = bind(&socket_filepath).unwrap();
+        let detail = Detail::default();
+        let server_detail = Arc::clone(&detail);
+        thread::spawn(move || serve(&listener, &server_detail));
+
+        let read = || {
+            let mut s = String::new();
+            UnixStream::connect(&socket_filepath)
+                .unwrap()
+                .read_to_string(&mut s)
+                .unwrap();
+            s
+        };
+        assert_eq!(read(), "");
+        *detail.lock().unwrap() = Some("a\nb".to_owned());
+        assert_eq!(read(), "a\nb");
+    }
+}
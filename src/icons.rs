@@ -0,0 +1,221 @@
+//! Icon registry, rendering each icon with the glyph of the selected icon set
+
+use std::{collections::BTreeMap, fmt, sync::RwLock};
+
+use crate::config::IconSet;
+
+/// Icons, named in config by their kebab-case variant name (ie. `player-playing`)
+#[derive(
+    Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, serde::Deserialize, serde::Serialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Icon {
+    Warning,
+    Update,
+    AutolockEnabled,
+    AutolockDisabled,
+    BluetoothEnabled,
+    BluetoothDisabled,
+    Chatgpt,
+    Claude,
+    Gpu,
+    InferenceUsage,
+    MarketDown,
+    MarketUp,
+    Microphone,
+    Mouse,
+    Mpv,
+    Network,
+    NetworkLowBandwidth,
+    NetworkNormal,
+    NetworkVpn,
+    NotificationsDisabled,
+    NotificationsEnabled,
+    AudioEffects,
+    Player,
+    PlayerPaused,
+    PlayerPlaying,
+    PlayerStopped,
+    Power,
+    PowerFlowLeft,
+    PowerFlowRight,
+    PowerGrid,
+    PowerHome,
+    PowerSolar,
+    ProgressbarServer,
+    Syncthing,
+    SyncthingDevice,
+    SyncthingDownloading,
+    SyncthingFolder,
+    SyncthingUploading,
+    Todotxt,
+    TodotxtHidden,
+    Unauthorized,
+    AlertCircle,
+    HourglassStart,
+    CircleSlice1,
+    CircleSlice2,
+    CircleSlice3,
+    CircleSlice4,
+    CircleSlice5,
+    CircleSlice6,
+    CircleSlice7,
+    CircleSlice8,
+    WeatherCloudy,
+    WeatherFog,
+    WeatherHail,
+    WeatherLightningRainy,
+    WeatherNight,
+    WeatherNightPartlyCloudy,
+    WeatherPartlyCloudy,
+    WeatherPouring,
+    WeatherRainy,
+    WeatherSnowy,
+    WeatherSnowyHeavy,
+    WeatherSnowyRainy,
+    WeatherSunny,
+    WeatherWindy,
+}
+
+impl Icon {
+    /// Nerd Font, Unicode and ASCII glyphs
+    const fn glyphs(self) -> [&'static str; 3] {
+        match self {
+            Self::Warning => ["", "⚠", "!"], // nf-fa-warning
+            Self::Update => ["", "↻", "U"],
+            Self::AutolockEnabled => ["󱫗", "🔒", "L"],
+            Self::AutolockDisabled => ["󱫕", "🔓", "l"],
+            Self::BluetoothEnabled => ["󰂯", "ᛒ", "B"],
+            Self::BluetoothDisabled => ["󰂲", "⊘", "b"],
+            Self::Chatgpt => ["󰫈", "◎", "O"],
+            Self::Claude => ["", "✳", "C"],
+            Self::Gpu => ["", "▦", "G"],
+            Self::InferenceUsage => ["󱩅", "✦", "I"],
+            Self::MarketDown => ["", "▼", "-"],
+            Self::MarketUp => ["", "▲", "+"],
+            Self::Microphone => ["", "🎤", "m"],
+            Self::Mouse => ["󰍽", "🖱", "M"],
+            Self::Mpv => ["", "▷", "mpv"],
+            Self::Network => ["", "🖧", "N"],
+            Self::NetworkLowBandwidth => ["󰅛", "⇣", "n"],
+            Self::NetworkNormal => ["󰲝", "⇅", "N"],
+            Self::NetworkVpn => ["󰒃", "🛡", "V"],
+            Self::NotificationsDisabled => ["󰚣", "🔕", "n"],
+            Self::NotificationsEnabled => ["󰍩", "💬", "N"],
+            Self::AudioEffects => ["󰋍", "≋", "~"],
+            Self::Player => ["", "♫", "M"],
+            Self::PlayerPaused => ["", "⏸", "="],
+            Self::PlayerPlaying => ["", "▶", ">"],
+            Self::PlayerStopped => ["", "⏹", "#"],
+            Self::Power => ["", "⚡", "P"],
+            Self::PowerFlowLeft => ["", "←", "<"],
+            Self::PowerFlowRight => ["", "→", ">"],
+            Self::PowerGrid => ["󰴾", "⏚", "G"],
+            Self::PowerHome => ["󱤃", "⌂", "H"],
+            Self::PowerSolar => ["", "☀", "S"],
+            Self::ProgressbarServer => ["", "⧗", "P"],
+            Self::Syncthing => ["󱋖", "⟲", "S"],
+            Self::SyncthingDevice => ["", "🖳", "D"],
+            Self::SyncthingDownloading => ["", "↓", "v"],
+            Self::SyncthingFolder => ["", "🗀", "F"],
+            Self::SyncthingUploading => ["", "↑", "^"],
+            Self::Todotxt => ["", "☑", "T"],
+            Self::TodotxtHidden => ["", "☐", "t"],
+            Self::Unauthorized => ["", "✗", "x"],
+            Self::AlertCircle => ["󰗖", "⊘", "!"], // nf-md-alert_circle_outline
+            Self::HourglassStart => ["", "⧗", "."], // nf-fa-hourglass_start
+            Self::CircleSlice1 => ["󰪞", "◔", "1"], // nf-md-circle_slice_1
+            Self::CircleSlice2 => ["󰪟", "◔", "2"], // nf-md-circle_slice_2
+            Self::CircleSlice3 => ["󰪠", "◑", "3"], // nf-md-circle_slice_3
+            Self::CircleSlice4 => ["󰪡", "◑", "4"], // nf-md-circle_slice_4
+            Self::CircleSlice5 => ["󰪢", "◕", "5"], // nf-md-circle_slice_5
+            Self::CircleSlice6 => ["󰪣", "◕", "6"], // nf-md-circle_slice_6
+            Self::CircleSlice7 => ["󰪤", "●", "7"], // nf-md-circle_slice_7
+            Self::CircleSlice8 => ["󰪥", "●", "8"], // nf-md-circle_slice_8
+            Self::WeatherCloudy => ["󰖐", "☁", "c"], // nf-md-weather_cloudy
+            Self::WeatherFog => ["󰖑", "🌫", "="],  // nf-md-weather_fog
+            Self::WeatherHail => ["󰖒", "🌨", "h"], // nf-md-weather_hail
+            Self::WeatherLightningRainy => ["󰙾", "⛈", "!"], // nf-md-weather_lightning_rainy
+            Self::WeatherNight => ["󰖔", "☾", "C"], // nf-md-weather_night
+            Self::WeatherNightPartlyCloudy => ["󰼱", "☁", "c"], // nf-md-weather_night_partly_cloudy
+            Self::WeatherPartlyCloudy => ["󰖕", "⛅", "c"], // nf-md-weather_partly_cloudy
+            Self::WeatherPouring => ["󰖖", "🌧", "R"], // nf-md-weather_pouring
+            Self::WeatherRainy => ["󰖗", "🌧", "r"], // nf-md-weather_rainy
+            Self::WeatherSnowy => ["󰖘", "❄", "s"], // nf-md-weather_snowy
+            Self::WeatherSnowyHeavy => ["󰼶", "❄", "S"], // nf-md-weather_snowy_heavy
+            Self::WeatherSnowyRainy => ["󰙿", "🌨", "s"], // nf-md-weather_snowy_rainy
+            Self::WeatherSunny => ["󰖙", "☀", "*"], // nf-md-weather_sunny
+            Self::WeatherWindy => ["󰖝", "🌬", "~"], // nf-md-weather_windy
+        }
+    }
+}
+
+impl fmt::Display for Icon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(ICON_THEME.read().unwrap().glyph(*self))
+    }
+}
+
+impl From<Icon> for String {
+    fn from(icon: Icon) -> Self {
+        icon.to_string()
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct IconTheme {
+    set: IconSet,
+    overrides: BTreeMap<Icon, String>,
+}
+
+impl IconTheme {
+    pub(crate) const fn new(set: IconSet, overrides: BTreeMap<Icon, String>) -> Self {
+        Self { set, overrides }
+    }
+
+    fn glyph(&self, icon: Icon) -> &str {
+        if let Some(glyph) = self.overrides.get(&icon) {
+            return glyph;
+        }
+        let [nerdfont, unicode, ascii] = icon.glyphs();
+        match self.set {
+            IconSet::Nerdfont => nerdfont,
+            IconSet::Unicode => unicode,
+            IconSet::Ascii => ascii,
+        }
+    }
+}
+
+/// Icon theme icons are rendered with, can be changed at runtime
+static ICON_THEME: RwLock<IconTheme> =
+    RwLock::new(IconTheme::new(IconSet::Nerdfont, BTreeMap::new()));
+
+/// Make `theme` the one icons are rendered with
+pub(crate) fn set_icon_theme(theme: IconTheme) {
+    log::debug!("{theme:?}");
+    *ICON_THEME.write().unwrap() = theme;
+}
+
+#[cfg(test)]
+#[expect(clippy::shadow_unrelated)]
+mod tests {
+    use super::*;
+    use crate::config::IconsConfig;
+
+    #[test]
+    fn test_glyph() {
+        let cfg: IconsConfig =
+            toml::from_str("set = \"ascii\"\n[overrides]\nplayer-playing = \"P\"\n").unwrap();
+        let theme = IconTheme::new(cfg.set.unwrap(), cfg.overrides);
+        assert_eq!(theme.glyph(Icon::PlayerPlaying), "P");
+        assert_eq!(theme.glyph(Icon::PlayerPaused), "=");
+
+        let theme = IconTheme::new(IconSet::Unicode, BTreeMap::new());
+        assert_eq!(theme.glyph(Icon::Warning), "⚠");
+
+        let theme = IconTheme::new(IconSet::Nerdfont, BTreeMap::new());
+        assert_eq!(theme.glyph(Icon::Warning), "\u{f071}");
+
+        assert!(toml::from_str::<IconsConfig>("[overrides]\nnot-an-icon = \"?\"\n").is_err());
+    }
+}
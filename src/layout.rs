@@ -0,0 +1,401 @@
+//! Text layout in display columns, as the bar renders it
+
+use std::{
+    cell::RefCell,
+    time::{Duration, Instant},
+};
+
+use unicode_segmentation::UnicodeSegmentation as _;
+use unicode_width::UnicodeWidthStr as _;
+
+use crate::{config::MarqueeConfig, markup};
+
+/// Priority of fragments that are never dropped
+pub(crate) const REQUIRED: u8 = u8::MAX;
+
+/// Display width of the visible text of `s`, in columns, ignoring polybar formatting tags
+pub(crate) fn width(s: &str) -> usize {
+    markup::parse(s)
+        .iter()
+        .map(|span| text_width(&span.text))
+        .sum()
+}
+
+/// Display width of plain text `s`, in columns
+fn text_width(s: &str) -> usize {
+    s.graphemes(true).map(str::width).sum()
+}
+
+/// Truncate plain text `s` to `max_width` columns, ending it with an ellipsis if it was cut
+pub(crate) fn ellipsis(s: &str, max_width: Option<usize>) -> String {
+    let Some(max_width) = max_width else {
+        return s.to_owned();
+    };
+    if text_width(s) <= max_width {
+        return s.to_owned();
+    }
+    let s = s.trim_end();
+    if text_width(s) <= max_width {
+        return s.to_owned();
+    }
+    let mut truncated = String::new();
+    let mut truncated_width = 0;
+    for grapheme in s.graphemes(true) {
+        let grapheme_width = grapheme.width();
+        // Keep a column for the ellipsis
+        if truncated_width + grapheme_width >= max_width {
+            break;
+        }
+        truncated.push_str(grapheme);
+        truncated_width += grapheme_width;
+    }
+    let mut truncated = truncated.trim_end().to_owned();
+    truncated.push('…');
+    truncated
+}
+
+/// Pad `s` with spaces on the left to `min_width` columns
+pub(crate) fn pad(s: &str, min_width: Option<usize>) -> String {
+    let pad_count = min_width.map_or(0, |m| m.saturating_sub(width(s)));
+    format!("{}{s}", " ".repeat(pad_count))
+}
+
+/// Fragments laid out in a maximum width, dropping the least important ones first when they do not fit
+#[derive(Default)]
+pub(crate) struct Layout {
+    /// Separators of the fragments in each group
+    group_seps: Vec<String>,
+    fragments: Vec<Fragment>,
+}
+
+struct Fragment {
+    group: usize,
+    /// Text, which may have markup
+    text: String,
+    priority: u8,
+}
+
+impl Layout {
+    /// Start a group of fragments joined by `sep`, groups themselves are joined by a space
+    pub(crate) fn group<S>(mut self, sep: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.group_seps.push(sep.into());
+        self
+    }
+
+    /// Add `text` to the last group, the fragments with the lowest `priority` are dropped first, empty ones
+    /// are ignored
+    pub(crate) fn fragment<S>(mut self, text: S, priority: u8) -> Self
+    where
+        S: Into<String>,
+    {
+        let text = text.into();
+        if text.is_empty() {
+            return self;
+        }
+        if self.group_seps.is_empty() {
+            self = self.group(" ");
+        }
+        self.fragments.push(Fragment {
+            group: self.group_seps.len() - 1,
+            text,
+            priority,
+        });
+        self
+    }
+
+    /// Render in at most `max_width` columns
+    ///
+    /// If the [`REQUIRED`] fragments alone do not fit, the last one, which must be plain text, is cut.
+    pub(crate) fn render(&self, max_width: Option<usize>) -> String {
+        self.render_with(max_width, |s, w| ellipsis(s, Some(w)))
+    }
+
+    /// Render in at most `max_width` columns, like [`Self::render`], but fitting the last fragment with
+    /// `fit(text, width)` if needed
+    pub(crate) fn render_with<F>(&self, max_width: Option<usize>, fit: F) -> String
+    where
+        F: Fn(&str, usize) -> String,
+    {
+        let mut kept: Vec<&Fragment> = self.fragments.iter().collect();
+        loop {
+            let rendered = self.join(kept.iter().map(|f| (f.group, f.text.as_str())));
+            let Some(max_width) = max_width else {
+                return rendered;
+            };
+            let rendered_width = width(&rendered);
+            if rendered_width <= max_width {
+                return rendered;
+            }
+            let to_drop = kept
+                .iter()
+                .enumerate()
+                .filter(|(_, f)| f.priority != REQUIRED)
+                .min_by_key(|(_, f)| f.priority)
+                .map(|(i, _)| i);
+            if let Some(to_drop) = to_drop {
+                kept.remove(to_drop);
+                continue;
+            }
+            let Some((last, others)) = kept.split_last() else {
+                return rendered;
+            };
+            let last_max_width = max_width.saturating_sub(rendered_width - width(&last.text));
+            let last_text = fit(&last.text, last_max_width);
+            return self.join(
+                others
+                    .iter()
+                    .map(|f| (f.group, f.text.as_str()))
+                    .chain([(last.group, last_text.as_str())]),
+            );
+        }
+    }
+
+    /// Whether the [`REQUIRED`] fragments alone do not fit in `max_width` columns
+    pub(crate) fn overflows(&self, max_width: usize) -> bool {
+        let required = self.join(
+            self.fragments
+                .iter()
+                .filter(|f| f.priority == REQUIRED)
+                .map(|f| (f.group, f.text.as_str())),
+        );
+        width(&required) > max_width
+    }
+
+    /// Join fragment texts, with the separator of their group, or a space between groups
+    fn join<'a, I>(&self, fragments: I) -> String
+    where
+        I: Iterator<Item = (usize, &'a str)>,
+    {
+        let mut joined = String::new();
+        let mut prev_group = None;
+        for (group, text) in fragments {
+            match prev_group {
+                Some(prev_group) if prev_group == group => {
+                    joined.push_str(self.group_seps.get(group).map_or(" ", String::as_str));
+                }
+                Some(_) => joined.push(' '),
+                None => {}
+            }
+            joined.push_str(text);
+            prev_group = Some(group);
+        }
+        joined
+    }
+}
+
+/// Scrolling window over text too long to fit, pausing at its start and end
+pub(crate) struct Marquee {
+    cfg: MarqueeConfig,
+    /// Text last shown, and when it was first
+    shown: RefCell<Option<(String, Instant)>>,
+}
+
+impl Marquee {
+    /// Build marquee from `cfg`, if enabled
+    pub(crate) fn new(cfg: &MarqueeConfig) -> Option<Self> {
+        cfg.enabled.then(|| Self {
+            cfg: cfg.clone(),
+            shown: RefCell::new(None),
+        })
+    }
+
+    /// Delay between scrolling steps of one column
+    pub(crate) fn step(&self) -> Duration {
+        Duration::from_secs(1) / self.cfg.speed_cps.get()
+    }
+
+    /// Window of `max_width` columns over plain text `s`, at the current scrolling position
+    pub(crate) fn window(&self, s: &str, max_width: usize) -> String {
+        let s_width = text_width(s);
+        if s_width <= max_width {
+            return s.to_owned();
+        }
+        let now = Instant::now();
+        let mut shown = self.shown.borrow_mut();
+        let start = match &*shown {
+            Some((shown_text, start)) if shown_text == s => *start,
+            _ => {
+                *shown = Some((s.to_owned(), now));
+                now
+            }
+        };
+        let offset = self.offset(s_width - max_width, now.duration_since(start));
+        scroll_window(s, offset, max_width)
+    }
+
+    /// Scrolling offset in columns, `elapsed` after the text was first shown
+    fn offset(&self, max_offset: usize, elapsed: Duration) -> usize {
+        let step_ms = self.step().as_millis().max(1);
+        let pause_ms = u128::from(self.cfg.pause_s) * 1000;
+        let scroll_ms = step_ms * max_offset as u128;
+        let t = elapsed.as_millis() % (2 * pause_ms + scroll_ms).max(1);
+        if t < pause_ms {
+            0
+        } else if t < pause_ms + scroll_ms {
+            ((t - pause_ms) / step_ms) as usize + 1
+        } else {
+            max_offset
+        }
+    }
+}
+
+/// Window of `max_width` columns over plain text `s`, starting `offset` columns in
+fn scroll_window(s: &str, offset: usize, max_width: usize) -> String {
+    let mut window = String::new();
+    let mut col = 0;
+    let mut window_width = 0;
+    for grapheme in s.graphemes(true) {
+        let grapheme_width = grapheme.width();
+        let grapheme_col = col;
+        col += grapheme_width;
+        if col <= offset {
+            continue;
+        }
+        if grapheme_col < offset || window_width + grapheme_width > max_width {
+            // Wide character cut by the window edge
+            let visible_width = (col - offset.max(grapheme_col)).min(max_width - window_width);
+            window.push_str(&" ".repeat(visible_width));
+            window_width += visible_width;
+        } else {
+            window.push_str(grapheme);
+            window_width += grapheme_width;
+        }
+        if window_width >= max_width {
+            break;
+        }
+    }
+    window
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use super::*;
+    use crate::theme;
+
+    #[test]
+    fn test_width() {
+        assert_eq!(width("blah"), 4);
+        assert_eq!(width("éé"), 2);
+        assert_eq!(width("e\u{301}"), 1);
+        assert_eq!(width("東京"), 4);
+        assert_eq!(width("😌"), 2);
+        assert_eq!(width("👩‍👩‍👧"), 2);
+        assert_eq!(
+            width(
+                &markup::Markup::new("blah")
+                    .fg(theme::Color::Attention)
+                    .into_string()
+            ),
+            4
+        );
+    }
+
+    #[test]
+    fn test_ellipsis() {
+        assert_eq!(ellipsis("blah blah blah", None), "blah blah blah");
+        assert_eq!(ellipsis("blah blah blah", Some(14)), "blah blah blah");
+        assert_eq!(ellipsis("blah blah blah!", Some(14)), "blah blah bla…");
+        assert_eq!(ellipsis("blah blah blah ", Some(14)), "blah blah blah");
+        assert_eq!(ellipsis("blah blah bla h", Some(14)), "blah blah bla…");
+        assert_eq!(ellipsis("blah blah bla ha", Some(15)), "blah blah bla…");
+        assert_eq!(ellipsis("éééé", Some(2)), "é…");
+        assert_eq!(ellipsis("Café crème", Some(10)), "Café crème");
+        assert_eq!(ellipsis("東京タワー", Some(6)), "東京…");
+        assert_eq!(ellipsis("東京タワー", Some(5)), "東京…");
+        assert_eq!(ellipsis("👩‍👩‍👧👩‍👩‍👧", Some(3)), "👩‍👩‍👧…");
+    }
+
+    #[test]
+    fn test_pad() {
+        assert_eq!(pad("blah", None), "blah");
+        assert_eq!(pad("blah", Some(2)), "blah");
+        assert_eq!(pad("blah", Some(6)), "  blah");
+        assert_eq!(pad("東京", Some(6)), "  東京");
+    }
+
+    #[test]
+    fn test_layout() {
+        let layout = Layout::default()
+            .group(" ")
+            .fragment("P", REQUIRED)
+            .fragment("player", 0)
+            .group(" / ")
+            .fragment("artist", 2)
+            .fragment("", 1)
+            .fragment("album", 1)
+            .fragment("title", REQUIRED);
+        assert_eq!(layout.render(None), "P player artist / album / title");
+        assert_eq!(layout.render(Some(31)), "P player artist / album / title");
+        assert_eq!(layout.render(Some(30)), "P artist / album / title");
+        assert_eq!(layout.render(Some(23)), "P artist / title");
+        assert_eq!(layout.render(Some(15)), "P title");
+        assert_eq!(layout.render(Some(5)), "P ti…");
+        assert!(!layout.overflows(7));
+        assert!(layout.overflows(6));
+        assert_eq!(
+            layout.render_with(Some(5), |s, w| s.get(..w).unwrap().to_owned()),
+            "P tit"
+        );
+    }
+
+    #[test]
+    fn test_marquee_offset() {
+        let marquee = Marquee::new(&MarqueeConfig {
+            enabled: true,
+            speed_cps: NonZeroU32::new(4).unwrap(),
+            pause_s: 2,
+        })
+        .unwrap();
+        assert_eq!(marquee.step(), Duration::from_millis(250));
+        for (elapsed_ms, offset) in [
+            (0, 0),
+            (1999, 0),
+            (2000, 1),
+            (2249, 1),
+            (2250, 2),
+            (2500, 3),
+            (2999, 4),
+            (3000, 5),
+            (3249, 5),
+            (5249, 5),
+            (5250, 0),
+            (7250, 1),
+        ] {
+            assert_eq!(
+                marquee.offset(5, Duration::from_millis(elapsed_ms)),
+                offset,
+                "{elapsed_ms}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_scroll_window() {
+        assert_eq!(scroll_window("abcdef", 0, 4), "abcd");
+        assert_eq!(scroll_window("abcdef", 2, 4), "cdef");
+        assert_eq!(scroll_window("東京タワー", 0, 5), "東京 ");
+        assert_eq!(scroll_window("東京タワー", 1, 5), " 京タ");
+        assert_eq!(scroll_window("東京タワー", 2, 5), "京タ ");
+        assert_eq!(scroll_window("東京タワー", 5, 5), " ワー");
+    }
+
+    #[test]
+    fn test_marquee_window() {
+        let marquee = Marquee::new(&MarqueeConfig {
+            enabled: true,
+            ..MarqueeConfig::default()
+        })
+        .unwrap();
+        assert_eq!(marquee.window("blah", 4), "blah");
+        assert_eq!(marquee.window("blah blah", 4), "blah");
+        assert!(
+            Marquee::new(&MarqueeConfig::default()).is_none(),
+            "disabled by default"
+        );
+    }
+}
@@ -0,0 +1,267 @@
+//! Logging to the terminal, or to the systemd journal or a file when there is no terminal to log to
+
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    env,
+    fs::{self, File},
+    io::{self, Write as _},
+    os::unix::{fs::MetadataExt as _, net::UnixDatagram},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::config::{self, LogConfig, LogTarget};
+
+/// Socket of the journal native protocol, see <https://systemd.io/JOURNAL_NATIVE_PROTOCOL/>
+const JOURNAL_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// Size from which the log file is rotated
+const LOG_FILE_MAX_SIZE: u64 = 1024 * 1024;
+
+thread_local! {
+    /// Name of the module run by the current thread
+    static THREAD_MODULE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Attribute logs of the current thread to module `name`
+pub(crate) fn set_thread_module(name: &str) {
+    THREAD_MODULE.with_borrow_mut(|m| *m = Some(name.to_owned()));
+}
+
+enum Sink {
+    Stderr,
+    Journal(UnixDatagram),
+    File(LogFile),
+}
+
+/// Log file, reopened if another process rotated it
+struct LogFile {
+    path: PathBuf,
+    file: File,
+}
+
+impl LogFile {
+    fn open(path: PathBuf) -> io::Result<Self> {
+        let file = File::options().create(true).append(true).open(&path)?;
+        Ok(Self { path, file })
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let rotated = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata.ino() != self.file.metadata()?.ino(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => true,
+            Err(err) => return Err(err),
+        };
+        if rotated {
+            *self = Self::open(self.path.clone())?;
+        } else if self.file.metadata()?.len() >= LOG_FILE_MAX_SIZE {
+            fs::rename(&self.path, self.path.with_extension("log.1"))?;
+            *self = Self::open(self.path.clone())?;
+        }
+        self.file.write_all(line.as_bytes())
+    }
+}
+
+struct Logger {
+    sink: Mutex<Sink>,
+    default_level: log::LevelFilter,
+    module_levels: BTreeMap<String, log::LevelFilter>,
+    /// Module of threads not attributed to one, in single module mode
+    default_module: Option<String>,
+}
+
+impl Logger {
+    fn module(&self) -> Option<String> {
+        THREAD_MODULE
+            .with_borrow(Clone::clone)
+            .or_else(|| self.default_module.clone())
+    }
+
+    fn level(&self, module: Option<&str>) -> log::LevelFilter {
+        module
+            .and_then(|m| self.module_levels.get(&config::module_config_name(m)))
+            .copied()
+            .unwrap_or(self.default_level)
+    }
+}
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        metadata.level() <= self.level(self.module().as_deref())
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        let module = self.module();
+        if record.level() > self.level(module.as_deref()) {
+            return;
+        }
+        // Logging errors can not be logged, and should not stop modules
+        let _ = match &mut *self.sink.lock().unwrap() {
+            Sink::Stderr => io::stderr().write_all(file_line(record, module.as_deref()).as_bytes()),
+            Sink::Journal(socket) => socket
+                .send_to(&journal_message(record, module.as_deref()), JOURNAL_SOCKET)
+                .map(|_| ()),
+            Sink::File(log_file) => log_file.write_line(&file_line(record, module.as_deref())),
+        };
+    }
+
+    fn flush(&self) {}
+}
+
+/// Setup logger from `cfg`, logging to stderr instead of its target if `terminal`, and attributing logs to module
+/// `default_module` when the thread has none
+///
+/// The `RUST_LOG` environment variable, ie. `RUST_LOG=debug`, overrides the configured default level.
+pub(crate) fn init(
+    cfg: Option<&LogConfig>,
+    default_module: Option<&str>,
+    terminal: bool,
+) -> anyhow::Result<()> {
+    let target = match cfg.and_then(|c| c.target) {
+        Some(target) => target,
+        None if Path::new(JOURNAL_SOCKET).exists() => LogTarget::Journal,
+        None => LogTarget::File,
+    };
+    let sink = match target {
+        _ if terminal => Sink::Stderr,
+        LogTarget::Journal => Sink::Journal(UnixDatagram::unbound()?),
+        LogTarget::File => {
+            let xdg_dirs = xdg::BaseDirectories::with_prefix(env!("CARGO_PKG_NAME"));
+            let path = xdg_dirs.place_state_file(concat!(env!("CARGO_PKG_NAME"), ".log"))?;
+            Sink::File(LogFile::open(path)?)
+        }
+    };
+    let default_level = env::var("RUST_LOG")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .or_else(|| cfg?.level)
+        .unwrap_or(if cfg!(debug_assertions) {
+            log::LevelFilter::Debug
+        } else {
+            log::LevelFilter::Info
+        });
+    let module_levels = cfg.map(|c| c.modules.clone()).unwrap_or_default();
+    let max_level = module_levels
+        .values()
+        .copied()
+        .fold(default_level, Ord::max);
+
+    log::set_boxed_logger(Box::new(Logger {
+        sink: Mutex::new(sink),
+        default_level,
+        module_levels,
+        default_module: default_module.map(ToOwned::to_owned),
+    }))?;
+    log::set_max_level(max_level);
+    Ok(())
+}
+
+/// Build journal native protocol datagram for `record`
+fn journal_message(record: &log::Record<'_>, module: Option<&str>) -> Vec<u8> {
+    let priority = match record.level() {
+        log::Level::Error => "3",
+        log::Level::Warn => "4",
+        log::Level::Info => "6",
+        log::Level::Debug | log::Level::Trace => "7",
+    };
+    let mut msg = Vec::new();
+    append_journal_field(&mut msg, "PRIORITY", priority);
+    append_journal_field(&mut msg, "SYSLOG_IDENTIFIER", env!("CARGO_PKG_NAME"));
+    if let Some(module) = module {
+        append_journal_field(&mut msg, "POLYBAR_MODULE", module);
+    }
+    if let Some(file) = record.file() {
+        append_journal_field(&mut msg, "CODE_FILE", file);
+    }
+    if let Some(line) = record.line() {
+        append_journal_field(&mut msg, "CODE_LINE", &line.to_string());
+    }
+    append_journal_field(&mut msg, "MESSAGE", &record.args().to_string());
+    msg
+}
+
+fn append_journal_field(msg: &mut Vec<u8>, name: &str, value: &str) {
+    msg.extend_from_slice(name.as_bytes());
+    if value.contains('\n') {
+        // Multiline values are length prefixed
+        msg.push(b'\n');
+        msg.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        msg.push(b'=');
+    }
+    msg.extend_from_slice(value.as_bytes());
+    msg.push(b'\n');
+}
+
+/// Format `record` as a log file line
+fn file_line(record: &log::Record<'_>, module: Option<&str>) -> String {
+    let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f");
+    let level = record.level();
+    match module {
+        Some(module) => format!("{timestamp} {level:<5} [{module}] {}\n", record.args()),
+        None => format!("{timestamp} {level:<5} {}\n", record.args()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal_message() {
+        let msg = journal_message(
+            &log::Record::builder()
+                .level(log::Level::Warn)
+                .args(format_args!("Update failed:\nno route to host"))
+                .build(),
+            Some("weather"),
+        );
+        let mut expected =
+            b"PRIORITY=4\nSYSLOG_IDENTIFIER=polybar-modules\nPOLYBAR_MODULE=weather\nMESSAGE\n"
+                .to_vec();
+        expected.extend_from_slice(&31_u64.to_le_bytes());
+        expected.extend_from_slice(b"Update failed:\nno route to host\n");
+        assert_eq!(msg, expected);
+    }
+
+    #[test]
+    fn test_file_rotation() {
+        let log_dir = tempfile::tempdir().unwrap();
+        let path = log_dir.path().join("test.log");
+        let mut log_file = LogFile::open(path.clone()).unwrap();
+        let line = "a".repeat(1024);
+        for _ in 0..=LOG_FILE_MAX_SIZE / 1024 {
+            log_file.write_line(&line).unwrap();
+        }
+        assert_eq!(fs::metadata(&path).unwrap().len(), 1024);
+        assert_eq!(
+            fs::metadata(path.with_extension("log.1")).unwrap().len(),
+            LOG_FILE_MAX_SIZE
+        );
+
+        // Rotated by another process
+        let mut other_log_file = LogFile::open(path.clone()).unwrap();
+        fs::rename(&path, path.with_extension("log.1")).unwrap();
+        other_log_file.write_line(&line).unwrap();
+        log_file.write_line(&line).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().len(), 2048);
+    }
+
+    #[test]
+    fn test_level() {
+        let logger = Logger {
+            sink: Mutex::new(Sink::Journal(UnixDatagram::unbound().unwrap())),
+            default_level: log::LevelFilter::Warn,
+            module_levels: BTreeMap::from([
+                ("weather".to_owned(), log::LevelFilter::Debug),
+                ("cpu_top".to_owned(), log::LevelFilter::Error),
+            ]),
+            default_module: None,
+        };
+        assert_eq!(logger.level(None), log::LevelFilter::Warn);
+        assert_eq!(logger.level(Some("cpu-freq")), log::LevelFilter::Warn);
+        assert_eq!(logger.level(Some("cpu-top")), log::LevelFilter::Error);
+        assert_eq!(logger.level(Some("weather")), log::LevelFilter::Debug);
+    }
+}
@@ -2,50 +2,363 @@
 
 #![feature(exit_status_error)]
 
-use std::io::{self, IsTerminal as _};
+use std::{
+    backtrace::Backtrace,
+    collections::HashSet,
+    io::{self, IsTerminal as _},
+    panic::{self, AssertUnwindSafe},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
 
 use anyhow::Context as _;
-use clap::Parser as _;
+use backon::BackoffBuilder as _;
+use clap::{CommandFactory as _, FromArgMatches as _};
 use config::PolybarModuleName;
+use polybar_module::stale::StaleHoldModule;
 
+mod alert;
+mod backend;
 mod config;
+mod control;
+mod daemon;
+mod detail;
+mod icons;
+mod layout;
+mod logging;
 mod markup;
 mod polybar_module;
+mod sparkline;
 mod theme;
 
-#[expect(clippy::too_many_lines)]
 fn main() -> anyhow::Result<()> {
-    // Init logger
-    if io::stdout().is_terminal() {
-        simple_logger::SimpleLogger::new()
-            .with_level(if cfg!(debug_assertions) {
-                log::LevelFilter::Debug
-            } else {
-                log::LevelFilter::Info
-            })
-            .env()
-            .init()
-            .context("Failed to setup logger")?;
-    }
+    // Log panics with their backtrace, if there is a logger
+    let default_panic_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if log::max_level() == log::LevelFilter::Off {
+            default_panic_hook(info);
+        } else {
+            log::error!("{info}\n{}", Backtrace::force_capture());
+        }
+    }));
 
     // Parse command line args
-    let cl_opts = config::CommandLineOpts::parse();
-    log::trace!("{cl_opts:?}");
+    let cl_matches = config::CommandLineOpts::command().get_matches();
+    let cl_opts = config::CommandLineOpts::from_arg_matches(&cl_matches)?;
 
     // Parse config file
     let cfg = config::parse_config_file();
 
-    // Init stuff
-    let module: polybar_module::PolybarModule = match cl_opts.module {
+    // Init logger, with the module name of single module mode, daemon threads set their own
+    let log_module = matches!(cl_opts.command, config::Command::Module(_))
+        .then(|| cl_matches.subcommand_name())
+        .flatten();
+    init_logger(log_module, cfg.as_ref().ok().and_then(|c| c.log.as_ref()))?;
+    log::trace!("{cl_opts:?}");
+
+    // Invalid sections fall back to defaults, so that modules not depending on them still run, and subcommands
+    // like check-config can report it
+    if let Err(err) = apply_global_config(cfg.as_ref().ok(), cl_opts.icons) {
+        log::error!("Failed to apply config: {err:#}");
+    }
+
+    let stale_limit = cfg
+        .as_ref()
+        .map_or(config::DEFAULT_STALE_LIMIT, config::Config::stale_limit);
+    let alerts_cfg = cfg
+        .as_ref()
+        .ok()
+        .and_then(|c| c.alerts.clone())
+        .unwrap_or_default();
+
+    match cl_opts.command {
+        config::Command::Module(module_name) => {
+            let name = cl_matches
+                .subcommand_name()
+                .ok_or_else(|| anyhow::anyhow!("Missing module subcommand"))?
+                .to_owned();
+            if cl_opts.once {
+                // Don't serve detail or control commands, a long running instance may do it already
+                let opts = RunOpts {
+                    name: &name,
+                    once: true,
+                    json: cl_opts.json,
+                    control_server: None,
+                    config_watch: None,
+                    stale_limit,
+                    alerts_cfg: &alerts_cfg,
+                };
+                return run_module(
+                    |latest_cfg| {
+                        build_module(module_name.clone(), latest_cfg.map_or(cfg.as_ref(), Ok))
+                    },
+                    &opts,
+                    |output, detail| {
+                        println!(
+                            "{}",
+                            backend::format_once(
+                                cl_opts.backend,
+                                &name,
+                                &output,
+                                detail.as_deref()
+                            )
+                        );
+                    },
+                );
+            }
+            let publishers =
+                backend::Publishers::new(cl_opts.backend, std::slice::from_ref(&name), false)?;
+            let publisher = detail_publisher(&name, publishers.publisher(0, &name)?)?;
+            let config_watch = watch_config(cl_opts.icons);
+            start_flag_detection(cfg.as_ref().ok());
+            let control_server = control::Server::new(&name);
+            let opts = RunOpts {
+                name: &name,
+                once: false,
+                json: cl_opts.json,
+                control_server: Some(&control_server),
+                config_watch: config_watch.as_ref(),
+                stale_limit,
+                alerts_cfg: &alerts_cfg,
+            };
+            run_module(
+                |latest_cfg| build_module(module_name.clone(), latest_cfg.map_or(cfg.as_ref(), Ok)),
+                &opts,
+                publisher,
+            )
+        }
+        config::Command::daemon { modules } => {
+            anyhow::ensure!(!cl_opts.once, "--once is not supported in daemon mode");
+            run_daemon(&modules, cl_opts.backend, cl_opts.json, cl_opts.icons, cfg)
+        }
+        config::Command::detail { module } => {
+            println!("{}", detail::query(&module)?);
+            Ok(())
+        }
+        config::Command::ctl { module, command } => control::send(&module, &command),
+        config::Command::check_config { dump_default } => {
+            if dump_default {
+                print!("{}", config::DEFAULT_CONFIG);
+                Ok(())
+            } else {
+                config::check_config_file()
+            }
+        }
+    }
+}
+
+/// Log to the terminal if there is one, or as configured otherwise
+fn init_logger(
+    module_name: Option<&str>,
+    log_cfg: Option<&config::LogConfig>,
+) -> anyhow::Result<()> {
+    logging::init(log_cfg, module_name, io::stdout().is_terminal())
+        .context("Failed to setup logger")
+}
+
+/// Run several modules, each in its own thread, publishing their output separately
+fn run_daemon(
+    module_specs: &[String],
+    backend: config::Backend,
+    json: bool,
+    icon_set: Option<config::IconSet>,
+    cfg: anyhow::Result<config::Config>,
+) -> anyhow::Result<()> {
+    let mut names = Vec::with_capacity(module_specs.len());
+    let mut module_names = Vec::with_capacity(module_specs.len());
+    for module_spec in module_specs {
+        let (name, module_name) = daemon::parse_module_spec(module_spec)
+            .with_context(|| format!("Invalid module specification {module_spec:?}"))?;
+        names.push(name);
+        module_names.push(module_name);
+    }
+    let mut unique_names = HashSet::new();
+    if let Some(name) = names.iter().find(|n| !unique_names.insert(*n)) {
+        anyhow::bail!("Module {name:?} specified more than once");
+    }
+
+    let publishers = backend::Publishers::new(backend, &names, true)?;
+    let stale_limit = cfg
+        .as_ref()
+        .map_or(config::DEFAULT_STALE_LIMIT, config::Config::stale_limit);
+    let alerts_cfg = cfg
+        .as_ref()
+        .ok()
+        .and_then(|c| c.alerts.clone())
+        .unwrap_or_default();
+    let cfg = Arc::new(cfg);
+    let config_watch = Arc::new(watch_config(icon_set));
+    start_flag_detection(cfg.as_ref().as_ref().ok());
+    let mut module_threads = Vec::with_capacity(module_specs.len());
+    for (index, (name, module_name)) in names.into_iter().zip(module_names).enumerate() {
+        let publisher = detail_publisher(&name, publishers.publisher(index, &name)?)?;
+        let cfg = Arc::clone(&cfg);
+        let config_watch = Arc::clone(&config_watch);
+        let alerts_cfg = alerts_cfg.clone();
+        let module_thread = thread::Builder::new().name(name.clone()).spawn({
+            let name = name.clone();
+            move || -> anyhow::Result<()> {
+                let control_server = control::Server::new(&name);
+                let opts = RunOpts {
+                    name: &name,
+                    once: false,
+                    json,
+                    control_server: Some(&control_server),
+                    config_watch: config_watch.as_ref().as_ref(),
+                    stale_limit,
+                    alerts_cfg: &alerts_cfg,
+                };
+                run_module(
+                    |latest_cfg| {
+                        build_module(
+                            module_name.clone(),
+                            latest_cfg.map_or(cfg.as_ref().as_ref(), Ok),
+                        )
+                    },
+                    &opts,
+                    publisher,
+                )
+            }
+        })?;
+        module_threads.push((name, module_thread));
+    }
+
+    // Modules run forever, so we only get past this if they all failed
+    for (name, module_thread) in module_threads {
+        match module_thread.join() {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => log::error!("Module {name:?} failed: {err:#}"),
+            Err(_) => log::error!("Module {name:?} panicked"),
+        }
+    }
+    anyhow::bail!("All modules have exited")
+}
+
+/// Wrap `publisher` of module `name`, to also serve its detail to `detail` subcommand clients
+fn detail_publisher(
+    name: &str,
+    mut publisher: backend::Publisher,
+) -> anyhow::Result<impl FnMut(String, Option<String>) + Send + use<>> {
+    let detail = detail::spawn_server(name)?;
+    Ok(move |output, module_detail| {
+        detail.lock().unwrap().clone_from(&module_detail);
+        publisher(output, module_detail);
+    })
+}
+
+/// How a module is run
+struct RunOpts<'a> {
+    /// Module name, ie. `cpu-freq`
+    name: &'a str,
+    /// Update and publish output only once, and return
+    once: bool,
+    /// Publish module state as JSON instead of rendered output
+    json: bool,
+    /// Server of control commands, kept when the module is rebuilt, if they are served
+    control_server: Option<&'a control::Server>,
+    /// Config file watch, to reload module when it changes, checked before each update
+    config_watch: Option<&'a config::ConfigWatch>,
+    /// How long modules keep showing their last good state after updates fail
+    stale_limit: Duration,
+    /// Desktop notifications of significant state changes
+    alerts_cfg: &'a config::AlertsConfig,
+}
+
+#[cfg(test)]
+impl RunOpts<'static> {
+    /// Options of `--once` runs in tests, without config
+    fn once() -> Self {
+        Self {
+            name: "test",
+            once: true,
+            json: false,
+            control_server: None,
+            config_watch: None,
+            stale_limit: Duration::ZERO,
+            alerts_cfg: Box::leak(Box::default()),
+        }
+    }
+}
+
+/// Apply config sections common to all modules, with `icon_set` from the command line overriding the configured
+/// one
+///
+/// Missing sections reset their settings to the defaults. Valid sections are applied even if others are not.
+fn apply_global_config(
+    cfg: Option<&config::Config>,
+    icon_set: Option<config::IconSet>,
+) -> anyhow::Result<()> {
+    let icons_cfg = cfg.and_then(|c| c.icons.as_ref());
+    icons::set_icon_theme(icons::IconTheme::new(
+        icon_set.or_else(|| icons_cfg?.set).unwrap_or_default(),
+        icons_cfg.map(|c| c.overrides.clone()).unwrap_or_default(),
+    ));
+    polybar_module::set_power_config(cfg.and_then(|c| c.power.clone()).unwrap_or_default());
+    let theme_res = theme::Palette::from_config(
+        cfg.and_then(|c| c.theme.as_ref())
+            .unwrap_or(&config::ThemeConfig::default()),
+    )
+    .map(theme::set_palette)
+    .context("Invalid theme configuration");
+    let http_res = polybar_module::http::set_config(
+        cfg.and_then(|c| c.http.as_ref())
+            .unwrap_or(&config::HttpConfig::default()),
+    )
+    .context("Invalid HTTP configuration");
+    theme_res.and(http_res)
+}
+
+/// Start automatic public screen and low bandwidth detection, where enabled in `cfg`
+fn start_flag_detection(cfg: Option<&config::Config>) {
+    let env = polybar_module::PolybarModuleEnv::new();
+    if let Some(public_screen_cfg) = cfg.and_then(|c| c.public_screen.as_ref()) {
+        polybar_module::public_screen::start_detection(
+            public_screen_cfg,
+            &env.public_screen_filepath,
+        );
+    }
+    if let Some(low_bandwidth_cfg) = cfg.and_then(|c| c.low_bandwidth.as_ref()) {
+        polybar_module::low_bandwidth::start_detection(low_bandwidth_cfg, &env.low_bw_filepath);
+    }
+}
+
+/// Watch config file if there is one, applying the sections common to all modules when it changes
+fn watch_config(icon_set: Option<config::IconSet>) -> Option<config::ConfigWatch> {
+    config::ConfigWatch::new(Box::new(move |cfg| {
+        if let Err(err) = apply_global_config(Some(cfg), icon_set) {
+            log::warn!("Failed to apply config: {err:#}");
+        }
+    }))
+    .inspect_err(|err| log::debug!("Not watching config file: {err:#}"))
+    .ok()
+}
+
+#[expect(clippy::too_many_lines)]
+fn build_module(
+    module_name: PolybarModuleName,
+    cfg: Result<&config::Config, &anyhow::Error>,
+) -> anyhow::Result<polybar_module::PolybarModule> {
+    // Modules with only tunables fall back to defaults when the config file or section is missing
+    let modules_cfg = cfg.ok().and_then(|c| c.module.as_ref());
+    let cfg = cfg.map_err(|err| anyhow::anyhow!("{err:#}"));
+    let module = match module_name {
         PolybarModuleName::arch_updates => polybar_module::PolybarModule::ArchUpdates(
-            polybar_module::arch_updates::ArchUpdatesModule::new(),
-        ),
-        PolybarModuleName::autolock => polybar_module::PolybarModule::Autolock(
-            polybar_module::autolock::AutolockModule::new()
-                .context("Failed to initialize autolock module")?,
+            polybar_module::arch_updates::ArchUpdatesModule::new(
+                &modules_cfg
+                    .and_then(|m| m.arch_updates.clone())
+                    .unwrap_or_default(),
+            ),
         ),
+        PolybarModuleName::autolock => {
+            polybar_module::PolybarModule::Autolock(polybar_module::autolock::AutolockModule::new())
+        }
         PolybarModuleName::battery_mouse => polybar_module::PolybarModule::BatteryMouse(
-            polybar_module::battery_mouse::BatteryMouseModule::new(),
+            polybar_module::battery_mouse::BatteryMouseModule::new(
+                modules_cfg
+                    .and_then(|m| m.battery_mouse.clone())
+                    .unwrap_or_default(),
+            ),
         ),
         PolybarModuleName::bluetooth {
             device_whitelist_addrs,
@@ -54,67 +367,100 @@ fn main() -> anyhow::Result<()> {
                 .context("Failed to initialize bluetooth module")?,
         ),
         PolybarModuleName::cpu_freq => polybar_module::PolybarModule::CpuFreq(
-            polybar_module::cpu_freq::CpuFreqModule::new()
-                .context("Failed to initialize CPU frequency module")?,
-        ),
-        PolybarModuleName::cpu_top { max_len } => polybar_module::PolybarModule::CpuTop(
-            polybar_module::cpu_top::CpuTopModule::new(max_len),
+            polybar_module::cpu_freq::CpuFreqModule::new(
+                modules_cfg
+                    .and_then(|m| m.cpu_freq.clone())
+                    .unwrap_or_default(),
+            )
+            .context("Failed to initialize CPU frequency module")?,
         ),
+        PolybarModuleName::cpu_top { max_len } => {
+            polybar_module::PolybarModule::CpuTop(polybar_module::cpu_top::CpuTopModule::new(
+                max_len,
+                modules_cfg
+                    .and_then(|m| m.cpu_top.clone())
+                    .unwrap_or_default(),
+            ))
+        }
         PolybarModuleName::debian_updates => polybar_module::PolybarModule::DebianUpdates(
-            polybar_module::debian_updates::DebianUpdatesModule::new()
-                .context("Failed to initialize Debian updates module")?,
+            polybar_module::debian_updates::DebianUpdatesModule::new(
+                &modules_cfg
+                    .and_then(|m| m.debian_updates.clone())
+                    .unwrap_or_default(),
+            )
+            .context("Failed to initialize Debian updates module")?,
         ),
         PolybarModuleName::gpu_nvidia => polybar_module::PolybarModule::GpuNvidia(
-            polybar_module::gpu_nvidia::GpuNvidiaModule::new()
-                .context("Failed to initialize Nvidia GPU module")?,
+            polybar_module::gpu_nvidia::GpuNvidiaModule::new(
+                &modules_cfg
+                    .and_then(|m| m.gpu_nvidia.clone())
+                    .unwrap_or_default(),
+            )
+            .context("Failed to initialize Nvidia GPU module")?,
         ),
         PolybarModuleName::home_power => {
             let home_power_cfg = cfg
                 .and_then(|c| {
                     c.module
+                        .as_ref()
                         .ok_or_else(|| anyhow::anyhow!("Missing 'module' config section"))
                 })
                 .and_then(|c| {
                     c.home_power
+                        .as_ref()
                         .ok_or_else(|| anyhow::anyhow!("Missing 'home_power' config section"))
                 })
                 .context("Unable to get home power module config from config file")?;
             polybar_module::PolybarModule::HomePower(
-                polybar_module::home_power::HomePowerModule::new(&home_power_cfg),
+                polybar_module::home_power::HomePowerModule::new(home_power_cfg),
             )
         }
         PolybarModuleName::inference_usage => polybar_module::PolybarModule::InferenceUsage(
-            polybar_module::inference_usage::InferenceUsageModule::new(),
+            polybar_module::inference_usage::InferenceUsageModule::new(
+                &modules_cfg
+                    .and_then(|m| m.inference_usage.clone())
+                    .unwrap_or_default(),
+            ),
         ),
         PolybarModuleName::internet_bandwidth => polybar_module::PolybarModule::InternetBandwidth(
             polybar_module::internet_bandwidth::InternetBandwidthModule::new(),
         ),
         PolybarModuleName::market => {
-            polybar_module::PolybarModule::Market(polybar_module::market::MarketModule::new())
+            polybar_module::PolybarModule::Market(polybar_module::market::MarketModule::new(
+                &modules_cfg
+                    .and_then(|m| m.market.clone())
+                    .unwrap_or_default(),
+            ))
         }
         PolybarModuleName::network_status => {
             let network_status_cfg = cfg
                 .and_then(|c| {
                     c.module
+                        .as_ref()
                         .ok_or_else(|| anyhow::anyhow!("Missing 'module' config section"))
                 })
                 .and_then(|c| {
                     c.network_status
+                        .as_ref()
                         .ok_or_else(|| anyhow::anyhow!("Missing 'network_status' config section"))
                 })
                 .context("Unable to get network status module config from config file")?;
             polybar_module::PolybarModule::NetworkStatus(
-                polybar_module::network_status::NetworkStatusModule::new(network_status_cfg)
-                    .context("Failed to initialize network status module")?,
+                polybar_module::network_status::NetworkStatusModule::new(
+                    network_status_cfg.clone(),
+                )
+                .context("Failed to initialize network status module")?,
             )
         }
         PolybarModuleName::notifications => polybar_module::PolybarModule::Notifications(
-            polybar_module::notifications::NotificationsModule::new()
-                .context("Failed to initialize notifications module")?,
+            polybar_module::notifications::NotificationsModule::new(),
         ),
         PolybarModuleName::player { max_len } => polybar_module::PolybarModule::Player(
-            polybar_module::player::PlayerModule::new(max_len)
-                .context("Failed to initialize player module")?,
+            polybar_module::player::PlayerModule::new(
+                max_len,
+                cfg.as_ref().ok().and_then(|c| c.marquee.as_ref()),
+            )
+            .context("Failed to initialize player module")?,
         ),
         PolybarModuleName::progressbar_server => polybar_module::PolybarModule::ProgressBarServer(
             polybar_module::progressbar_server::ProgressBarServerModule::new()
@@ -135,16 +481,20 @@ fn main() -> anyhow::Result<()> {
             )
         }
         PolybarModuleName::todotxt { max_len } => polybar_module::PolybarModule::TodoTxt(
-            polybar_module::todotxt::TodoTxtModule::new(max_len)
-                .context("Failed to initialize Todo.txt module")?,
+            polybar_module::todotxt::TodoTxtModule::new(
+                max_len,
+                cfg.as_ref().ok().and_then(|c| c.marquee.as_ref()),
+            )
+            .context("Failed to initialize Todo.txt module")?,
         ),
         PolybarModuleName::weather => {
             let weather_cfg = cfg?
                 .module
-                .and_then(|m| m.weather)
+                .as_ref()
+                .and_then(|m| m.weather.as_ref())
                 .context("Missing 'module.weather' config section")?;
             polybar_module::PolybarModule::Weather(polybar_module::weather::WeatherModule::new(
-                &weather_cfg,
+                weather_cfg,
             ))
         }
         PolybarModuleName::xmonad => polybar_module::PolybarModule::Xmonad(
@@ -152,56 +502,303 @@ fn main() -> anyhow::Result<()> {
                 .context("Failed to initialize Xmonad module")?,
         ),
     };
+    Ok(module)
+}
+
+/// Module update or wait panicked
+#[derive(thiserror::Error, Debug)]
+#[error("Module panicked: {message}")]
+struct ModulePanic {
+    message: String,
+    /// Whether the module had successfully updated before panicking
+    updated: bool,
+}
 
+/// Build module with `build` and run it, rebuilding it if it panics
+///
+/// `build` gets the last valid config if the config file changed since startup.
+fn run_module<B, P>(build: B, opts: &RunOpts<'_>, mut publish: P) -> anyhow::Result<()>
+where
+    B: Fn(Option<&config::Config>) -> anyhow::Result<polybar_module::PolybarModule>,
+    P: FnMut(String, Option<String>),
+{
+    logging::set_thread_module(opts.name);
+    let latest_cfg = || opts.config_watch.and_then(config::ConfigWatch::latest);
+    let mut module = build(latest_cfg().as_deref())?;
+    let mut backoff = polybar_module::NETWORK_ERROR_BACKOFF.build();
+    loop {
+        let err = match dispatch_module(module, opts, &mut publish) {
+            Ok(()) => return Ok(()),
+            Err(err) if opts.once => return Err(err),
+            Err(err) => err,
+        };
+        let module_panic = err.downcast::<ModulePanic>()?;
+        if module_panic.updated {
+            backoff = polybar_module::NETWORK_ERROR_BACKOFF.build();
+        }
+        module = loop {
+            let delay = backoff.next().unwrap();
+            log::warn!("Restarting module {:?} in {delay:?}", opts.name);
+            polybar_module::sleep_suspend_aware(delay);
+            match build(latest_cfg().as_deref()) {
+                Ok(new_module) => break new_module,
+                Err(build_err) => {
+                    log::error!("Failed to rebuild module {:?}: {build_err:#}", opts.name);
+                }
+            }
+        };
+    }
+}
+
+fn dispatch_module<P>(
+    module: polybar_module::PolybarModule,
+    opts: &RunOpts<'_>,
+    publish: P,
+) -> anyhow::Result<()>
+where
+    P: FnMut(String, Option<String>),
+{
     // Update/render loop, dynamic dispatch sadness, sadly https://crates.io/crates/enum_dispatch does not work here
     match module {
-        polybar_module::PolybarModule::ArchUpdates(module) => render_loop(module),
-        polybar_module::PolybarModule::Autolock(module) => render_loop(module),
-        polybar_module::PolybarModule::BatteryMouse(module) => render_loop(module),
-        polybar_module::PolybarModule::Bluetooth(module) => render_loop(module),
-        polybar_module::PolybarModule::CpuFreq(module) => render_loop(module),
-        polybar_module::PolybarModule::CpuTop(module) => render_loop(module),
-        polybar_module::PolybarModule::DebianUpdates(module) => render_loop(module),
-        polybar_module::PolybarModule::GpuNvidia(module) => render_loop(module),
-        polybar_module::PolybarModule::HomePower(module) => render_loop(module),
-        polybar_module::PolybarModule::InferenceUsage(module) => render_loop(module),
-        polybar_module::PolybarModule::InternetBandwidth(module) => render_loop(module),
-        polybar_module::PolybarModule::Market(module) => render_loop(module),
-        polybar_module::PolybarModule::NetworkStatus(module) => render_loop(module),
-        polybar_module::PolybarModule::Notifications(module) => render_loop(module),
-        polybar_module::PolybarModule::Player(module) => render_loop(module),
-        polybar_module::PolybarModule::ProgressBarServer(module) => render_loop(module),
-        polybar_module::PolybarModule::PulseAudio(module) => render_loop(module),
-        polybar_module::PolybarModule::Syncthing(module) => render_loop(module),
-        polybar_module::PolybarModule::TodoTxt(module) => render_loop(module),
-        polybar_module::PolybarModule::Weather(module) => render_loop(module),
-        polybar_module::PolybarModule::Xmonad(module) => render_loop(module),
-    }
-}
-
-fn render_loop<T>(mut module: T) -> !
+        polybar_module::PolybarModule::ArchUpdates(module) => render_loop(
+            StaleHoldModule::new(module, opts.stale_limit).with_cache(opts.name),
+            opts,
+            publish,
+        ),
+        polybar_module::PolybarModule::Autolock(module) => render_loop(module, opts, publish),
+        polybar_module::PolybarModule::BatteryMouse(module) => render_loop(module, opts, publish),
+        polybar_module::PolybarModule::Bluetooth(module) => render_loop(module, opts, publish),
+        polybar_module::PolybarModule::CpuFreq(module) => render_loop(module, opts, publish),
+        polybar_module::PolybarModule::CpuTop(module) => render_loop(module, opts, publish),
+        polybar_module::PolybarModule::DebianUpdates(module) => render_loop(
+            StaleHoldModule::new(module, opts.stale_limit).with_cache(opts.name),
+            opts,
+            publish,
+        ),
+        polybar_module::PolybarModule::GpuNvidia(module) => render_loop(module, opts, publish),
+        polybar_module::PolybarModule::HomePower(module) => render_loop(
+            StaleHoldModule::new(module, opts.stale_limit).with_cache(opts.name),
+            opts,
+            publish,
+        ),
+        // Not held stale, it renders failures per account, and holds its last complete state itself
+        // for only a short while, quotas being misleading once outdated
+        polybar_module::PolybarModule::InferenceUsage(module) => render_loop(module, opts, publish),
+        polybar_module::PolybarModule::InternetBandwidth(module) => {
+            render_loop(module, opts, publish)
+        }
+        polybar_module::PolybarModule::Market(module) => render_loop(
+            StaleHoldModule::new(module, opts.stale_limit).with_cache(opts.name),
+            opts,
+            publish,
+        ),
+        polybar_module::PolybarModule::NetworkStatus(module) => render_loop(module, opts, publish),
+        polybar_module::PolybarModule::Notifications(module) => render_loop(module, opts, publish),
+        polybar_module::PolybarModule::Player(module) => render_loop(module, opts, publish),
+        polybar_module::PolybarModule::ProgressBarServer(module) => {
+            render_loop(module, opts, publish)
+        }
+        polybar_module::PolybarModule::PulseAudio(module) => render_loop(module, opts, publish),
+        polybar_module::PolybarModule::Syncthing(module) => render_loop(
+            StaleHoldModule::new(module, opts.stale_limit).with_cache(opts.name),
+            opts,
+            publish,
+        ),
+        polybar_module::PolybarModule::TodoTxt(module) => render_loop(module, opts, publish),
+        polybar_module::PolybarModule::Weather(module) => render_loop(
+            StaleHoldModule::new(module, opts.stale_limit).with_cache(opts.name),
+            opts,
+            publish,
+        ),
+        polybar_module::PolybarModule::Xmonad(module) => render_loop(module, opts, publish),
+    }
+}
+
+fn render_loop<T, P>(mut module: T, opts: &RunOpts<'_>, mut publish: P) -> anyhow::Result<()>
 where
     T: polybar_module::RenderablePolybarModule,
+    P: FnMut(String, Option<String>),
 {
+    if let Some(control_server) = opts.control_server
+        && let Err(err) = control_server.set_handler(module.control_handler())
+    {
+        log::error!(
+            "Failed to serve control commands of module {:?}: {err:#}",
+            opts.name
+        );
+    }
+
+    // Show state from a previous run while the first update runs
+    if !opts.once
+        && let Some(state) = module.initial_state()
+    {
+        publish(
+            render_state(&module, &state, opts.json)?,
+            module.render_detail(&state),
+        );
+    }
+
+    let mut alerter = alert::Alerter::new(opts.name, opts.alerts_cfg.clone());
     let mut prev_state: Option<T::State> = None;
+    let mut prev_output: Option<String> = None;
+    // Start from 0 so that a module rebuilt after a panic picks up the latest config
+    let mut config_generation = 0;
+    // Whether to render even if the state did not change, ie. for new colors
+    let mut force_render = false;
     loop {
-        // Update
-        module.wait_update(prev_state.as_ref());
-        let state = module.update();
+        // Reload config
+        if let Some(cfg) = opts
+            .config_watch
+            .and_then(|w| w.changed(&mut config_generation))
+        {
+            alerter.set_config(cfg.alerts.clone().unwrap_or_default());
+            force_render = true;
+            match module.reload_config(&cfg) {
+                Ok(true) => {
+                    log::info!("Module {:?} reloaded", opts.name);
+                    prev_state = None;
+                }
+                Ok(false) => {}
+                Err(err) => log::warn!(
+                    "Failed to reload module {:?}, keeping previous one: {err:#}",
+                    opts.name
+                ),
+            }
+        }
+
+        // Update, the module is dropped if it panics, so its state being inconsistent does not matter
+        let state = match panic::catch_unwind(AssertUnwindSafe(|| {
+            // Some modules block even without a previous state, ie. until an event happens
+            if !opts.once {
+                module.wait_update(prev_state.as_ref());
+            }
+            module.update()
+        })) {
+            Ok(state) => state,
+            Err(payload) => {
+                let output = if opts.json {
+                    "null".to_owned()
+                } else {
+                    markup::Markup::new(icons::Icon::Warning)
+                        .fg(theme::Color::Critical)
+                        .into_string()
+                };
+                publish(output, None);
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|s| (*s).to_owned())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                return Err(ModulePanic {
+                    message,
+                    updated: prev_state.is_some(),
+                }
+                .into());
+            }
+        };
         log::debug!("{state:?}");
 
         // Render or skip?
-        let do_render = match &prev_state {
+        let changed = match &prev_state {
             Some(prev_state) => prev_state != &state,
             None => true,
         };
-        if !do_render {
+        if !changed && !force_render && module.rerender_interval(&state).is_none() {
             continue;
         }
+        if changed && let Some(prev_state) = &prev_state {
+            alerter.send(module.alerts(prev_state, &state));
+        }
 
         // Render
-        let output = module.render(&state);
-        println!("{output}");
+        let output = render_state(&module, &state, opts.json)?;
+        if !changed && !force_render && prev_output.as_ref() == Some(&output) {
+            continue;
+        }
+        let detail = module.render_detail(&state);
+        publish(output.clone(), detail);
+        force_render = false;
+        if opts.once {
+            return Ok(());
+        }
         prev_state = Some(state);
+        prev_output = Some(output);
+    }
+}
+
+/// Render module state as markup, or as JSON if `json` is set
+fn render_state<T>(module: &T, state: &T::State, json: bool) -> anyhow::Result<String>
+where
+    T: polybar_module::RenderablePolybarModule,
+{
+    if json {
+        serde_json::to_string(state).context("Failed to serialize module state")
+    } else {
+        Ok(module.render(state))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct PanickingModule;
+
+    impl polybar_module::RenderablePolybarModule for PanickingModule {
+        type State = ();
+
+        fn wait_update(&mut self, _prev_state: Option<&Self::State>) {}
+
+        fn update(&mut self) -> Self::State {
+            panic!("boom");
+        }
+
+        fn render(&self, _state: &Self::State) -> String {
+            String::new()
+        }
+    }
+
+    /// Module that would block forever waiting for an update
+    struct BlockingModule;
+
+    impl polybar_module::RenderablePolybarModule for BlockingModule {
+        type State = u8;
+
+        fn wait_update(&mut self, _prev_state: Option<&Self::State>) {
+            unreachable!("waited for update");
+        }
+
+        fn update(&mut self) -> Self::State {
+            1
+        }
+
+        fn render(&self, state: &Self::State) -> String {
+            state.to_string()
+        }
+    }
+
+    #[test]
+    fn test_render_loop_once() {
+        let opts = RunOpts::once();
+        let mut outputs = vec![];
+        render_loop(BlockingModule, &opts, |output, _detail| {
+            outputs.push(output);
+        })
+        .unwrap();
+        assert_eq!(outputs, vec!["1".to_owned()]);
+    }
+
+    #[test]
+    fn test_render_loop_panic() {
+        let opts = RunOpts::once();
+        let mut outputs = vec![];
+        let err = render_loop(PanickingModule, &opts, |output, _detail| {
+            outputs.push(output);
+        })
+        .unwrap_err();
+        let module_panic = err.downcast::<ModulePanic>().unwrap();
+        assert_eq!(module_panic.message, "boom");
+        assert!(!module_panic.updated);
+        assert_eq!(outputs, vec!["%{F:critical}\u{f071}%{F-}".to_owned()]);
     }
 }
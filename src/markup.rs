@@ -1,9 +1,10 @@
 use std::fmt::Write as _;
 
-use crate::theme;
+use unicode_segmentation::UnicodeSegmentation as _;
 
-#[expect(dead_code)]
-#[derive(Clone, Copy)]
+use crate::{config::BarConfig, theme};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub(crate) enum PolybarActionType {
     ClickLeft = 1,
     ClickMiddle = 2,
@@ -15,7 +16,26 @@ pub(crate) enum PolybarActionType {
     DoubleClickRight = 8,
 }
 
-enum MarkupOp {
+impl TryFrom<u8> for PolybarActionType {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::ClickLeft),
+            2 => Ok(Self::ClickMiddle),
+            3 => Ok(Self::ClickRight),
+            4 => Ok(Self::ScrollUp),
+            5 => Ok(Self::ScrollDown),
+            6 => Ok(Self::DoubleClickLeft),
+            7 => Ok(Self::DoubleClickMiddle),
+            8 => Ok(Self::DoubleClickRight),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum MarkupOp {
     Foreground(theme::Color),
     Underline(theme::Color),
     Overline(theme::Color),
@@ -53,7 +73,6 @@ impl Markup {
         self
     }
 
-    #[expect(dead_code)]
     pub(crate) fn overline(mut self, color: theme::Color) -> Self {
         self.ops.push(MarkupOp::Overline(color));
         self
@@ -65,7 +84,6 @@ impl Markup {
         self
     }
 
-    #[expect(dead_code)]
     pub(crate) fn font(mut self, index: u8) -> Self {
         self.ops.push(MarkupOp::Font(index));
         self
@@ -87,16 +105,16 @@ impl Markup {
         for op in self.ops.iter().rev() {
             match op {
                 MarkupOp::Foreground(color) => {
-                    let _ = write!(r, "%{{F#{:6x}}}", *color as u32);
+                    let _ = write!(r, "%{{F:{}}}", color.name());
                 }
                 MarkupOp::Underline(color) => {
-                    let _ = write!(r, "%{{u#{:06x}}}%{{+u}}", *color as u32);
+                    let _ = write!(r, "%{{u:{}}}%{{+u}}", color.name());
                 }
                 MarkupOp::Overline(color) => {
-                    let _ = write!(r, "%{{o#{:06x}}}%{{+o}}", *color as u32);
+                    let _ = write!(r, "%{{o:{}}}%{{+o}}", color.name());
                 }
                 MarkupOp::Background(color) => {
-                    let _ = write!(r, "%{{b#{:6x}}}", *color as u32);
+                    let _ = write!(r, "%{{b:{}}}", color.name());
                 }
                 MarkupOp::Font(index) => {
                     let _ = write!(r, "%{{T{index}}}");
@@ -124,48 +142,426 @@ impl Markup {
     }
 }
 
+/// Polybar color value of `rgb`, zero padded, ie. `#002b36`
+fn polybar_color(rgb: u32) -> String {
+    format!("#{rgb:06x}")
+}
+
 impl From<Markup> for String {
     fn from(val: Markup) -> Self {
         val.into_string()
     }
 }
 
+/// Text fragment of rendered output, with the markup operations applying to it
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct Span {
+    pub text: String,
+    pub ops: Vec<MarkupOp>,
+}
+
+/// Markup state while parsing, mirroring how polybar applies formatting tags
+#[derive(Default)]
+struct ParseState {
+    fg: Option<theme::Color>,
+    bg: Option<theme::Color>,
+    underline: Option<theme::Color>,
+    underline_enabled: bool,
+    overline: Option<theme::Color>,
+    overline_enabled: bool,
+    font: Option<u8>,
+    actions: Vec<(PolybarActionType, String)>,
+}
+
+impl ParseState {
+    fn ops(&self) -> Vec<MarkupOp> {
+        let mut ops: Vec<MarkupOp> = self
+            .actions
+            .iter()
+            .map(|(type_, command)| MarkupOp::Action {
+                type_: *type_,
+                command: command.to_owned(),
+            })
+            .collect();
+        ops.extend(self.fg.map(MarkupOp::Foreground));
+        ops.extend(self.bg.map(MarkupOp::Background));
+        if self.underline_enabled {
+            ops.push(MarkupOp::Underline(
+                self.underline.unwrap_or(theme::Color::Foreground),
+            ));
+        }
+        if self.overline_enabled {
+            ops.push(MarkupOp::Overline(
+                self.overline.unwrap_or(theme::Color::Foreground),
+            ));
+        }
+        ops.extend(self.font.map(MarkupOp::Font));
+        ops
+    }
+
+    /// Apply a non action formatting tag
+    fn apply(&mut self, tag: &str) {
+        match tag {
+            "F-" => self.fg = None,
+            "B-" | "b-" => self.bg = None,
+            "u-" => self.underline = None,
+            "+u" => self.underline_enabled = true,
+            "-u" => self.underline_enabled = false,
+            "o-" => self.overline = None,
+            "+o" => self.overline_enabled = true,
+            "-o" => self.overline_enabled = false,
+            "T-" => self.font = None,
+            _ => {
+                if let Some(color) = tag.strip_prefix('F').and_then(parse_color) {
+                    self.fg = Some(color);
+                } else if let Some(color) = tag
+                    .strip_prefix('B')
+                    .or_else(|| tag.strip_prefix('b'))
+                    .and_then(parse_color)
+                {
+                    self.bg = Some(color);
+                } else if let Some(color) = tag.strip_prefix('u').and_then(parse_color) {
+                    self.underline = Some(color);
+                } else if let Some(color) = tag.strip_prefix('o').and_then(parse_color) {
+                    self.overline = Some(color);
+                } else if let Some(index) = tag.strip_prefix('T').and_then(|i| i.parse().ok()) {
+                    self.font = Some(index);
+                } else {
+                    log::trace!("Ignoring formatting tag {tag:?}");
+                }
+            }
+        }
+    }
+}
+
+/// Parse color of a formatting tag, as generated by [`Markup::into_string`], ie. `:good`
+fn parse_color(s: &str) -> Option<theme::Color> {
+    theme::Color::from_name(s.strip_prefix(':')?)
+}
+
+/// Parse formatting tags, as generated by [`Markup::into_string`], into spans of text
+pub(crate) fn parse(s: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut state = ParseState::default();
+    let mut text = String::new();
+    let mut rest = s;
+    while let Some((before, after)) = rest.split_once("%{") {
+        text.push_str(before);
+        let Some((tag, after_tag)) = split_tag(after) else {
+            // Unterminated tag, keep it as text
+            text.push_str("%{");
+            rest = after;
+            continue;
+        };
+        if !text.is_empty() {
+            spans.push(Span {
+                text: std::mem::take(&mut text),
+                ops: state.ops(),
+            });
+        }
+        match tag {
+            Tag::Format(tag) => state.apply(tag),
+            Tag::ActionStart { type_, command } => state.actions.push((type_, command)),
+            Tag::ActionEnd => {
+                state.actions.pop();
+            }
+        }
+        rest = after_tag;
+    }
+    text.push_str(rest);
+    if !text.is_empty() {
+        spans.push(Span {
+            text,
+            ops: state.ops(),
+        });
+    }
+    spans
+}
+
+enum Tag<'a> {
+    Format(&'a str),
+    ActionStart {
+        type_: PolybarActionType,
+        command: String,
+    },
+    ActionEnd,
+}
+
+/// Split tag at the start of `s` (after `%{`) from what follows it
+fn split_tag(s: &str) -> Option<(Tag<'_>, &str)> {
+    if let Some(after) = s.strip_prefix("A}") {
+        return Some((Tag::ActionEnd, after));
+    }
+    if let Some(action) = s.strip_prefix('A') {
+        let (button, action) = action.split_once(':')?;
+        let type_ = if button.is_empty() {
+            PolybarActionType::ClickLeft
+        } else {
+            PolybarActionType::try_from(button.parse::<u8>().ok()?).ok()?
+        };
+        // Command ends at the first unescaped colon
+        let mut command = String::new();
+        let mut chars = action.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' if action.get(i + 1..).is_some_and(|a| a.starts_with(':')) => {
+                    command.push(':');
+                    chars.next();
+                }
+                ':' => {
+                    let after = action.get(i + 1..)?.strip_prefix('}')?;
+                    return Some((Tag::ActionStart { type_, command }, after));
+                }
+                c => command.push(c),
+            }
+        }
+        return None;
+    }
+    let (tag, after) = s.split_once('}')?;
+    Some((Tag::Format(tag), after))
+}
+
+/// Convert rendered output `s` to polybar markup, with colors from the active palette
+pub(crate) fn to_polybar(s: &str) -> String {
+    let mut r = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some((before, after)) = rest.split_once("%{") {
+        r.push_str(before);
+        let Some((tag, after_tag)) = split_tag(after) else {
+            // Unterminated tag, keep it as text
+            r.push_str("%{");
+            rest = after;
+            continue;
+        };
+        let color_tag = match tag {
+            Tag::Format(tag) => tag
+                .split_at_checked(1)
+                .filter(|(kind, _color)| ["F", "B", "b", "u", "o"].contains(kind))
+                .and_then(|(kind, color)| Some((kind, parse_color(color)?))),
+            Tag::ActionStart { .. } | Tag::ActionEnd => None,
+        };
+        if let Some((kind, color)) = color_tag {
+            let _ = write!(r, "%{{{kind}{}}}", polybar_color(color.rgb()));
+        } else {
+            r.push_str("%{");
+            r.push_str(after.strip_suffix(after_tag).unwrap_or_default());
+        }
+        rest = after_tag;
+    }
+    r.push_str(rest);
+    r
+}
+
+/// Render already rendered output `s` with a single color, dropping its own foreground colors
+pub(crate) fn recolor(s: &str, color: theme::Color) -> String {
+    let mut r = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some((before, after)) = rest.split_once("%{") {
+        r.push_str(before);
+        let Some((tag, after_tag)) = split_tag(after) else {
+            // Unterminated tag, keep it as text
+            r.push_str("%{");
+            rest = after;
+            continue;
+        };
+        if !matches!(tag, Tag::Format(tag) if tag.starts_with('F')) {
+            r.push_str("%{");
+            r.push_str(after.strip_suffix(after_tag).unwrap_or_default());
+        }
+        rest = after_tag;
+    }
+    r.push_str(rest);
+    Markup::new(r).fg(color).into_string()
+}
+
 /// Vertical bars, from lowest to highest level
-const RAMP_ICONS: [&str; 8] = ["▁", "▂", "▃", "▄", "▅", "▆", "▇", "█"];
+pub(crate) const RAMP_ICONS: [&str; 8] = ["▁", "▂", "▃", "▄", "▅", "▆", "▇", "█"];
+
+/// Level of `frac`, clamped to 0-1, among `levels` ones, from 1 to `levels`
+pub(crate) fn ramp_level(frac: f64, levels: usize) -> usize {
+    #[expect(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    let level = (frac.clamp(0.0, 1.0) * levels as f64).ceil() as usize;
+    level.max(1)
+}
+
+/// Vertical bar of `frac`, clamped to 0-1
+pub(crate) fn ramp_icon(frac: f64) -> &'static str {
+    #[expect(clippy::indexing_slicing)]
+    RAMP_ICONS[ramp_level(frac, RAMP_ICONS.len()) - 1]
+}
 
 /// Render `frac`, clamped to 0-1, as a vertical bar
 pub(crate) fn ramp(frac: f64, color: theme::Color) -> String {
+    Markup::new(ramp_icon(frac)).fg(color).into_string()
+}
+
+/// Color of `frac` from `stops`, ie. the one of the last stop it reaches, stops being fractions sorted in
+/// increasing order
+fn stop_color(frac: f64, stops: &[(f64, theme::Color)]) -> theme::Color {
+    stops
+        .iter()
+        .rev()
+        .find(|(from, _color)| frac >= *from)
+        .map_or(theme::Color::Foreground, |(_from, color)| *color)
+}
+
+/// Number of `count` parts filled by `frac`, clamped to 0-1
+fn filled_count(frac: f64, count: usize) -> usize {
     #[expect(
         clippy::cast_possible_truncation,
         clippy::cast_precision_loss,
         clippy::cast_sign_loss
     )]
-    let level = (frac.clamp(0.0, 1.0) * RAMP_ICONS.len() as f64).ceil() as usize;
-    #[expect(clippy::indexing_slicing)]
-    let icon = RAMP_ICONS[level.saturating_sub(1)];
-    Markup::new(icon).fg(color).into_string()
+    let filled = (frac.clamp(0.0, 1.0) * count as f64).round() as usize;
+    filled
+}
+
+/// Render `frac`, clamped to 0-1, as a horizontal bar, filled with the color of `stops` it reaches
+pub(crate) fn bar(frac: f64, cfg: &BarConfig, stops: &[(f64, theme::Color)]) -> String {
+    let width = cfg.width.get();
+    let filled = filled_count(frac, width);
+    let mut r = String::new();
+    if filled > 0 {
+        r.push_str(
+            &Markup::new(cfg.filled.repeat(filled))
+                .fg(stop_color(frac, stops))
+                .into_string(),
+        );
+    }
+    if filled < width {
+        r.push_str(
+            &Markup::new(cfg.empty.repeat(width - filled))
+                .fg(theme::Color::Unfocused)
+                .into_string(),
+        );
+    }
+    match cfg.font {
+        Some(font) => Markup::new(r).font(font).into_string(),
+        None => r,
+    }
+}
+
+/// Render plain text `s` as a gauge of `frac`, clamped to 0-1, overlining as many of its characters, with the
+/// color of `stops` it reaches
+pub(crate) fn gauge(s: &str, frac: f64, cfg: &BarConfig, stops: &[(f64, theme::Color)]) -> String {
+    let filled = filled_count(frac, s.graphemes(true).count());
+    let mut r = String::new();
+    if filled > 0 {
+        r.push_str(
+            &Markup::new(s.graphemes(true).take(filled).collect::<String>())
+                .overline(stop_color(frac, stops))
+                .into_string(),
+        );
+    }
+    r.extend(s.graphemes(true).skip(filled));
+    match cfg.font {
+        Some(font) => Markup::new(r).font(font).into_string(),
+        None => r,
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::num::NonZeroUsize;
+
     use super::*;
 
     #[test]
     fn test_markup() {
         assert_eq!(
             Markup::new("").fg(theme::Color::MainIcon).into_string(),
-            "%{F#f1e9d2}%{F-}"
+            "%{F:main_icon}%{F-}"
         );
     }
 
+    #[test]
+    fn test_polybar_color() {
+        assert_eq!(polybar_color(0x00_2b_36), "#002b36");
+        assert_eq!(polybar_color(0x0f_00_00), "#0f0000");
+        assert_eq!(polybar_color(0xf1_e9_d2), "#f1e9d2");
+    }
+
     #[test]
     fn test_ramp() {
-        assert_eq!(ramp(1.1, theme::Color::Good), "%{F#819500}█%{F-}");
-        assert_eq!(ramp(1.0, theme::Color::Good), "%{F#819500}█%{F-}");
-        assert_eq!(ramp(0.5, theme::Color::Good), "%{F#819500}▄%{F-}");
-        assert_eq!(ramp(0.125, theme::Color::Notice), "%{F#ac8300}▁%{F-}");
-        assert_eq!(ramp(0.126, theme::Color::Notice), "%{F#ac8300}▂%{F-}");
-        assert_eq!(ramp(0.0, theme::Color::Attention), "%{F#d56500}▁%{F-}");
+        assert_eq!(ramp(1.1, theme::Color::Good), "%{F:good}█%{F-}");
+        assert_eq!(ramp(1.0, theme::Color::Good), "%{F:good}█%{F-}");
+        assert_eq!(ramp(0.5, theme::Color::Good), "%{F:good}▄%{F-}");
+        assert_eq!(ramp(0.125, theme::Color::Notice), "%{F:notice}▁%{F-}");
+        assert_eq!(ramp(0.126, theme::Color::Notice), "%{F:notice}▂%{F-}");
+        assert_eq!(ramp(0.0, theme::Color::Attention), "%{F:attention}▁%{F-}");
+    }
+
+    #[test]
+    fn test_bar() {
+        let cfg = BarConfig::default();
+        let stops = [(0.0, theme::Color::Attention), (0.5, theme::Color::Good)];
+        assert_eq!(bar(0.0, &cfg, &stops), "%{F:unfocused}╍╍╍╍╍%{F-}");
+        assert_eq!(
+            bar(0.3, &cfg, &stops),
+            "%{F:attention}━━%{F-}%{F:unfocused}╍╍╍%{F-}"
+        );
+        assert_eq!(
+            bar(0.5, &cfg, &stops),
+            "%{F:good}━━━%{F-}%{F:unfocused}╍╍%{F-}"
+        );
+        assert_eq!(bar(1.2, &cfg, &[]), "%{F:foreground}━━━━━%{F-}");
+        assert_eq!(
+            bar(
+                1.0,
+                &BarConfig {
+                    width: NonZeroUsize::new(2).unwrap(),
+                    font: Some(2),
+                    ..BarConfig::default()
+                },
+                &stops
+            ),
+            "%{T2}%{F:good}━━%{F-}%{T-}"
+        );
+    }
+
+    #[test]
+    fn test_gauge() {
+        let cfg = BarConfig::default();
+        let stops = [(0.0, theme::Color::Attention)];
+        assert_eq!(gauge("m0 50%", 0.0, &cfg, &stops), "m0 50%");
+        assert_eq!(
+            gauge("m0 50%", 0.5, &cfg, &stops),
+            "%{o:attention}%{+o}m0 %{-o}50%"
+        );
+        assert_eq!(
+            gauge(
+                "é1",
+                1.0,
+                &BarConfig {
+                    font: Some(3),
+                    ..BarConfig::default()
+                },
+                &stops
+            ),
+            "%{T3}%{o:attention}%{+o}é1%{-o}%{T-}"
+        );
+    }
+
+    #[test]
+    fn test_recolor() {
+        assert_eq!(recolor("", theme::Color::Unfocused), "%{F:unfocused}%{F-}");
+        let rendered = format!(
+            "{} 12% {}",
+            Markup::new("I").fg(theme::Color::MainIcon).into_string(),
+            Markup::new("hot")
+                .fg(theme::Color::Critical)
+                .underline(theme::Color::Critical)
+                .action(PolybarActionType::ClickLeft, "notify-send a:b")
+                .into_string()
+        );
+        assert_eq!(
+            recolor(&rendered, theme::Color::Unfocused),
+            "%{F:unfocused}I 12% %{A1:notify-send a\\:b:}%{u:critical}%{+u}hot%{-u}%{A}%{F-}"
+        );
     }
 
     #[test]
@@ -180,4 +576,104 @@ mod tests {
             "%{A3:this contains a \\: and ; and \\:}:)%{A}"
         );
     }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(""), vec![]);
+        assert_eq!(
+            parse("a b"),
+            vec![Span {
+                text: "a b".to_owned(),
+                ops: vec![]
+            }]
+        );
+
+        let rendered = format!(
+            "{} 12% {}",
+            Markup::new("I").fg(theme::Color::MainIcon).into_string(),
+            Markup::new("hot")
+                .fg(theme::Color::Critical)
+                .underline(theme::Color::Critical)
+                .action(PolybarActionType::ClickLeft, "notify-send a:b")
+                .into_string()
+        );
+        assert_eq!(
+            parse(&rendered),
+            vec![
+                Span {
+                    text: "I".to_owned(),
+                    ops: vec![MarkupOp::Foreground(theme::Color::MainIcon)]
+                },
+                Span {
+                    text: " 12% ".to_owned(),
+                    ops: vec![]
+                },
+                Span {
+                    text: "hot".to_owned(),
+                    ops: vec![
+                        MarkupOp::Action {
+                            type_: PolybarActionType::ClickLeft,
+                            command: "notify-send a:b".to_owned()
+                        },
+                        MarkupOp::Foreground(theme::Color::Critical),
+                        MarkupOp::Underline(theme::Color::Critical)
+                    ]
+                },
+            ]
+        );
+
+        // Nested actions
+        assert_eq!(
+            parse("%{A4:up:}%{A5:down:}x%{A}y%{A}"),
+            vec![
+                Span {
+                    text: "x".to_owned(),
+                    ops: vec![
+                        MarkupOp::Action {
+                            type_: PolybarActionType::ScrollUp,
+                            command: "up".to_owned()
+                        },
+                        MarkupOp::Action {
+                            type_: PolybarActionType::ScrollDown,
+                            command: "down".to_owned()
+                        },
+                    ]
+                },
+                Span {
+                    text: "y".to_owned(),
+                    ops: vec![MarkupOp::Action {
+                        type_: PolybarActionType::ScrollUp,
+                        command: "up".to_owned()
+                    }]
+                },
+            ]
+        );
+
+        // Unterminated tag
+        assert_eq!(
+            parse("100%{"),
+            vec![Span {
+                text: "100%{".to_owned(),
+                ops: vec![]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_to_polybar() {
+        assert_eq!(to_polybar("a 100%{"), "a 100%{");
+        let rendered = Markup::new(
+            Markup::new("I")
+                .fg(theme::Color::MainIcon)
+                .underline(theme::Color::Good)
+                .into_string(),
+        )
+        .action(PolybarActionType::ClickLeft, "notify-send a:b")
+        .font(2)
+        .into_string();
+        assert_eq!(
+            to_polybar(&rendered),
+            "%{T2}%{A1:notify-send a\\:b:}%{u#819500}%{+u}%{F#f1e9d2}I%{F-}%{-u}%{A}%{T-}"
+        );
+    }
 }
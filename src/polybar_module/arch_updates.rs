@@ -9,41 +9,49 @@ use anyhow::Context as _;
 use backon::BackoffBuilder as _;
 
 use crate::{
+    alert,
+    config::{self, ArchUpdatesModuleConfig},
+    icons::Icon,
     markup,
     polybar_module::{
         NetworkMode, PolybarModuleEnv, RenderablePolybarModule, sleep_suspend_aware,
         wait_network_ready,
     },
-    theme::{self, ICON_WARNING},
+    theme,
 };
 
 pub(crate) struct ArchUpdatesModule {
+    cfg: ArchUpdatesModuleConfig,
+    update_interval: Duration,
     xdg_dirs: xdg::BaseDirectories,
     env: PolybarModuleEnv,
     server_error_backoff_builder: backon::ExponentialBuilder,
     server_error_backoff: backon::ExponentialBackoff,
 }
 
-#[derive(Debug, Eq, PartialEq)]
-#[expect(clippy::struct_field_names)]
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
 pub(crate) struct ArchUpdatesModuleState {
     repo_update_count: usize,
     repo_security_update_count: usize,
     aur_update_count: usize,
+    /// Pending updates, one per line as output by checkupdates and the AUR helper, ie. `pkg 1.0-1 -> 1.1-1`
+    updates: Vec<String>,
 }
 
 impl ArchUpdatesModule {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(cfg: &ArchUpdatesModuleConfig) -> Self {
         let xdg_dirs = xdg::BaseDirectories::new();
         let env = PolybarModuleEnv::new();
         let server_error_backoff_builder = backon::ExponentialBuilder::default()
             .with_jitter()
             .with_factor(3.0)
-            .with_min_delay(Duration::from_mins(15))
-            .with_max_delay(Duration::from_hours(6))
+            .with_min_delay(Duration::from_secs(cfg.error_min_delay_s))
+            .with_max_delay(Duration::from_secs(cfg.error_max_delay_s))
             .without_max_times();
         let server_error_backoff = server_error_backoff_builder.build();
         Self {
+            cfg: cfg.clone(),
+            update_interval: Duration::from_secs(cfg.update_interval_s.get()),
             xdg_dirs,
             env,
             server_error_backoff_builder,
@@ -122,16 +130,21 @@ impl ArchUpdatesModule {
         let output_yay_str = String::from_utf8_lossy(&output_aur.stdout);
         let aur_update_count = output_yay_str.lines().count();
 
+        let updates = output_cu_str
+            .lines()
+            .chain(output_yay_str.lines())
+            .map(ToOwned::to_owned)
+            .collect();
+
         Ok(ArchUpdatesModuleState {
             repo_update_count: repo_updates.len(),
             repo_security_update_count,
             aur_update_count,
+            updates,
         })
     }
 }
 
-pub(crate) const ICON_UPDATE: &str = "";
-
 impl RenderablePolybarModule for ArchUpdatesModule {
     type State = Option<ArchUpdatesModuleState>;
 
@@ -141,7 +154,7 @@ impl RenderablePolybarModule for ArchUpdatesModule {
                 // Nominal
                 Some(_) => {
                     self.server_error_backoff = self.server_error_backoff_builder.build();
-                    Duration::from_hours(3)
+                    self.update_interval
                 }
                 // Error occured
                 None => self.server_error_backoff.next().unwrap(),
@@ -171,7 +184,7 @@ impl RenderablePolybarModule for ArchUpdatesModule {
                 } else {
                     let mut r = format!(
                         "{} {}",
-                        markup::Markup::new(ICON_UPDATE)
+                        markup::Markup::new(Icon::Update)
                             .fg(theme::Color::MainIcon)
                             .into_string(),
                         state.repo_update_count
@@ -188,11 +201,50 @@ impl RenderablePolybarModule for ArchUpdatesModule {
                     r
                 }
             }
-            None => markup::Markup::new(ICON_WARNING)
+            None => markup::Markup::new(Icon::Warning)
                 .fg(theme::Color::Attention)
                 .into_string(),
         }
     }
+
+    fn render_detail(&self, state: &Self::State) -> Option<String> {
+        state
+            .as_ref()
+            .filter(|s| !s.updates.is_empty())
+            .map(|s| s.updates.join("\n"))
+    }
+
+    fn alerts(&self, prev_state: &Self::State, state: &Self::State) -> Vec<alert::Alert> {
+        match (prev_state, state) {
+            (Some(prev_state), Some(state))
+                if state.repo_security_update_count > prev_state.repo_security_update_count =>
+            {
+                vec![alert::Alert {
+                    rule: "security_updates",
+                    urgency: alert::Urgency::Critical,
+                    summary: "Security updates available".to_owned(),
+                    body: format!(
+                        "{} security update(s) pending",
+                        state.repo_security_update_count
+                    ),
+                }]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn reload_config(&mut self, cfg: &config::Config) -> anyhow::Result<bool> {
+        let cfg = cfg
+            .module
+            .as_ref()
+            .and_then(|m| m.arch_updates.clone())
+            .unwrap_or_default();
+        if cfg == self.cfg {
+            return Ok(false);
+        }
+        *self = Self::new(&cfg);
+        Ok(true)
+    }
 }
 
 #[cfg(test)]
@@ -202,12 +254,13 @@ mod tests {
 
     #[test]
     fn test_render() {
-        let module = ArchUpdatesModule::new();
+        let module = ArchUpdatesModule::new(&ArchUpdatesModuleConfig::default());
 
         let state = Some(ArchUpdatesModuleState {
             repo_update_count: 0,
             repo_security_update_count: 0,
             aur_update_count: 0,
+            updates: vec![],
         });
         assert_eq!(module.render(&state), "");
 
@@ -215,44 +268,78 @@ mod tests {
             repo_update_count: 12,
             repo_security_update_count: 0,
             aur_update_count: 0,
+            updates: vec![],
         });
-        assert_eq!(module.render(&state), "%{F#f1e9d2}%{F-} 12");
+        assert_eq!(module.render(&state), "%{F:main_icon}%{F-} 12");
 
         let state = Some(ArchUpdatesModuleState {
             repo_update_count: 12,
             repo_security_update_count: 2,
             aur_update_count: 0,
+            updates: vec![],
         });
         assert_eq!(
             module.render(&state),
-            "%{F#f1e9d2}%{F-} 12%{F#d56500}(2)%{F-}"
+            "%{F:main_icon}%{F-} 12%{F:attention}(2)%{F-}"
         );
 
         let state = Some(ArchUpdatesModuleState {
             repo_update_count: 12,
             repo_security_update_count: 2,
             aur_update_count: 3,
+            updates: vec![],
         });
         assert_eq!(
             module.render(&state),
-            "%{F#f1e9d2}%{F-} 12%{F#d56500}(2)%{F-}+3"
+            "%{F:main_icon}%{F-} 12%{F:attention}(2)%{F-}+3"
         );
 
         let state = Some(ArchUpdatesModuleState {
             repo_update_count: 12,
             repo_security_update_count: 0,
             aur_update_count: 3,
+            updates: vec![],
         });
-        assert_eq!(module.render(&state), "%{F#f1e9d2}%{F-} 12+3");
+        assert_eq!(module.render(&state), "%{F:main_icon}%{F-} 12+3");
 
         let state = Some(ArchUpdatesModuleState {
             repo_update_count: 0,
             repo_security_update_count: 0,
             aur_update_count: 3,
+            updates: vec![],
         });
-        assert_eq!(module.render(&state), "%{F#f1e9d2}%{F-} 0+3");
+        assert_eq!(module.render(&state), "%{F:main_icon}%{F-} 0+3");
 
         let state = None;
-        assert_eq!(module.render(&state), "%{F#d56500}%{F-}");
+        assert_eq!(module.render(&state), "%{F:attention}%{F-}");
+    }
+
+    #[test]
+    fn test_render_detail() {
+        let module = ArchUpdatesModule::new(&ArchUpdatesModuleConfig::default());
+
+        let state = Some(ArchUpdatesModuleState {
+            repo_update_count: 1,
+            repo_security_update_count: 0,
+            aur_update_count: 1,
+            updates: vec![
+                "glibc 2.42-1 -> 2.42-2".to_owned(),
+                "yay 12.5.0-1 -> 12.5.1-1".to_owned(),
+            ],
+        });
+        assert_eq!(
+            module.render_detail(&state).unwrap(),
+            "glibc 2.42-1 -> 2.42-2\nyay 12.5.0-1 -> 12.5.1-1"
+        );
+
+        let state = Some(ArchUpdatesModuleState {
+            repo_update_count: 0,
+            repo_security_update_count: 0,
+            aur_update_count: 0,
+            updates: vec![],
+        });
+        assert_eq!(module.render_detail(&state), None);
+
+        assert_eq!(module.render_detail(&None), None);
     }
 }
@@ -1,64 +1,96 @@
+use std::{
+    process::{Command, Stdio},
+    sync::mpsc::{Receiver, Sender, channel},
+};
+
 use super::is_systemd_user_unit_running;
-use crate::{markup, polybar_module::RenderablePolybarModule, theme};
+use crate::{
+    control::{self, ControlCommand},
+    icons::Icon,
+    markup,
+    polybar_module::RenderablePolybarModule,
+    theme,
+};
 
 pub(crate) struct AutolockModule {
-    signals: signal_hook::iterator::Signals,
+    refresh_tx: Sender<()>,
+    refresh_rx: Receiver<()>,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, serde::Serialize)]
 pub(crate) struct AutolockModuleState {
     enabled: bool,
 }
 
+const UNIT: &str = "autolock.service";
+
 impl AutolockModule {
-    pub(crate) fn new() -> anyhow::Result<Self> {
-        let signals = signal_hook::iterator::Signals::new([signal_hook::consts::signal::SIGUSR1])?;
-        Ok(Self { signals })
+    pub(crate) fn new() -> Self {
+        let (refresh_tx, refresh_rx) = channel();
+        Self {
+            refresh_tx,
+            refresh_rx,
+        }
     }
-}
 
-const ICON_AUTOLOCK_ENABLED: &str = "󱫗";
-const ICON_AUTOLOCK_DISABLED: &str = "󱫕";
+    fn handle(refresh_tx: &Sender<()>, command: &ControlCommand) -> anyhow::Result<()> {
+        match command {
+            ControlCommand::refresh => {}
+            ControlCommand::toggle => {
+                let action = if is_systemd_user_unit_running(UNIT) {
+                    "stop"
+                } else {
+                    "start"
+                };
+                Command::new("systemctl")
+                    .args(["--user", action, UNIT])
+                    // Standard output may be the bar protocol stream, so it must not be inherited
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .status()?
+                    .exit_ok()?;
+            }
+            _ => return Err(control::unsupported(command)),
+        }
+        refresh_tx.send(())?;
+        Ok(())
+    }
+}
 
 impl RenderablePolybarModule for AutolockModule {
     type State = AutolockModuleState;
 
     fn wait_update(&mut self, prev_state: Option<&Self::State>) {
         if let Some(_prev_state) = prev_state {
-            self.signals.forever().next();
+            // Sender is owned by the module too, so this never fails
+            let _ = self.refresh_rx.recv();
         }
     }
 
     fn update(&mut self) -> Self::State {
         Self::State {
-            enabled: is_systemd_user_unit_running("autolock.service"),
+            enabled: is_systemd_user_unit_running(UNIT),
         }
     }
 
     fn render(&self, state: &Self::State) -> String {
+        let action = control::action("autolock", &ControlCommand::toggle);
         if state.enabled {
-            markup::Markup::new(ICON_AUTOLOCK_ENABLED)
-                .action(
-                    markup::PolybarActionType::ClickLeft,
-                    format!(
-                        "systemctl --user stop autolock.service && pkill -USR1 -f '{} autolock$'",
-                        env!("CARGO_PKG_NAME")
-                    ),
-                )
+            markup::Markup::new(Icon::AutolockEnabled)
+                .action(markup::PolybarActionType::ClickLeft, action)
                 .into_string()
         } else {
-            markup::Markup::new(ICON_AUTOLOCK_DISABLED)
+            markup::Markup::new(Icon::AutolockDisabled)
                 .underline(theme::Color::Notice)
-                .action(
-                    markup::PolybarActionType::ClickLeft,
-                    format!(
-                        "systemctl --user start autolock.service && pkill -USR1 -f '{} autolock$'",
-                        env!("CARGO_PKG_NAME")
-                    ),
-                )
+                .action(markup::PolybarActionType::ClickLeft, action)
                 .into_string()
         }
     }
+
+    fn control_handler(&self) -> Option<control::Handler> {
+        let refresh_tx = self.refresh_tx.clone();
+        Some(Box::new(move |command| Self::handle(&refresh_tx, command)))
+    }
 }
 
 #[cfg(test)]
@@ -68,20 +100,30 @@ mod tests {
 
     #[test]
     fn test_render() {
-        let module = AutolockModule::new().unwrap();
+        let module = AutolockModule::new();
 
         let state = AutolockModuleState { enabled: true };
         assert_eq!(
             module.render(&state),
-            "%{A1:systemctl --user stop autolock.service && pkill -USR1 -f \'polybar-modules autolock$\':}󱫗%{A}"
-                .to_owned()
+            "%{A1:polybar-modules ctl autolock toggle:}󱫗%{A}"
         );
 
         let state = AutolockModuleState { enabled: false };
         assert_eq!(
             module.render(&state),
-            "%{A1:systemctl --user start autolock.service && pkill -USR1 -f \'polybar-modules autolock$\':}%{u#ac8300}%{+u}󱫕%{-u}%{A}"
-                .to_owned()
+            "%{A1:polybar-modules ctl autolock toggle:}%{u:notice}%{+u}󱫕%{-u}%{A}"
         );
     }
+
+    #[test]
+    fn test_control() {
+        let module = AutolockModule::new();
+        let mut handler = module.control_handler().unwrap();
+
+        handler(&ControlCommand::refresh).unwrap();
+        assert!(module.refresh_rx.try_recv().is_ok());
+
+        assert!(handler(&ControlCommand::select_sink { id: 1 }).is_err());
+        assert!(module.refresh_rx.try_recv().is_err());
+    }
 }
@@ -1,17 +1,30 @@
 use std::{error::Error, fs, result::Result, thread::sleep, time::Duration};
 
-use crate::{markup, polybar_module::RenderablePolybarModule, theme};
+use crate::{
+    alert,
+    config::{self, BatteryMouseModuleConfig, LevelStyle},
+    icons::Icon,
+    markup,
+    polybar_module::{PolybarModuleEnv, RenderablePolybarModule},
+    theme,
+};
 
-pub(crate) struct BatteryMouseModule {}
+pub(crate) struct BatteryMouseModule {
+    cfg: BatteryMouseModuleConfig,
+    env: PolybarModuleEnv,
+}
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, serde::Serialize)]
 pub(crate) struct BatteryMouseModuleState {
     levels: Vec<(String, Option<u8>)>,
 }
 
 impl BatteryMouseModule {
-    pub(crate) fn new() -> Self {
-        Self {}
+    pub(crate) fn new(cfg: BatteryMouseModuleConfig) -> Self {
+        Self {
+            cfg,
+            env: PolybarModuleEnv::new(),
+        }
     }
 
     fn sysfs_capacity_level_to_prct(s: &str) -> Option<u8> {
@@ -28,16 +41,58 @@ impl BatteryMouseModule {
             v => unreachable!("Unexpected value: {v:?}"),
         }
     }
-}
 
-const ICON_MOUSE: &str = "󰍽";
+    fn level_color(&self, level: u8) -> Option<theme::Color> {
+        if level < self.cfg.attention_level_prct {
+            Some(theme::Color::Attention)
+        } else if level < self.cfg.notice_level_prct {
+            Some(theme::Color::Notice)
+        } else {
+            None
+        }
+    }
+
+    fn render_level(&self, name: &str, level: u8) -> String {
+        let frac = f64::from(level) / 100.0;
+        let stops = [
+            (0.0, theme::Color::Attention),
+            (
+                f64::from(self.cfg.attention_level_prct) / 100.0,
+                theme::Color::Notice,
+            ),
+            (
+                f64::from(self.cfg.notice_level_prct) / 100.0,
+                theme::Color::Foreground,
+            ),
+        ];
+        let mut markup = match self.cfg.style {
+            LevelStyle::Text => markup::Markup::new(format!("{name} {level}%")),
+            LevelStyle::Bar => {
+                return format!("{name} {}", markup::bar(frac, &self.cfg.bar, &stops));
+            }
+            LevelStyle::Gauge => markup::Markup::new(markup::gauge(
+                &format!("{name} {level}%"),
+                frac,
+                &self.cfg.bar,
+                &stops,
+            )),
+        };
+        if let Some(color) = self.level_color(level) {
+            markup = markup.fg(color);
+        }
+        markup.into_string()
+    }
+}
 
 impl RenderablePolybarModule for BatteryMouseModule {
     type State = BatteryMouseModuleState;
 
     fn wait_update(&mut self, prev_state: Option<&Self::State>) {
         if prev_state.is_some() {
-            sleep(Duration::from_secs(5));
+            sleep(
+                self.env
+                    .poll_interval(Duration::from_secs(self.cfg.update_interval_s.get())),
+            );
         }
     }
 
@@ -81,36 +136,64 @@ impl RenderablePolybarModule for BatteryMouseModule {
         let mut fragments: Vec<String> = Vec::new();
         if !state.levels.is_empty() {
             fragments.push(
-                markup::Markup::new(ICON_MOUSE)
+                markup::Markup::new(Icon::Mouse)
                     .fg(theme::Color::MainIcon)
                     .into_string(),
             );
             for (name, level) in &state.levels {
                 fragments.push(match level {
-                    Some(level) => {
-                        let mut markup = markup::Markup::new(format!("{name} {level}%"));
-                        if level < &40 {
-                            markup = markup.fg(theme::Color::Attention);
-                        } else if level < &50 {
-                            markup = markup.fg(theme::Color::Notice);
-                        }
-                        markup.into_string()
-                    }
+                    Some(level) => self.render_level(name, *level),
                     None => format!("{name} ?"),
                 });
             }
         }
         fragments.join(" ")
     }
+
+    fn alerts(&self, prev_state: &Self::State, state: &Self::State) -> Vec<alert::Alert> {
+        let threshold = self.cfg.alert_level_prct;
+        state
+            .levels
+            .iter()
+            .filter_map(|(name, level)| {
+                let level = (*level)?;
+                let prev_level = prev_state
+                    .levels
+                    .iter()
+                    .find(|(prev_name, _)| prev_name == name)
+                    .and_then(|(_, prev_level)| *prev_level)?;
+                (prev_level >= threshold && level < threshold).then(|| alert::Alert {
+                    rule: "battery_low",
+                    urgency: alert::Urgency::Normal,
+                    summary: format!("{name} battery low"),
+                    body: format!("{level}% left"),
+                })
+            })
+            .collect()
+    }
+
+    fn reload_config(&mut self, cfg: &config::Config) -> anyhow::Result<bool> {
+        let cfg = cfg
+            .module
+            .as_ref()
+            .and_then(|m| m.battery_mouse.clone())
+            .unwrap_or_default();
+        if cfg == self.cfg {
+            return Ok(false);
+        }
+        *self = Self::new(cfg);
+        Ok(true)
+    }
 }
 
 #[cfg(test)]
+#[expect(clippy::shadow_unrelated)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_render() {
-        let module = BatteryMouseModule::new();
+        let module = BatteryMouseModule::new(BatteryMouseModuleConfig::default());
 
         let levels = vec![
             ("m0".to_owned(), Some(100)),
@@ -124,7 +207,56 @@ mod tests {
         let state = BatteryMouseModuleState { levels };
         assert_eq!(
             module.render(&state),
-            "%{F#f1e9d2}󰍽%{F-} m0 100% m1 50% %{F#ac8300}m2 49%%{F-} %{F#d56500}m3 30%%{F-} %{F#d56500}m4 29%%{F-} %{F#d56500}m5 5%%{F-} m6 ?"
+            "%{F:main_icon}󰍽%{F-} m0 100% m1 50% %{F:notice}m2 49%%{F-} %{F:attention}m3 30%%{F-} %{F:attention}m4 29%%{F-} %{F:attention}m5 5%%{F-} m6 ?"
+        );
+    }
+
+    #[test]
+    fn test_render_style() {
+        let module = BatteryMouseModule::new(BatteryMouseModuleConfig {
+            style: LevelStyle::Bar,
+            ..BatteryMouseModuleConfig::default()
+        });
+        let state = BatteryMouseModuleState {
+            levels: vec![("m0".to_owned(), Some(100)), ("m1".to_owned(), Some(20))],
+        };
+        assert_eq!(
+            module.render(&state),
+            "%{F:main_icon}󰍽%{F-} m0 %{F:foreground}━━━━━%{F-} m1 %{F:attention}━%{F-}%{F:unfocused}╍╍╍╍%{F-}"
+        );
+
+        let module = BatteryMouseModule::new(BatteryMouseModuleConfig {
+            style: LevelStyle::Gauge,
+            ..BatteryMouseModuleConfig::default()
+        });
+        assert_eq!(
+            module.render(&state),
+            "%{F:main_icon}󰍽%{F-} %{o:foreground}%{+o}m0 100%%{-o} %{F:attention}%{o:attention}%{+o}m%{-o}1 20%%{F-}"
+        );
+    }
+
+    #[test]
+    fn test_alerts() {
+        let module = BatteryMouseModule::new(BatteryMouseModuleConfig::default());
+        let prev_state = BatteryMouseModuleState {
+            levels: vec![("m0".to_owned(), Some(31)), ("m1".to_owned(), Some(30))],
+        };
+        let state = BatteryMouseModuleState {
+            levels: vec![
+                ("m0".to_owned(), Some(29)),
+                ("m1".to_owned(), Some(30)),
+                ("m2".to_owned(), Some(10)),
+            ],
+        };
+        assert_eq!(
+            module.alerts(&prev_state, &state),
+            vec![alert::Alert {
+                rule: "battery_low",
+                urgency: alert::Urgency::Normal,
+                summary: "m0 battery low".to_owned(),
+                body: "29% left".to_owned(),
+            }]
         );
+        assert!(module.alerts(&state, &state).is_empty());
     }
 }
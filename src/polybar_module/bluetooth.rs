@@ -8,7 +8,13 @@ use std::{
 
 use anyhow::Context as _;
 
-use crate::{markup, polybar_module::RenderablePolybarModule, theme};
+use crate::{
+    control::{self, ControlCommand},
+    icons::Icon,
+    layout, markup,
+    polybar_module::{RenderablePolybarModule, serialize_display},
+    theme,
+};
 
 pub(crate) struct BluetoothModule {
     controller: BluetoothController,
@@ -16,10 +22,11 @@ pub(crate) struct BluetoothModule {
     bluetoothctl_child: Child,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, serde::Serialize)]
 struct BluetoothDevice {
     connected: bool,
     name: String,
+    #[serde(serialize_with = "serialize_display")]
     addr: macaddr::MacAddr6,
 }
 
@@ -28,7 +35,7 @@ struct BluetoothController {
     addr: macaddr::MacAddr6,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, serde::Serialize)]
 pub(crate) struct BluetoothModuleState {
     controller_powered: bool,
     devices: Vec<BluetoothDevice>,
@@ -52,6 +59,7 @@ impl BluetoothModule {
     fn bluetoothcl_cmd(args: &[&str]) -> anyhow::Result<String> {
         let output = Command::new("bluetoothctl")
             .args(args)
+            .stdin(Stdio::null())
             .stderr(Stdio::null())
             .output()?;
         output
@@ -140,9 +148,6 @@ impl Drop for BluetoothModule {
     }
 }
 
-const ICON_BLUETOOTH_ENABLED: &str = "󰂯";
-const ICON_BLUETOOTH_DISABLED: &str = "󰂲";
-
 impl RenderablePolybarModule for BluetoothModule {
     type State = BluetoothModuleState;
 
@@ -243,34 +248,32 @@ impl RenderablePolybarModule for BluetoothModule {
     }
 
     fn render(&self, state: &Self::State) -> String {
-        let mut fragments = vec![if state.controller_powered {
-            markup::Markup::new(ICON_BLUETOOTH_ENABLED)
-                .fg(theme::Color::MainIcon)
-                .action(
-                    markup::PolybarActionType::ClickLeft,
-                    "bluetoothctl power off",
-                )
-        } else {
-            markup::Markup::new(ICON_BLUETOOTH_DISABLED)
-                .fg(theme::Color::MainIcon)
-                .action(
-                    markup::PolybarActionType::ClickLeft,
-                    "bluetoothctl power on",
-                )
-        }];
+        let mut fragments = vec![
+            markup::Markup::new(if state.controller_powered {
+                Icon::BluetoothEnabled
+            } else {
+                Icon::BluetoothDisabled
+            })
+            .fg(theme::Color::MainIcon)
+            .action(
+                markup::PolybarActionType::ClickLeft,
+                control::action("bluetooth", &ControlCommand::toggle),
+            ),
+        ];
         for device in &state.devices {
-            let name = theme::ellipsis(&theme::shorten_model_name(&device.name), Some(4));
+            let name = layout::ellipsis(&theme::shorten_model_name(&device.name), Some(4));
             let mut device_markup = markup::Markup::new(name);
             if device.connected {
                 device_markup = device_markup.underline(theme::Color::Foreground);
             }
+            let command = if device.connected {
+                ControlCommand::disconnect_device { addr: device.addr }
+            } else {
+                ControlCommand::connect_device { addr: device.addr }
+            };
             let action_markup = device_markup.action(
                 markup::PolybarActionType::ClickLeft,
-                format!(
-                    "bluetoothctl {}connect {}",
-                    if device.connected { "dis" } else { "" },
-                    device.addr
-                ),
+                control::action("bluetooth", &command),
             );
             fragments.push(action_markup);
         }
@@ -280,6 +283,26 @@ impl RenderablePolybarModule for BluetoothModule {
             .collect::<Vec<_>>()
             .join(" ")
     }
+
+    fn control_handler(&self) -> Option<control::Handler> {
+        // Note: bluetoothctl reports changes, which will naturally update module
+        Some(Box::new(|command| {
+            match command {
+                ControlCommand::toggle => {
+                    let powered = Self::probe_controller()?.powered;
+                    Self::bluetoothcl_cmd(&["power", if powered { "off" } else { "on" }])?;
+                }
+                ControlCommand::connect_device { addr } => {
+                    Self::bluetoothcl_cmd(&["connect", &addr.to_string()])?;
+                }
+                ControlCommand::disconnect_device { addr } => {
+                    Self::bluetoothcl_cmd(&["disconnect", &addr.to_string()])?;
+                }
+                _ => return Err(control::unsupported(command)),
+            }
+            Ok(())
+        }))
+    }
 }
 
 #[cfg(test)]
@@ -342,7 +365,7 @@ mod tests {
         };
         assert_eq!(
             module.render(&state),
-            "%{A1:bluetoothctl power on:}%{F#f1e9d2}󰂲%{F-}%{A}"
+            "%{A1:polybar-modules ctl bluetooth toggle:}%{F:main_icon}󰂲%{F-}%{A}"
         );
 
         let state = BluetoothModuleState {
@@ -351,7 +374,7 @@ mod tests {
         };
         assert_eq!(
             module.render(&state),
-            "%{A1:bluetoothctl power off:}%{F#f1e9d2}󰂯%{F-}%{A}"
+            "%{A1:polybar-modules ctl bluetooth toggle:}%{F:main_icon}󰂯%{F-}%{A}"
         );
 
         let state = BluetoothModuleState {
@@ -371,7 +394,7 @@ mod tests {
         };
         assert_eq!(
             module.render(&state),
-            "%{A1:bluetoothctl power off:}%{F#f1e9d2}󰂯%{F-}%{A} %{A1:bluetoothctl connect 01\\:02\\:03\\:04\\:05\\:06:}D1%{A} %{A1:bluetoothctl disconnect 02\\:01\\:03\\:04\\:05\\:06:}%{u#8faaab}%{+u}D2%{-u}%{A}"
+            "%{A1:polybar-modules ctl bluetooth toggle:}%{F:main_icon}󰂯%{F-}%{A} %{A1:polybar-modules ctl bluetooth connect-device 01\\:02\\:03\\:04\\:05\\:06:}D1%{A} %{A1:polybar-modules ctl bluetooth disconnect-device 02\\:01\\:03\\:04\\:05\\:06:}%{u:foreground}%{+u}D2%{-u}%{A}"
         );
 
         // SAFETY: actually NOT safe, but this is for tests only, and we are feeling lucky
@@ -7,26 +7,32 @@ use std::{
 };
 
 use crate::{
+    config::{self, CpuFreqModuleConfig},
+    icons::Icon,
     markup,
-    polybar_module::RenderablePolybarModule,
-    theme::{self, ICON_WARNING},
+    polybar_module::{PolybarModuleEnv, RenderablePolybarModule},
+    sparkline, theme,
 };
 
 pub(crate) struct CpuFreqModule {
+    cfg: CpuFreqModuleConfig,
     freq_range: (u32, u32),
     freq_files: Vec<File>,
+    env: PolybarModuleEnv,
+    avg_freq_history: bounded_vec_deque::BoundedVecDeque<u32>,
 }
 
-#[derive(Debug, Eq, PartialEq)]
-#[expect(clippy::struct_field_names)]
+#[derive(Debug, Eq, PartialEq, serde::Serialize)]
 pub(crate) struct CpuFreqModuleState {
     min_freq: u32,
     max_freq: u32,
     avg_freq: u32,
+    /// Last average frequencies, oldest first, if the sparkline is enabled
+    avg_freq_history: Vec<u32>,
 }
 
 impl CpuFreqModule {
-    pub(crate) fn new() -> anyhow::Result<Self> {
+    pub(crate) fn new(cfg: CpuFreqModuleConfig) -> anyhow::Result<Self> {
         let dirs: Vec<PathBuf> =
             glob::glob("/sys/devices/system/cpu/cpu*/cpufreq/")?.collect::<Result<_, _>>()?;
         log::debug!("{} CPUs", dirs.len());
@@ -62,9 +68,14 @@ impl CpuFreqModule {
             .ok_or_else(|| anyhow::anyhow!("Unable to read maximum CPU frequency"))?;
         log::debug!("Frequency range: [{freq_min}, {freq_max}]");
 
+        let avg_freq_history =
+            bounded_vec_deque::BoundedVecDeque::new(cfg.sparkline.as_ref().map_or(0, |s| s.len));
         Ok(Self {
+            cfg,
             freq_range: (freq_min, freq_max),
             freq_files,
+            env: PolybarModuleEnv::new(),
+            avg_freq_history,
         })
     }
 
@@ -92,12 +103,32 @@ impl CpuFreqModule {
         let max_freq: u32 = *freqs.iter().max().unwrap();
         #[expect(clippy::cast_possible_truncation)]
         let avg_freq: u32 = freqs.iter().sum::<u32>() / freqs.len() as u32;
+        self.avg_freq_history.push_back(avg_freq);
         Ok(CpuFreqModuleState {
             min_freq,
             max_freq,
             avg_freq,
+            avg_freq_history: self.avg_freq_history.iter().copied().collect(),
         })
     }
+
+    /// Load of frequency `freq`, in percent of the frequency range
+    fn freq_load(&self, freq: u32) -> f64 {
+        (f64::from(freq) - f64::from(self.freq_range.0)) * 100.0
+            / f64::from(self.freq_range.1 - self.freq_range.0)
+    }
+
+    fn load_color(&self, freq_load: f64) -> Option<theme::Color> {
+        if freq_load > f64::from(self.cfg.attention_load_prct) {
+            Some(theme::Color::Attention)
+        } else if freq_load > f64::from(self.cfg.notice_load_prct) {
+            Some(theme::Color::Notice)
+        } else if freq_load < f64::from(self.cfg.good_load_prct) {
+            Some(theme::Color::Good)
+        } else {
+            None
+        }
+    }
 }
 
 impl RenderablePolybarModule for CpuFreqModule {
@@ -105,7 +136,10 @@ impl RenderablePolybarModule for CpuFreqModule {
 
     fn wait_update(&mut self, prev_state: Option<&Self::State>) {
         if prev_state.is_some() {
-            sleep(Duration::from_secs(1));
+            sleep(
+                self.env
+                    .poll_interval(Duration::from_secs(self.cfg.update_interval_s.get())),
+            );
         }
     }
 
@@ -122,8 +156,7 @@ impl RenderablePolybarModule for CpuFreqModule {
     fn render(&self, state: &Self::State) -> String {
         match state {
             Some(state) => {
-                let freq_load = f64::from(100 * (state.avg_freq - self.freq_range.0))
-                    / f64::from(self.freq_range.1 - self.freq_range.0);
+                let freq_load = self.freq_load(state.avg_freq);
                 log::debug!("freq_load={freq_load}");
                 let mut markup = markup::Markup::new(format!(
                     "{:.1}/{:.1}/{:.1} GHz",
@@ -131,20 +164,45 @@ impl RenderablePolybarModule for CpuFreqModule {
                     f64::from(state.avg_freq) / 1_000_000.0,
                     f64::from(state.max_freq) / 1_000_000.0
                 ));
-                if freq_load > 100.0 {
-                    markup = markup.fg(theme::Color::Attention);
-                } else if freq_load > 80.0 {
-                    markup = markup.fg(theme::Color::Notice);
-                } else if freq_load < 50.0 {
-                    markup = markup.fg(theme::Color::Good);
+                if let Some(color) = self.load_color(freq_load) {
+                    markup = markup.fg(color);
+                }
+                let mut s = markup.into_string();
+                if !state.avg_freq_history.is_empty() {
+                    let samples: Vec<sparkline::Sample> = state
+                        .avg_freq_history
+                        .iter()
+                        .map(|f| {
+                            let sample_load = self.freq_load(*f);
+                            (sample_load / 100.0, self.load_color(sample_load))
+                        })
+                        .collect();
+                    s.push(' ');
+                    s.push_str(&sparkline::render(
+                        &samples,
+                        self.cfg.sparkline.as_ref().is_some_and(|c| c.braille),
+                    ));
                 }
-                markup.into_string()
+                s
             }
-            None => markup::Markup::new(ICON_WARNING)
+            None => markup::Markup::new(Icon::Warning)
                 .fg(theme::Color::Attention)
                 .into_string(),
         }
     }
+
+    fn reload_config(&mut self, cfg: &config::Config) -> anyhow::Result<bool> {
+        let cfg = cfg
+            .module
+            .as_ref()
+            .and_then(|m| m.cpu_freq.clone())
+            .unwrap_or_default();
+        if cfg == self.cfg {
+            return Ok(false);
+        }
+        *self = Self::new(cfg)?;
+        Ok(true)
+    }
 }
 
 #[cfg(test)]
@@ -155,21 +213,26 @@ mod tests {
     #[test]
     fn test_render() {
         let module = CpuFreqModule {
+            cfg: CpuFreqModuleConfig::default(),
             freq_range: (1_000_000, 4_000_000),
             freq_files: vec![],
+            env: PolybarModuleEnv::new(),
+            avg_freq_history: bounded_vec_deque::BoundedVecDeque::new(0),
         };
 
         let state = Some(CpuFreqModuleState {
             min_freq: 1_000_000,
             max_freq: 4_000_000,
             avg_freq: 2_000_000,
+            avg_freq_history: vec![],
         });
-        assert_eq!(module.render(&state), "%{F#819500}1.0/2.0/4.0 GHz%{F-}");
+        assert_eq!(module.render(&state), "%{F:good}1.0/2.0/4.0 GHz%{F-}");
 
         let state = Some(CpuFreqModuleState {
             min_freq: 1_000_000,
             max_freq: 4_000_000,
             avg_freq: 3_000_000,
+            avg_freq_history: vec![],
         });
         assert_eq!(module.render(&state), "1.0/3.0/4.0 GHz");
 
@@ -177,14 +240,27 @@ mod tests {
             min_freq: 1_000_000,
             max_freq: 4_000_000,
             avg_freq: 3_500_000,
+            avg_freq_history: vec![],
         });
-        assert_eq!(module.render(&state), "%{F#ac8300}1.0/3.5/4.0 GHz%{F-}");
+        assert_eq!(module.render(&state), "%{F:notice}1.0/3.5/4.0 GHz%{F-}");
 
         let state = Some(CpuFreqModuleState {
             min_freq: 1_000_000,
             max_freq: 4_000_000,
             avg_freq: 4_500_000,
+            avg_freq_history: vec![],
+        });
+        assert_eq!(module.render(&state), "%{F:attention}1.0/4.5/4.0 GHz%{F-}");
+
+        let state = Some(CpuFreqModuleState {
+            min_freq: 1_000_000,
+            max_freq: 4_000_000,
+            avg_freq: 2_000_000,
+            avg_freq_history: vec![1_000_000, 3_000_000, 3_700_000, 2_000_000],
         });
-        assert_eq!(module.render(&state), "%{F#d56500}1.0/4.5/4.0 GHz%{F-}");
+        assert_eq!(
+            module.render(&state),
+            "%{F:good}1.0/2.0/4.0 GHz%{F-} %{F:good}▁%{F-}▆%{F:notice}█%{F-}%{F:good}▃%{F-}"
+        );
     }
 }
@@ -11,27 +11,33 @@ use sysinfo::{
 };
 
 use crate::{
-    markup,
-    polybar_module::RenderablePolybarModule,
-    theme::{self, ICON_WARNING},
+    config::{self, CpuTopModuleConfig},
+    icons::Icon,
+    layout, markup,
+    polybar_module::{PolybarModuleEnv, RenderablePolybarModule},
+    theme,
 };
 
 pub(crate) struct CpuTopModule {
+    cfg: CpuTopModuleConfig,
     max_len: Option<usize>,
     system: System,
+    env: PolybarModuleEnv,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, serde::Serialize)]
 pub(crate) struct CpuTopModuleState {
     cpu_prct: u32,
     process_name: String,
 }
 
 impl CpuTopModule {
-    pub(crate) fn new(max_len: Option<usize>) -> Self {
+    pub(crate) fn new(max_len: Option<usize>, cfg: CpuTopModuleConfig) -> Self {
         Self {
+            cfg,
             max_len,
             system: System::new(),
+            env: PolybarModuleEnv::new(),
         }
     }
 
@@ -100,13 +106,14 @@ impl RenderablePolybarModule for CpuTopModule {
     type State = Option<CpuTopModuleState>;
 
     fn wait_update(&mut self, prev_state: Option<&Self::State>) {
-        // TODO increase sleep delay if on battery?
         if let Some(prev_state) = prev_state {
             let sleep_duration = match prev_state {
-                Some(state) if state.cpu_prct > 30 => Duration::from_secs(1),
-                _ => Duration::from_secs(3),
+                Some(state) if state.cpu_prct > self.cfg.busy_cpu_prct => {
+                    Duration::from_secs(self.cfg.busy_update_interval_s.get())
+                }
+                _ => Duration::from_secs(self.cfg.idle_update_interval_s.get()),
             };
-            sleep(sleep_duration);
+            sleep(self.env.poll_interval(sleep_duration));
         }
     }
 
@@ -127,23 +134,36 @@ impl RenderablePolybarModule for CpuTopModule {
                 let mut markup = markup::Markup::new(format!(
                     "{: >2}% {}",
                     state.cpu_prct,
-                    theme::pad(
-                        &theme::ellipsis(&state.process_name, max_proc_len),
+                    layout::pad(
+                        &layout::ellipsis(&state.process_name, max_proc_len),
                         max_proc_len
                     ),
                 ));
-                if state.cpu_prct >= 90 {
+                if state.cpu_prct >= self.cfg.attention_cpu_prct {
                     markup = markup.fg(theme::Color::Attention);
-                } else if state.cpu_prct >= 50 {
+                } else if state.cpu_prct >= self.cfg.notice_cpu_prct {
                     markup = markup.fg(theme::Color::Notice);
                 }
                 markup.into_string()
             }
-            None => markup::Markup::new(ICON_WARNING)
+            None => markup::Markup::new(Icon::Warning)
                 .fg(theme::Color::Attention)
                 .into_string(),
         }
     }
+
+    fn reload_config(&mut self, cfg: &config::Config) -> anyhow::Result<bool> {
+        let cfg = cfg
+            .module
+            .as_ref()
+            .and_then(|m| m.cpu_top.clone())
+            .unwrap_or_default();
+        if cfg == self.cfg {
+            return Ok(false);
+        }
+        *self = Self::new(self.max_len, cfg);
+        Ok(true)
+    }
 }
 
 #[cfg(test)]
@@ -216,13 +236,13 @@ mod tests {
 
     #[test]
     fn test_render() {
-        let module = CpuTopModule::new(Some(10));
+        let module = CpuTopModule::new(Some(10), CpuTopModuleConfig::default());
 
         for (cpu_prct, process_name, expected) in [
             (1, "bz", " 1%     bz"),
             (1, "bzzzzzzzzzzzzzzzz", " 1% bzzzz…"),
-            (50, "bz", "%{F#ac8300}50%     bz%{F-}"),
-            (99, "bz", "%{F#d56500}99%     bz%{F-}"),
+            (50, "bz", "%{F:notice}50%     bz%{F-}"),
+            (99, "bz", "%{F:attention}99%     bz%{F-}"),
         ] {
             let state = Some(CpuTopModuleState {
                 cpu_prct,
@@ -234,7 +254,20 @@ mod tests {
         let state = None;
         assert_eq!(
             module.render(&state),
-            format!("%{{F#d56500}}{ICON_WARNING}%{{F-}}")
+            format!("%{{F:attention}}{}%{{F-}}", Icon::Warning)
         );
     }
+
+    #[test]
+    fn test_reload_config() {
+        let mut module = CpuTopModule::new(Some(10), CpuTopModuleConfig::default());
+        let mut cfg = toml::from_str::<config::Config>("").unwrap();
+        assert!(!module.reload_config(&cfg).unwrap());
+
+        cfg = toml::from_str("[module.cpu_top]\nbusy_cpu_prct = 20\n").unwrap();
+        assert!(module.reload_config(&cfg).unwrap());
+        assert_eq!(module.cfg.busy_cpu_prct, 20);
+        assert_eq!(module.max_len, Some(10));
+        assert!(!module.reload_config(&cfg).unwrap());
+    }
 }
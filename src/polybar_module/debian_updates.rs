@@ -7,27 +7,31 @@ use anyhow::Context as _;
 use backon::BackoffBuilder as _;
 
 use crate::{
+    alert,
+    config::{self, DebianUpdatesModuleConfig},
+    icons::Icon,
     markup,
     polybar_module::{
         NETWORK_ERROR_BACKOFF, NetworkMode, PolybarModuleEnv, RenderablePolybarModule,
-        arch_updates::ICON_UPDATE, sleep_suspend_aware, wait_network_ready,
+        sleep_suspend_aware, wait_network_ready,
     },
-    theme::{self, ICON_WARNING},
+    theme,
 };
 
 pub(crate) struct DebianUpdatesModule {
+    update_interval: Duration,
     env: PolybarModuleEnv,
     debian_relase_codename: String,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
 pub(crate) struct DebianUpdatesModuleState {
     update_count: usize,
     security_update_count: usize,
 }
 
 impl DebianUpdatesModule {
-    pub(crate) fn new() -> anyhow::Result<Self> {
+    pub(crate) fn new(cfg: &DebianUpdatesModuleConfig) -> anyhow::Result<Self> {
         let env = PolybarModuleEnv::new();
 
         // Run lsb_release
@@ -50,6 +54,7 @@ impl DebianUpdatesModule {
         // }
 
         Ok(Self {
+            update_interval: Duration::from_secs(cfg.update_interval_s.get()),
             env,
             debian_relase_codename,
         })
@@ -120,7 +125,7 @@ impl RenderablePolybarModule for DebianUpdatesModule {
                 // Nominal
                 Some(_) => {
                     self.env.network_error_backoff = NETWORK_ERROR_BACKOFF.build();
-                    Duration::from_mins(3)
+                    self.update_interval
                 }
                 // Error occured
                 None => self.env.network_error_backoff.next().unwrap(),
@@ -150,7 +155,7 @@ impl RenderablePolybarModule for DebianUpdatesModule {
                 } else {
                     let mut r = format!(
                         "{} {}",
-                        markup::Markup::new(ICON_UPDATE)
+                        markup::Markup::new(Icon::Update)
                             .fg(theme::Color::MainIcon)
                             .into_string(),
                         state.update_count
@@ -163,11 +168,40 @@ impl RenderablePolybarModule for DebianUpdatesModule {
                     r
                 }
             }
-            None => markup::Markup::new(ICON_WARNING)
+            None => markup::Markup::new(Icon::Warning)
                 .fg(theme::Color::Attention)
                 .into_string(),
         }
     }
+
+    fn alerts(&self, prev_state: &Self::State, state: &Self::State) -> Vec<alert::Alert> {
+        match (prev_state, state) {
+            (Some(prev_state), Some(state))
+                if state.security_update_count > prev_state.security_update_count =>
+            {
+                vec![alert::Alert {
+                    rule: "security_updates",
+                    urgency: alert::Urgency::Critical,
+                    summary: "Security updates available".to_owned(),
+                    body: format!("{} security update(s) pending", state.security_update_count),
+                }]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn reload_config(&mut self, cfg: &config::Config) -> anyhow::Result<bool> {
+        let cfg = cfg
+            .module
+            .as_ref()
+            .and_then(|m| m.debian_updates.clone())
+            .unwrap_or_default();
+        if Duration::from_secs(cfg.update_interval_s.get()) == self.update_interval {
+            return Ok(false);
+        }
+        *self = Self::new(&cfg)?;
+        Ok(true)
+    }
 }
 
 #[cfg(test)]
@@ -177,7 +211,7 @@ mod tests {
 
     #[test]
     fn test_render() {
-        let module = DebianUpdatesModule::new().unwrap();
+        let module = DebianUpdatesModule::new(&DebianUpdatesModuleConfig::default()).unwrap();
 
         let state = Some(DebianUpdatesModuleState {
             update_count: 0,
@@ -189,7 +223,7 @@ mod tests {
             update_count: 12,
             security_update_count: 0,
         });
-        assert_eq!(module.render(&state), "%{F#f1e9d2}%{F-} 12");
+        assert_eq!(module.render(&state), "%{F:main_icon}%{F-} 12");
 
         let state = Some(DebianUpdatesModuleState {
             update_count: 12,
@@ -197,10 +231,10 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            "%{F#f1e9d2}%{F-} 12%{F#d56500}(2)%{F-}"
+            "%{F:main_icon}%{F-} 12%{F:attention}(2)%{F-}"
         );
 
         let state = None;
-        assert_eq!(module.render(&state), "%{F#d56500}%{F-}");
+        assert_eq!(module.render(&state), "%{F:attention}%{F-}");
     }
 }
@@ -7,18 +7,25 @@ use std::{
 };
 
 use crate::{
+    alert,
+    config::{self, GpuNvidiaModuleConfig, SparklineConfig},
+    icons::Icon,
     markup,
     polybar_module::RenderablePolybarModule,
-    theme::{self, ICON_WARNING},
+    sparkline, theme,
 };
 
 pub(crate) struct GpuNvidiaModule {
-    _proc: Child,
+    update_interval: Duration,
+    overheat_temp_c: u8,
+    sparkline_cfg: Option<SparklineConfig>,
+    mem_used_history: bounded_vec_deque::BoundedVecDeque<u16>,
+    nvidia_smi_child: Child,
     poller: mio::Poll,
     proc_output: BufReader<ChildStdout>,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, serde::Serialize)]
 pub(crate) struct GpuNvidiaModuleState {
     mem_used: u16,
     mem_total: u16,
@@ -27,15 +34,15 @@ pub(crate) struct GpuNvidiaModuleState {
     throttle: bool,
     temp: u8,
     power_draw: u16,
+    /// Last memory usage values, oldest first, if the sparkline is enabled
+    mem_used_history: Vec<u16>,
 }
 
-const OVERHEAT_TEMP_THRESHOLD: u8 = 70;
-
 impl GpuNvidiaModule {
-    pub(crate) fn new() -> anyhow::Result<Self> {
+    pub(crate) fn new(cfg: &GpuNvidiaModuleConfig) -> anyhow::Result<Self> {
         let mut proc = Command::new("nvidia-smi")
             .args([
-                "-l", "1",
+                "-l", &cfg.update_interval_s.to_string(),
                 "--format=csv,noheader,nounits",
                 "--query-gpu=memory.used,memory.total,clocks.current.graphics,clocks.current.memory,clocks_throttle_reasons.hw_slowdown,temperature.gpu,power.draw"
             ])
@@ -56,7 +63,13 @@ impl GpuNvidiaModule {
         let proc_output = BufReader::new(stdout);
 
         Ok(Self {
-            _proc: proc,
+            update_interval: Duration::from_secs(cfg.update_interval_s.get()),
+            overheat_temp_c: cfg.overheat_temp_c,
+            sparkline_cfg: cfg.sparkline.clone(),
+            mem_used_history: bounded_vec_deque::BoundedVecDeque::new(
+                cfg.sparkline.as_ref().map_or(0, |s| s.len),
+            ),
+            nvidia_smi_child: proc,
             poller,
             proc_output,
         })
@@ -101,6 +114,7 @@ impl GpuNvidiaModule {
             .ok_or_else(|| anyhow::anyhow!(parse_err_str))?
             .parse::<f32>()? as u16;
 
+        self.mem_used_history.push_back(mem_used);
         Ok(GpuNvidiaModuleState {
             mem_used,
             mem_total,
@@ -109,21 +123,43 @@ impl GpuNvidiaModule {
             throttle,
             temp,
             power_draw,
+            mem_used_history: self.mem_used_history.iter().copied().collect(),
         })
     }
 
-    fn ramp_prct(prct: u8) -> String {
-        let color = match prct {
+    const fn prct_color(prct: u8) -> theme::Color {
+        match prct {
             0..=36 => theme::Color::Good,
             37..=60 => theme::Color::Notice,
             61..=84 => theme::Color::Attention,
             _ => theme::Color::Critical,
-        };
-        markup::ramp(f64::from(prct) / 100.0, color)
+        }
+    }
+
+    fn ramp_prct(prct: u8) -> String {
+        markup::ramp(f64::from(prct) / 100.0, Self::prct_color(prct))
+    }
+
+    fn mem_sparkline(state: &GpuNvidiaModuleState, braille: bool) -> String {
+        let samples: Vec<sparkline::Sample> = state
+            .mem_used_history
+            .iter()
+            .map(|m| {
+                let frac = f64::from(*m) / f64::from(state.mem_total);
+                #[expect(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                let prct = (frac * 100.0) as u8;
+                (frac, Some(Self::prct_color(prct)))
+            })
+            .collect();
+        sparkline::render(&samples, braille)
     }
 }
 
-const ICON_GPU: &str = "";
+impl Drop for GpuNvidiaModule {
+    fn drop(&mut self) {
+        let _ = self.nvidia_smi_child.kill();
+    }
+}
 
 impl RenderablePolybarModule for GpuNvidiaModule {
     type State = Option<GpuNvidiaModuleState>;
@@ -165,7 +201,7 @@ impl RenderablePolybarModule for GpuNvidiaModule {
         match state {
             #[expect(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
             Some(state) => {
-                let temp_str = if state.throttle || state.temp >= OVERHEAT_TEMP_THRESHOLD {
+                let temp_str = if state.throttle || state.temp >= self.overheat_temp_c {
                     markup::Markup::new(format!("{}°C", state.temp))
                         .fg(theme::Color::Critical)
                         .underline(theme::Color::Critical)
@@ -176,22 +212,62 @@ impl RenderablePolybarModule for GpuNvidiaModule {
                 let mem_prct = 100.0 * f32::from(state.mem_used) / f32::from(state.mem_total);
                 format!(
                     "{} {:2.0}% {} {:4}+{:4}MHz {} {:3}W",
-                    markup::Markup::new(ICON_GPU)
+                    markup::Markup::new(Icon::Gpu)
                         .fg(theme::Color::MainIcon)
                         .into_string(),
                     mem_prct,
-                    Self::ramp_prct(mem_prct as u8),
+                    if state.mem_used_history.is_empty() {
+                        Self::ramp_prct(mem_prct as u8)
+                    } else {
+                        Self::mem_sparkline(
+                            state,
+                            self.sparkline_cfg.as_ref().is_some_and(|s| s.braille),
+                        )
+                    },
                     state.freq_graphics,
                     state.freq_mem,
                     temp_str,
                     state.power_draw
                 )
             }
-            None => markup::Markup::new(ICON_WARNING)
+            None => markup::Markup::new(Icon::Warning)
                 .fg(theme::Color::Attention)
                 .into_string(),
         }
     }
+
+    fn alerts(&self, prev_state: &Self::State, state: &Self::State) -> Vec<alert::Alert> {
+        match (prev_state, state) {
+            (Some(prev_state), Some(state))
+                if prev_state.temp < self.overheat_temp_c && state.temp >= self.overheat_temp_c =>
+            {
+                vec![alert::Alert {
+                    rule: "overheat",
+                    urgency: alert::Urgency::Critical,
+                    summary: "GPU overheating".to_owned(),
+                    body: format!("Temperature is {}°C", state.temp),
+                }]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn reload_config(&mut self, cfg: &config::Config) -> anyhow::Result<bool> {
+        let cfg = cfg
+            .module
+            .as_ref()
+            .and_then(|m| m.gpu_nvidia.clone())
+            .unwrap_or_default();
+        if Duration::from_secs(cfg.update_interval_s.get()) == self.update_interval
+            && cfg.overheat_temp_c == self.overheat_temp_c
+            && cfg.sparkline == self.sparkline_cfg
+        {
+            return Ok(false);
+        }
+        // Previous nvidia-smi process is killed when the old instance is dropped
+        *self = Self::new(&cfg)?;
+        Ok(true)
+    }
 }
 
 #[cfg(test)]
@@ -202,20 +278,42 @@ mod tests {
     #[test]
     fn test_ramp_prct_color_thresholds() {
         for (prct, expected) in [
-            (36, "%{F#819500}▃%{F-}"),
-            (37, "%{F#ac8300}▃%{F-}"),
-            (60, "%{F#ac8300}▅%{F-}"),
-            (61, "%{F#d56500}▅%{F-}"),
-            (84, "%{F#d56500}▇%{F-}"),
-            (85, "%{F#f23749}▇%{F-}"),
+            (36, "%{F:good}▃%{F-}"),
+            (37, "%{F:notice}▃%{F-}"),
+            (60, "%{F:notice}▅%{F-}"),
+            (61, "%{F:attention}▅%{F-}"),
+            (84, "%{F:attention}▇%{F-}"),
+            (85, "%{F:critical}▇%{F-}"),
         ] {
             assert_eq!(GpuNvidiaModule::ramp_prct(prct), expected);
         }
     }
 
+    #[test]
+    fn test_mem_sparkline() {
+        let state = GpuNvidiaModuleState {
+            mem_used: 3500,
+            mem_total: 4000,
+            freq_graphics: 1600,
+            freq_mem: 2000,
+            throttle: false,
+            temp: 69,
+            power_draw: 200,
+            mem_used_history: vec![200, 2000, 3500],
+        };
+        assert_eq!(
+            GpuNvidiaModule::mem_sparkline(&state, false),
+            "%{F:good}▁%{F-}%{F:notice}▄%{F-}%{F:critical}▇%{F-}"
+        );
+        assert_eq!(
+            GpuNvidiaModule::mem_sparkline(&state, true),
+            "%{F:good}⢀%{F-}%{F:critical}⣼%{F-}"
+        );
+    }
+
     #[test]
     fn test_render() {
-        let module = GpuNvidiaModule::new().unwrap();
+        let module = GpuNvidiaModule::new(&GpuNvidiaModuleConfig::default()).unwrap();
 
         let state = Some(GpuNvidiaModuleState {
             mem_used: 200,
@@ -225,10 +323,11 @@ mod tests {
             throttle: false,
             temp: 40,
             power_draw: 20,
+            mem_used_history: vec![],
         });
         assert_eq!(
             module.render(&state),
-            "%{F#f1e9d2}%{F-}  5% %{F#819500}▁%{F-}  600+ 800MHz 40°C  20W"
+            "%{F:main_icon}%{F-}  5% %{F:good}▁%{F-}  600+ 800MHz 40°C  20W"
         );
 
         let state = Some(GpuNvidiaModuleState {
@@ -239,10 +338,11 @@ mod tests {
             throttle: false,
             temp: 69,
             power_draw: 200,
+            mem_used_history: vec![],
         });
         assert_eq!(
             module.render(&state),
-            "%{F#f1e9d2}%{F-} 88% %{F#f23749}▇%{F-} 1600+2000MHz 69°C 200W"
+            "%{F:main_icon}%{F-} 88% %{F:critical}▇%{F-} 1600+2000MHz 69°C 200W"
         );
 
         let state = Some(GpuNvidiaModuleState {
@@ -253,10 +353,11 @@ mod tests {
             throttle: true,
             temp: 69,
             power_draw: 200,
+            mem_used_history: vec![],
         });
         assert_eq!(
             module.render(&state),
-            "%{F#f1e9d2}%{F-} 88% %{F#f23749}▇%{F-} 1600+2000MHz %{u#f23749}%{+u}%{F#f23749}69°C%{F-}%{-u} 200W"
+            "%{F:main_icon}%{F-} 88% %{F:critical}▇%{F-} 1600+2000MHz %{u:critical}%{+u}%{F:critical}69°C%{F-}%{-u} 200W"
         );
 
         let state = Some(GpuNvidiaModuleState {
@@ -267,10 +368,11 @@ mod tests {
             throttle: false,
             temp: 70,
             power_draw: 200,
+            mem_used_history: vec![],
         });
         assert_eq!(
             module.render(&state),
-            "%{F#f1e9d2}%{F-} 88% %{F#f23749}▇%{F-} 1600+2000MHz %{u#f23749}%{+u}%{F#f23749}70°C%{F-}%{-u} 200W"
+            "%{F:main_icon}%{F-} 88% %{F:critical}▇%{F-} 1600+2000MHz %{u:critical}%{+u}%{F:critical}70°C%{F-}%{-u} 200W"
         );
 
         let state = Some(GpuNvidiaModuleState {
@@ -281,13 +383,14 @@ mod tests {
             throttle: false,
             temp: 70,
             power_draw: 200,
+            mem_used_history: vec![],
         });
         assert_eq!(
             module.render(&state),
-            "%{F#f1e9d2}%{F-} 98% %{F#f23749}█%{F-} 1600+2000MHz %{u#f23749}%{+u}%{F#f23749}70°C%{F-}%{-u} 200W"
+            "%{F:main_icon}%{F-} 98% %{F:critical}█%{F-} 1600+2000MHz %{u:critical}%{+u}%{F:critical}70°C%{F-}%{-u} 200W"
         );
 
         let state = None;
-        assert_eq!(module.render(&state), "%{F#d56500}%{F-}");
+        assert_eq!(module.render(&state), "%{F:attention}%{F-}");
     }
 }
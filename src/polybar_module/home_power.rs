@@ -10,16 +10,19 @@ use itertools::Itertools as _;
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use sha2::Digest as _;
 use tokio_modbus::prelude::SyncReader as _;
-use tungstenite::WebSocket;
+use tungstenite::{WebSocket, client::IntoClientRequest as _};
 
 use crate::{
-    config::{HomePowerModuleConfig, InverterModbusConfig, ShellyDeviceConfig},
+    config::{
+        self, HomePowerModuleConfig, InverterModbusConfig, ShellyDeviceConfig, SparklineConfig,
+    },
+    icons::Icon,
     markup,
     polybar_module::{
-        NETWORK_ERROR_BACKOFF, NetworkMode, PolybarModuleEnv, RenderablePolybarModule,
+        NETWORK_ERROR_BACKOFF, NetworkMode, PolybarModuleEnv, RenderablePolybarModule, http,
         sleep_suspend_aware,
     },
-    theme::{self, ICON_WARNING},
+    sparkline, theme,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -63,24 +66,30 @@ pub(crate) struct HomePowerModule {
     modbus_cfg: InverterModbusConfig,
     modbus_ctx: Option<tokio_modbus::client::sync::Context>,
     shelly_devices: Vec<(ShellyDeviceConfig, Option<ShellyPlus>)>,
+    update_interval: Duration,
     env: PolybarModuleEnv,
+    sparkline_cfg: Option<SparklineConfig>,
+    solar_power_history: bounded_vec_deque::BoundedVecDeque<u32>,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
 pub(crate) struct HomePowerModuleState {
     solar_power: u32,
     home_consumption_power: u32,
     grid_power: u32,
     devices: Vec<HomeDevice>,
+    /// Last solar power values, oldest first, if the sparkline is enabled
+    #[serde(default)]
+    solar_power_history: Vec<u32>,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
 struct HomeDevice {
     name: String,
     status: Option<HomeDeviceStatus>,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
 struct HomeDeviceStatus {
     enabled: bool,
     power: u32,
@@ -162,13 +171,16 @@ fn sha256_hex(s: &str) -> String {
 
 impl ShellyPlus {
     fn connect(host: &str, password: &str) -> anyhow::Result<Self> {
-        let host_port = format!("{host}:80");
-        let addr = host_port.to_socket_addrs()?.next().unwrap();
-        let stream = TcpStream::connect_timeout(&addr, SHELLY_CONNECT_TIMEOUT)?;
+        let stream = http::connect_tcp(host, 80, SHELLY_CONNECT_TIMEOUT)?;
         stream.set_read_timeout(Some(SHELLY_RECV_TIMEOUT))?;
         stream.set_write_timeout(Some(SHELLY_SEND_TIMEOUT))?;
-        let url = format!("ws://{host}/rpc");
-        let ws = tungstenite::client(url, stream)?.0;
+        let mut request = format!("ws://{host}/rpc").into_client_request()?;
+        if let Some(user_agent) = http::user_agent() {
+            request
+                .headers_mut()
+                .insert(tungstenite::http::header::USER_AGENT, user_agent.parse()?);
+        }
+        let ws = tungstenite::client(request, stream)?.0;
 
         Ok(Self {
             ws,
@@ -291,7 +303,12 @@ impl HomePowerModule {
             modbus_cfg: cfg.inverter_modbus.clone(),
             modbus_ctx: None,
             shelly_devices,
+            update_interval: Duration::from_secs(cfg.update_interval_s.get()),
             env,
+            sparkline_cfg: cfg.sparkline.clone(),
+            solar_power_history: bounded_vec_deque::BoundedVecDeque::new(
+                cfg.sparkline.as_ref().map_or(0, |s| s.len),
+            ),
         }
     }
 
@@ -342,6 +359,19 @@ impl HomePowerModule {
         Ok(f64::from(value) * 10_f64.powf(f64::from(scale_factor)))
     }
 
+    fn solar_sparkline(&self, history: &[u32]) -> String {
+        // Relative to the maximum, which depends on the installation
+        let max = history.iter().copied().max().unwrap_or_default().max(1);
+        let samples: Vec<sparkline::Sample> = history
+            .iter()
+            .map(|p| (f64::from(*p) / f64::from(max), None))
+            .collect();
+        sparkline::render(
+            &samples,
+            self.sparkline_cfg.as_ref().is_some_and(|s| s.braille),
+        )
+    }
+
     fn try_update(&mut self) -> anyhow::Result<HomePowerModuleState> {
         // https://knowledge-center.solaredge.com/sites/kc/files/sunspec-implementation-technical-note.pdf
         // https://github.com/nmakel/solaredge_modbus/blob/fd3ce7ae32a259ee371c672dac3bcd75bfe51258/src/solaredge_modbus/__init__.py#L486
@@ -424,23 +454,20 @@ impl HomePowerModule {
 
         self.modbus_ctx = Some(modbus_ctx);
 
+        #[expect(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let solar_power = solar_power as u32;
+        self.solar_power_history.push_back(solar_power);
         #[expect(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
         Ok(HomePowerModuleState {
-            solar_power: solar_power as u32,
+            solar_power,
             home_consumption_power: home_consumption_power as u32,
             grid_power: grid_export.abs() as u32,
             devices,
+            solar_power_history: self.solar_power_history.iter().copied().collect(),
         })
     }
 }
 
-const ICON_POWER: &str = "";
-const ICON_POWER_SOLAR: &str = "";
-const ICON_POWER_HOME: &str = "󱤃";
-const ICON_POWER_GRID: &str = "󰴾";
-const ICON_POWER_FLOW_LEFT: &str = "";
-const ICON_POWER_FLOW_RIGHT: &str = "";
-
 impl RenderablePolybarModule for HomePowerModule {
     type State = Option<HomePowerModuleState>;
 
@@ -448,7 +475,7 @@ impl RenderablePolybarModule for HomePowerModule {
         if let Some(prev_state) = prev_state {
             let sleep_duration = if prev_state.is_some() {
                 self.env.network_error_backoff = NETWORK_ERROR_BACKOFF.build();
-                Duration::from_secs(1)
+                self.env.poll_interval(self.update_interval)
             } else {
                 self.env.network_error_backoff.next().unwrap()
             };
@@ -473,26 +500,31 @@ impl RenderablePolybarModule for HomePowerModule {
         match state {
             Some(state) => {
                 format!(
-                    "{} {}{:.1}{}{}{:.1}{}{}{:.1}kW{}",
-                    markup::Markup::new(ICON_POWER)
+                    "{} {}{:.1}{}{}{:.1}{}{}{:.1}kW{}{}",
+                    markup::Markup::new(Icon::Power)
                         .fg(theme::Color::MainIcon)
                         .into_string(),
-                    ICON_POWER_SOLAR,
+                    Icon::PowerSolar,
                     f64::from(state.solar_power) / 1000.0,
                     if state.solar_power > 0 {
-                        ICON_POWER_FLOW_RIGHT
+                        Icon::PowerFlowRight.to_string()
                     } else {
-                        " "
+                        " ".to_owned()
                     },
-                    ICON_POWER_HOME,
+                    Icon::PowerHome,
                     f64::from(state.home_consumption_power) / 1000.0,
                     match state.solar_power.cmp(&state.home_consumption_power) {
-                        Ordering::Greater => ICON_POWER_FLOW_RIGHT,
-                        Ordering::Less => ICON_POWER_FLOW_LEFT,
-                        Ordering::Equal => " ",
+                        Ordering::Greater => Icon::PowerFlowRight.to_string(),
+                        Ordering::Less => Icon::PowerFlowLeft.to_string(),
+                        Ordering::Equal => " ".to_owned(),
                     },
-                    ICON_POWER_GRID,
+                    Icon::PowerGrid,
                     f64::from(state.grid_power) / 1000.0,
+                    if state.solar_power_history.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" {}", self.solar_sparkline(&state.solar_power_history))
+                    },
                     if state.devices.is_empty() {
                         String::new()
                     } else {
@@ -518,16 +550,38 @@ impl RenderablePolybarModule for HomePowerModule {
                     }
                 )
             }
-            None => markup::Markup::new(ICON_WARNING)
+            None => markup::Markup::new(Icon::Warning)
                 .fg(theme::Color::Attention)
                 .into_string(),
         }
     }
+
+    fn reload_config(&mut self, cfg: &config::Config) -> anyhow::Result<bool> {
+        let cfg = cfg
+            .module
+            .as_ref()
+            .and_then(|m| m.home_power.as_ref())
+            .context("Missing 'module.home_power' config section")?;
+        if cfg.inverter_modbus == self.modbus_cfg
+            && Duration::from_secs(cfg.update_interval_s.get()) == self.update_interval
+            && cfg.sparkline == self.sparkline_cfg
+            && cfg
+                .shelly_devices
+                .iter()
+                .eq(self.shelly_devices.iter().map(|(c, _)| c))
+        {
+            return Ok(false);
+        }
+        *self = Self::new(cfg);
+        Ok(true)
+    }
 }
 
 #[cfg(test)]
 #[expect(clippy::shadow_unrelated)]
 mod tests {
+    use std::num::NonZeroU64;
+
     use super::*;
     use crate::config::InverterModbusConfig;
 
@@ -539,6 +593,8 @@ mod tests {
                 host: "127.0.0.1".to_owned(),
                 port: 0,
             },
+            update_interval_s: NonZeroU64::new(1).unwrap(),
+            sparkline: None,
         });
 
         let state = Some(HomePowerModuleState {
@@ -546,8 +602,12 @@ mod tests {
             home_consumption_power: 600,
             grid_power: 1400,
             devices: vec![],
+            solar_power_history: vec![],
         });
-        assert_eq!(module.render(&state), "%{F#f1e9d2}%{F-} 2.0󱤃0.6󰴾1.4kW");
+        assert_eq!(
+            module.render(&state),
+            "%{F:main_icon}%{F-} 2.0󱤃0.6󰴾1.4kW"
+        );
 
         let state = Some(HomePowerModuleState {
             solar_power: 0,
@@ -580,13 +640,14 @@ mod tests {
                     status: None,
                 },
             ],
+            solar_power_history: vec![],
         });
         assert_eq!(
             module.render(&state),
-            "%{F#f1e9d2}%{F-} 0.0 󱤃0.6󰴾1.4kW D1 %{u#8faaab}%{+u}D2%{-u} %{u#ac8300}%{+u}D3%{-u} %{F#657377}D4%{F-}"
+            "%{F:main_icon}%{F-} 0.0 󱤃0.6󰴾1.4kW D1 %{u:foreground}%{+u}D2%{-u} %{u:notice}%{+u}D3%{-u} %{F:unfocused}D4%{F-}"
         );
 
         let state = None;
-        assert_eq!(module.render(&state), "%{F#d56500}%{F-}");
+        assert_eq!(module.render(&state), "%{F:attention}%{F-}");
     }
 }
@@ -1,6 +1,8 @@
 use std::{
     collections::HashMap,
-    env, fs,
+    env,
+    fmt::Write as _,
+    fs,
     io::{self, Write as _},
     os::unix::fs::MetadataExt as _,
     path::{Path, PathBuf},
@@ -11,21 +13,25 @@ use std::{
 
 use anyhow::Context as _;
 use backon::BackoffBuilder as _;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Local, Utc};
 use itertools::Itertools as _;
 
 use crate::{
+    alert, config,
+    icons::Icon,
     markup,
     polybar_module::{
-        RenderablePolybarModule, TCP_REMOTE_TIMEOUT, sleep_suspend_aware, wait_network_ready,
+        RenderablePolybarModule, TCP_REMOTE_TIMEOUT, http, sleep_suspend_aware, wait_network_ready,
     },
-    theme::{self, ICON_WARNING},
+    theme,
 };
 
 /// Inference API usage module
 pub(crate) struct InferenceUsageModule {
     client: ureq::Agent,
     home_path: String,
+    /// Quota left dropping below which sends a notification, in percent
+    alert_quota_left_pct: f64,
     /// Claude state of each credentials file
     claude_accounts: HashMap<PathBuf, ClaudeAccount>,
     /// `ChatGPT` state of each auth file
@@ -83,15 +89,18 @@ impl RateLimitBackoff {
 }
 
 /// Usage of a single rate limit window
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
 pub(crate) struct UsageWindow {
     quota_left_pct: f64,
     /// Share of the window duration left before it resets, `None` if the window is not running
     time_left_frac: Option<f64>,
+    /// When the window resets, `None` if the window is not running
+    resets_at: Option<DateTime<Utc>>,
 }
 
 /// Claude usage fetch status
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+#[expect(clippy::large_enum_variant)]
 pub(crate) enum ClaudeUsageStatus {
     /// Successfully fetched usage data
     Available {
@@ -107,7 +116,7 @@ pub(crate) enum ClaudeUsageStatus {
 }
 
 /// Inference usage state, with one entry per account of each provider
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
 pub(crate) struct InferenceUsageModuleState {
     claude_statuses: Vec<ClaudeUsageStatus>,
     chatgpt_statuses: Vec<Option<Vec<UsageWindow>>>,
@@ -122,22 +131,45 @@ impl InferenceUsageModuleState {
                 .iter()
                 .any(|status| !matches!(status, ClaudeUsageStatus::Available { .. }))
     }
+
+    /// Available usage windows, with their name, ie. `Claude 5h`
+    fn named_windows(&self) -> Vec<(String, &UsageWindow)> {
+        let account = |provider: &str, index: usize| {
+            if index == 0 {
+                provider.to_owned()
+            } else {
+                format!("{provider} #{}", index + 1)
+            }
+        };
+        let mut named_windows = Vec::new();
+        for (index, status) in self.claude_statuses.iter().enumerate() {
+            if let ClaudeUsageStatus::Available { h5, d7 } = status {
+                named_windows.push((format!("{} 5h", account("Claude", index)), h5));
+                named_windows.push((format!("{} 7d", account("Claude", index)), d7));
+            }
+        }
+        for (index, windows) in self.chatgpt_statuses.iter().enumerate() {
+            for (window_index, window) in windows.iter().flatten().enumerate() {
+                named_windows.push((
+                    format!("{} window {}", account("ChatGPT", index), window_index + 1),
+                    window,
+                ));
+            }
+        }
+        named_windows
+    }
 }
 
-const ICON_INFERENCE_USAGE: &str = "󱩅";
-const ICON_CLAUDE: &str = "";
-const ICON_CHATGPT: &str = "󰫈";
-const ICON_UNAUTHORIZED: &str = "";
-const QUOTA_ICONS: [&str; 9] = [
-    "󰗖", // nf-md-alert_circle_outline
-    "󰪞", // nf-md-circle_slice_1
-    "󰪟", // nf-md-circle_slice_2
-    "󰪠", // nf-md-circle_slice_3
-    "󰪡", // nf-md-circle_slice_4
-    "󰪢", // nf-md-circle_slice_5
-    "󰪣", // nf-md-circle_slice_6
-    "󰪤", // nf-md-circle_slice_7
-    "󰪥", // nf-md-circle_slice_8
+const QUOTA_ICONS: [Icon; 9] = [
+    Icon::AlertCircle,
+    Icon::CircleSlice1,
+    Icon::CircleSlice2,
+    Icon::CircleSlice3,
+    Icon::CircleSlice4,
+    Icon::CircleSlice5,
+    Icon::CircleSlice6,
+    Icon::CircleSlice7,
+    Icon::CircleSlice8,
 ];
 /// Duration of the Claude short rolling window
 const CLAUDE_H5_WINDOW: Duration = Duration::from_hours(5);
@@ -200,7 +232,7 @@ const CLAUDE_STORAGE_LOCK: ClaudeLockParams = ClaudeLockParams {
 /// Lock serializing OAuth grants, held across the refresh request
 const CLAUDE_REFRESH_LOCK: ClaudeLockParams = ClaudeLockParams {
     dir: ".oauth_refresh.lock",
-    stale: Duration::from_secs(60),
+    stale: Duration::from_mins(1),
     heartbeat: Some(Duration::from_secs(5)),
 };
 
@@ -310,6 +342,8 @@ struct ChatGptRateLimitWindow {
     used_percent: f64,
     limit_window_seconds: u64,
     reset_after_seconds: u64,
+    #[serde(default, with = "chrono::serde::ts_seconds_option")]
+    reset_at: Option<DateTime<Utc>>,
 }
 
 #[derive(serde::Serialize)]
@@ -449,15 +483,9 @@ impl Drop for ClaudeLock {
 }
 
 impl InferenceUsageModule {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(cfg: &config::InferenceUsageModuleConfig) -> Self {
         let client = ureq::Agent::new_with_config(
-            ureq::Agent::config_builder()
-                .tls_config(
-                    ureq::tls::TlsConfig::builder()
-                        .provider(ureq::tls::TlsProvider::NativeTls)
-                        .build(),
-                )
-                .timeout_global(Some(TCP_REMOTE_TIMEOUT))
+            http::agent_config(TCP_REMOTE_TIMEOUT)
                 .timeout_resolve(Some(CONNECT_TIMEOUT))
                 .timeout_connect(Some(CONNECT_TIMEOUT))
                 .build(),
@@ -465,6 +493,7 @@ impl InferenceUsageModule {
         Self {
             client,
             home_path: env::var("HOME").unwrap(),
+            alert_quota_left_pct: f64::from(cfg.alert_quota_left_prct),
             claude_accounts: HashMap::new(),
             chatgpt_accounts: HashMap::new(),
             degraded_backoff: DEGRADED_BACKOFF.build(),
@@ -571,6 +600,7 @@ impl InferenceUsageModule {
                     (window.reset_after_seconds as f64 / window.limit_window_seconds as f64)
                         .clamp(0.0, 1.0),
                 ),
+                resets_at: window.reset_at,
             })
             .collect()
     }
@@ -699,6 +729,7 @@ impl InferenceUsageModule {
                     .div_duration_f64(window_len)
                     .clamp(0.0, 1.0)
             }),
+            resets_at: window.resets_at,
         }
     }
 
@@ -915,7 +946,25 @@ impl InferenceUsageModule {
             .collect()
     }
 
-    fn provider_markup(label: &str, usage: &str, default: bool, url: &str) -> String {
+    /// Describe window quota and reset time, on a single line
+    fn detail_window(label: Option<&str>, window: &UsageWindow) -> String {
+        let mut r = String::from("  ");
+        if let Some(label) = label {
+            write!(r, "{label}: ").unwrap();
+        }
+        write!(r, "{:.0}% left", window.quota_left_pct).unwrap();
+        if let Some(resets_at) = window.resets_at {
+            write!(
+                r,
+                ", resets {}",
+                resets_at.with_timezone(&Local).format("%a %H:%M")
+            )
+            .unwrap();
+        }
+        r
+    }
+
+    fn provider_markup(label: Icon, usage: &str, default: bool, url: &str) -> String {
         if !default {
             return usage.to_owned();
         }
@@ -1001,7 +1050,7 @@ impl RenderablePolybarModule for InferenceUsageModule {
 
     fn render(&self, state: &Self::State) -> String {
         let warning = || {
-            markup::Markup::new(ICON_WARNING)
+            markup::Markup::new(Icon::Warning)
                 .fg(theme::Color::Attention)
                 .into_string()
         };
@@ -1011,12 +1060,12 @@ impl RenderablePolybarModule for InferenceUsageModule {
             .iter()
             .map(|status| match status {
                 ClaudeUsageStatus::Available { h5, d7 } => Self::render_windows([h5, d7]),
-                ClaudeUsageStatus::AuthInvalid => ICON_UNAUTHORIZED.to_owned(),
+                ClaudeUsageStatus::AuthInvalid => Icon::Unauthorized.to_string(),
                 ClaudeUsageStatus::Error => warning(),
             })
             .enumerate()
             .map(|(index, usage)| {
-                Self::provider_markup(ICON_CLAUDE, &usage, index == 0, CLAUDE_USAGE_URL)
+                Self::provider_markup(Icon::Claude, &usage, index == 0, CLAUDE_USAGE_URL)
             })
             .join(" ");
         let chatgpt = state
@@ -1025,12 +1074,12 @@ impl RenderablePolybarModule for InferenceUsageModule {
             .map(|windows| windows.as_ref().map_or_else(warning, Self::render_windows))
             .enumerate()
             .map(|(index, usage)| {
-                Self::provider_markup(ICON_CHATGPT, &usage, index == 0, CHATGPT_USAGE_URL)
+                Self::provider_markup(Icon::Chatgpt, &usage, index == 0, CHATGPT_USAGE_URL)
             })
             .join(" ");
 
         [
-            markup::Markup::new(ICON_INFERENCE_USAGE)
+            markup::Markup::new(Icon::InferenceUsage)
                 .fg(theme::Color::MainIcon)
                 .into_string(),
             claude,
@@ -1038,6 +1087,68 @@ impl RenderablePolybarModule for InferenceUsageModule {
         ]
         .join(" ")
     }
+
+    fn render_detail(&self, state: &Self::State) -> Option<String> {
+        let header = |provider: &str, index: usize| {
+            if index == 0 {
+                format!("{provider} (default)")
+            } else {
+                provider.to_owned()
+            }
+        };
+        let mut lines = Vec::new();
+        for (index, status) in state.claude_statuses.iter().enumerate() {
+            lines.push(header("Claude", index));
+            match status {
+                ClaudeUsageStatus::Available { h5, d7 } => {
+                    lines.push(Self::detail_window(Some("5h"), h5));
+                    lines.push(Self::detail_window(Some("7d"), d7));
+                }
+                ClaudeUsageStatus::AuthInvalid => lines.push("  Authentication invalid".to_owned()),
+                ClaudeUsageStatus::Error => lines.push("  Error".to_owned()),
+            }
+        }
+        for (index, windows) in state.chatgpt_statuses.iter().enumerate() {
+            lines.push(header("ChatGPT", index));
+            match windows {
+                Some(windows) => {
+                    lines.extend(windows.iter().map(|w| Self::detail_window(None, w)));
+                }
+                None => lines.push("  Error".to_owned()),
+            }
+        }
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+
+    fn alerts(&self, prev_state: &Self::State, state: &Self::State) -> Vec<alert::Alert> {
+        let prev_windows = prev_state.named_windows();
+        state
+            .named_windows()
+            .into_iter()
+            .filter_map(|(name, window)| {
+                let (_, prev_window) = prev_windows.iter().find(|(n, _)| *n == name)?;
+                (prev_window.quota_left_pct >= self.alert_quota_left_pct
+                    && window.quota_left_pct < self.alert_quota_left_pct)
+                    .then(|| alert::Alert {
+                        rule: "quota_low",
+                        urgency: alert::Urgency::Normal,
+                        summary: format!("{name} quota low"),
+                        body: format!("{:.0}% left", window.quota_left_pct),
+                    })
+            })
+            .collect()
+    }
+
+    fn reload_config(&mut self, cfg: &config::Config) -> anyhow::Result<bool> {
+        // Only used for alerts, no need to rebuild
+        let cfg = cfg
+            .module
+            .as_ref()
+            .and_then(|m| m.inference_usage.clone())
+            .unwrap_or_default();
+        self.alert_quota_left_pct = f64::from(cfg.alert_quota_left_prct);
+        Ok(false)
+    }
 }
 
 #[cfg(test)]
@@ -1066,6 +1177,7 @@ mod tests {
         UsageWindow {
             quota_left_pct,
             time_left_frac: Some(time_left_frac),
+            resets_at: None,
         }
     }
 
@@ -1473,7 +1585,7 @@ mod tests {
         fs::write(&failed, "{}").unwrap();
         fs::write(&other, "{}").unwrap();
 
-        let mut module = InferenceUsageModule::new();
+        let mut module = InferenceUsageModule::new(&config::InferenceUsageModuleConfig::default());
         module
             .claude_accounts
             .entry(failed.clone())
@@ -1506,7 +1618,7 @@ mod tests {
         // Credentials no request can be built from, so none is sent
         fs::write(&other, "{}").unwrap();
 
-        let mut module = InferenceUsageModule::new();
+        let mut module = InferenceUsageModule::new(&config::InferenceUsageModuleConfig::default());
         module.chatgpt_accounts.entry(limited).or_default().hit();
 
         // A skipped account leaves the others alone, which an entry of their own attests
@@ -1535,7 +1647,7 @@ mod tests {
         let chatgpt_perso = home.path().join(".config/codex/auth-personal.json");
         let chatgpt_work = home.path().join(".config/codex/auth-work.json");
 
-        let mut module = InferenceUsageModule::new();
+        let mut module = InferenceUsageModule::new(&config::InferenceUsageModuleConfig::default());
         module.home_path = home.path().to_str().unwrap().to_owned();
         for path in [
             claude_work.clone(),
@@ -1579,19 +1691,19 @@ mod tests {
     #[test]
     fn test_render_quota() {
         for (quota_left_pct, expected) in [
-            (0.0, "%{F#d56500}󰗖%{F-}"),
-            (1.0, "%{F#d56500}󰪞%{F-}"),
-            (5.0, "%{F#d56500}󰪞%{F-}"),
-            (10.0, "%{F#ac8300}󰪞%{F-}"),
-            (20.0, "%{F#ac8300}󰪟%{F-}"),
-            (30.0, "%{F#ac8300}󰪠%{F-}"),
-            (40.0, "%{F#819500}󰪠%{F-}"),
-            (50.0, "%{F#819500}󰪡%{F-}"),
-            (60.0, "%{F#819500}󰪢%{F-}"),
-            (70.0, "%{F#819500}󰪢%{F-}"),
-            (80.0, "%{F#819500}󰪣%{F-}"),
-            (90.0, "%{F#819500}󰪤%{F-}"),
-            (100.0, "%{F#819500}󰪥%{F-}"),
+            (0.0, "%{F:attention}󰗖%{F-}"),
+            (1.0, "%{F:attention}󰪞%{F-}"),
+            (5.0, "%{F:attention}󰪞%{F-}"),
+            (10.0, "%{F:notice}󰪞%{F-}"),
+            (20.0, "%{F:notice}󰪟%{F-}"),
+            (30.0, "%{F:notice}󰪠%{F-}"),
+            (40.0, "%{F:good}󰪠%{F-}"),
+            (50.0, "%{F:good}󰪡%{F-}"),
+            (60.0, "%{F:good}󰪢%{F-}"),
+            (70.0, "%{F:good}󰪢%{F-}"),
+            (80.0, "%{F:good}󰪣%{F-}"),
+            (90.0, "%{F:good}󰪤%{F-}"),
+            (100.0, "%{F:good}󰪥%{F-}"),
         ] {
             assert_eq!(InferenceUsageModule::render_quota(quota_left_pct), expected);
         }
@@ -1599,7 +1711,7 @@ mod tests {
 
     /// Assert `state` renders with the given usage for each account of each provider
     fn assert_render(state: &InferenceUsageModuleState, [claude, chatgpt]: [&[&str]; 2]) {
-        let provider = |label: &str, usages: &[&str], url: &str| {
+        let provider = |label: Icon, usages: &[&str], url: &str| {
             let (default, extra) = usages.split_first().unwrap();
             let default = markup::Markup::new(format!("{label} {default}"))
                 .action(
@@ -1612,13 +1724,13 @@ mod tests {
                 .join(" ")
         };
         assert_eq!(
-            InferenceUsageModule::new().render(state),
+            InferenceUsageModule::new(&config::InferenceUsageModuleConfig::default()).render(state),
             [
-                markup::Markup::new(ICON_INFERENCE_USAGE)
+                markup::Markup::new(Icon::InferenceUsage)
                     .fg(theme::Color::MainIcon)
                     .into_string(),
-                provider(ICON_CLAUDE, claude, CLAUDE_USAGE_URL),
-                provider(ICON_CHATGPT, chatgpt, CHATGPT_USAGE_URL),
+                provider(Icon::Claude, claude, CLAUDE_USAGE_URL),
+                provider(Icon::Chatgpt, chatgpt, CHATGPT_USAGE_URL),
             ]
             .join(" ")
         );
@@ -1626,7 +1738,7 @@ mod tests {
 
     #[test]
     fn test_render_accounts() {
-        let att_warn = markup::Markup::new(ICON_WARNING)
+        let att_warn = markup::Markup::new(Icon::Warning)
             .fg(theme::Color::Attention)
             .into_string();
 
@@ -1649,13 +1761,13 @@ mod tests {
             &state,
             [
                 &[
-                    "%{F#819500}󰪡%{F-}%{F#819500}▆%{F-}%{F#819500}󰪣%{F-}%{F#819500}█%{F-}",
-                    ICON_UNAUTHORIZED,
+                    "%{F:good}󰪡%{F-}%{F:good}▆%{F-}%{F:good}󰪣%{F-}%{F:good}█%{F-}",
+                    "",
                 ],
                 &[
-                    "%{F#819500}󰪣%{F-}%{F#819500}▄%{F-}",
+                    "%{F:good}󰪣%{F-}%{F:good}▄%{F-}",
                     &att_warn,
-                    "%{F#ac8300}󰪟%{F-}%{F#ac8300}▃%{F-}",
+                    "%{F:notice}󰪟%{F-}%{F:notice}▃%{F-}",
                 ],
             ],
         );
@@ -1663,7 +1775,7 @@ mod tests {
 
     #[test]
     fn test_render() {
-        let att_warn = markup::Markup::new(ICON_WARNING)
+        let att_warn = markup::Markup::new(Icon::Warning)
             .fg(theme::Color::Attention)
             .into_string();
 
@@ -1677,8 +1789,8 @@ mod tests {
         assert_render(
             &state,
             [
-                &["%{F#819500}󰪡%{F-}%{F#819500}▆%{F-}%{F#819500}󰪣%{F-}%{F#819500}█%{F-}"],
-                &["%{F#819500}󰪣%{F-}%{F#819500}▄%{F-}%{F#819500}󰪤%{F-}%{F#819500}█%{F-}"],
+                &["%{F:good}󰪡%{F-}%{F:good}▆%{F-}%{F:good}󰪣%{F-}%{F:good}█%{F-}"],
+                &["%{F:good}󰪣%{F-}%{F:good}▄%{F-}%{F:good}󰪤%{F-}%{F:good}█%{F-}"],
             ],
         );
 
@@ -1699,8 +1811,8 @@ mod tests {
         assert_render(
             &state,
             [
-                &["%{F#819500}󰪤%{F-}%{F#819500}▁%{F-}%{F#819500}󰪤%{F-}%{F#819500}▄%{F-}"],
-                &["%{F#819500}󰪤%{F-}%{F#819500}▁%{F-}%{F#819500}󰪤%{F-}%{F#819500}▅%{F-}"],
+                &["%{F:good}󰪤%{F-}%{F:good}▁%{F-}%{F:good}󰪤%{F-}%{F:good}▄%{F-}"],
+                &["%{F:good}󰪤%{F-}%{F:good}▁%{F-}%{F:good}󰪤%{F-}%{F:good}▅%{F-}"],
             ],
         );
 
@@ -1712,8 +1824,8 @@ mod tests {
         assert_render(
             &state,
             [
-                &[ICON_UNAUTHORIZED],
-                &["%{F#ac8300}󰪟%{F-}%{F#ac8300}▃%{F-}%{F#d56500}󰪞%{F-}%{F#d56500}▇%{F-}"],
+                &[""],
+                &["%{F:notice}󰪟%{F-}%{F:notice}▃%{F-}%{F:attention}󰪞%{F-}%{F:attention}▇%{F-}"],
             ],
         );
 
@@ -1723,6 +1835,7 @@ mod tests {
                 h5: UsageWindow {
                     quota_left_pct: 100.0,
                     time_left_frac: None,
+                    resets_at: None,
                 },
                 d7: usage_window(80.0, 0.9),
             }],
@@ -1731,7 +1844,7 @@ mod tests {
         assert_render(
             &state,
             [
-                &["%{F#819500}󰪥%{F-}%{F#819500}󰪣%{F-}%{F#819500}█%{F-}"],
+                &["%{F:good}󰪥%{F-}%{F:good}󰪣%{F-}%{F:good}█%{F-}"],
                 &[&att_warn],
             ],
         );
@@ -1741,10 +1854,72 @@ mod tests {
             claude_statuses: vec![ClaudeUsageStatus::Error],
             chatgpt_statuses: vec![Some(vec![usage_window(82.0, 1.0)])],
         };
-        assert_render(
-            &state,
-            [&[&att_warn], &["%{F#819500}󰪣%{F-}%{F#819500}█%{F-}"]],
+        assert_render(&state, [&[&att_warn], &["%{F:good}󰪣%{F-}%{F:good}█%{F-}"]]);
+    }
+
+    #[test]
+    fn test_render_detail() {
+        let module = InferenceUsageModule::new(&config::InferenceUsageModuleConfig::default());
+
+        let state = InferenceUsageModuleState {
+            claude_statuses: vec![
+                ClaudeUsageStatus::Available {
+                    h5: usage_window(50.0, 0.75),
+                    d7: usage_window(80.4, 0.9),
+                },
+                ClaudeUsageStatus::AuthInvalid,
+            ],
+            chatgpt_statuses: vec![Some(vec![usage_window(81.0, 0.5)]), None],
+        };
+        assert_eq!(
+            module.render_detail(&state).unwrap(),
+            "Claude (default)\n  5h: 50% left\n  7d: 80% left\nClaude\n  Authentication invalid\nChatGPT (default)\n  81% left\nChatGPT\n  Error"
+        );
+
+        let state = InferenceUsageModuleState {
+            claude_statuses: vec![],
+            chatgpt_statuses: vec![],
+        };
+        assert_eq!(module.render_detail(&state), None);
+    }
+
+    #[test]
+    fn test_alerts() {
+        let module = InferenceUsageModule::new(&config::InferenceUsageModuleConfig::default());
+
+        let prev_state = InferenceUsageModuleState {
+            claude_statuses: vec![
+                ClaudeUsageStatus::Available {
+                    h5: usage_window(10.0, 0.5),
+                    d7: usage_window(50.0, 0.5),
+                },
+                ClaudeUsageStatus::Available {
+                    h5: usage_window(30.0, 0.5),
+                    d7: usage_window(50.0, 0.5),
+                },
+            ],
+            chatgpt_statuses: vec![Some(vec![usage_window(9.0, 0.5)])],
+        };
+        let state = InferenceUsageModuleState {
+            claude_statuses: vec![
+                ClaudeUsageStatus::Available {
+                    h5: usage_window(9.5, 0.5),
+                    d7: usage_window(50.0, 0.5),
+                },
+                ClaudeUsageStatus::Error,
+            ],
+            chatgpt_statuses: vec![Some(vec![usage_window(5.0, 0.5)])],
+        };
+        assert_eq!(
+            module.alerts(&prev_state, &state),
+            vec![alert::Alert {
+                rule: "quota_low",
+                urgency: alert::Urgency::Normal,
+                summary: "Claude 5h quota low".to_owned(),
+                body: "10% left".to_owned(),
+            }]
         );
+        assert!(module.alerts(&state, &prev_state).is_empty());
     }
 
     #[test]
@@ -1759,6 +1934,7 @@ mod tests {
             UsageWindow {
                 quota_left_pct: 88.0,
                 time_left_frac: Some(0.5),
+                resets_at: window.resets_at,
             }
         );
     }
@@ -1775,6 +1951,7 @@ mod tests {
             UsageWindow {
                 quota_left_pct: 100.0,
                 time_left_frac: Some(0.0),
+                resets_at: window.resets_at,
             }
         );
     }
@@ -1791,6 +1968,7 @@ mod tests {
             UsageWindow {
                 quota_left_pct: 100.0,
                 time_left_frac: None,
+                resets_at: None,
             }
         );
     }
@@ -1804,6 +1982,7 @@ mod tests {
             vec![UsageWindow {
                 quota_left_pct: 82.0,
                 time_left_frac: Some(567_359.0 / 604_800.0),
+                resets_at: DateTime::from_timestamp(1_784_539_045, 0),
             }]
         );
     }
@@ -1819,10 +1998,12 @@ mod tests {
                 UsageWindow {
                     quota_left_pct: 81.0,
                     time_left_frac: Some(0.25),
+                    resets_at: DateTime::from_timestamp(1, 0),
                 },
                 UsageWindow {
                     quota_left_pct: 90.0,
                     time_left_frac: Some(0.5),
+                    resets_at: DateTime::from_timestamp(1, 0),
                 },
             ]
         );
@@ -1863,7 +2044,7 @@ mod tests {
     fn test_next_delay_shortened_while_degraded() {
         // Jitter can double each computed delay
         let base_range = DEGRADED_MIN_DELAY..(2 * DEGRADED_MIN_DELAY);
-        let mut module = InferenceUsageModule::new();
+        let mut module = InferenceUsageModule::new(&config::InferenceUsageModuleConfig::default());
         assert_eq!(module.next_delay(), UPDATE_INTERVAL);
 
         module.degraded_since = Some(SystemTime::now());
@@ -1885,7 +2066,8 @@ mod tests {
     fn test_next_delay_nominal_while_rate_limited() {
         // Either provider throttling us holds the update back, they answer 429 for the same reason
         for claude in [true, false] {
-            let mut module = InferenceUsageModule::new();
+            let mut module =
+                InferenceUsageModule::new(&config::InferenceUsageModuleConfig::default());
             module.degraded_since = Some(SystemTime::now());
             rate_limit(&mut module, claude).hit();
             assert_eq!(module.next_delay(), UPDATE_INTERVAL);
@@ -1906,7 +2088,7 @@ mod tests {
             }],
             chatgpt_statuses: vec![Some(vec![usage_window(50.0, 0.5)])],
         };
-        let mut module = InferenceUsageModule::new();
+        let mut module = InferenceUsageModule::new(&config::InferenceUsageModuleConfig::default());
         module.degraded_since = Some(SystemTime::now());
         module.last_complete_state = Some(complete.clone());
 
@@ -1929,7 +2111,8 @@ mod tests {
         assert_eq!(module.next_delay(), UPDATE_INTERVAL);
 
         // An escalated retry is capped too, its ceiling is above the hold even without a rate limit
-        let mut ordinary = InferenceUsageModule::new();
+        let mut ordinary =
+            InferenceUsageModule::new(&config::InferenceUsageModuleConfig::default());
         ordinary.degraded_since = SystemTime::now().checked_sub(DEGRADED_HOLD / 2);
         ordinary.last_complete_state = Some(complete);
         let delay = iter::repeat_with(|| ordinary.next_delay()).nth(19).unwrap();
@@ -1,6 +1,8 @@
 use crate::{
+    control::{self, ControlCommand},
+    icons::Icon,
     markup,
-    polybar_module::{NetworkMode, PolybarModuleEnv, RenderablePolybarModule},
+    polybar_module::{NetworkMode, PolybarModuleEnv, RenderablePolybarModule, toggle_flag_file},
     theme,
 };
 
@@ -8,21 +10,19 @@ pub(crate) struct InternetBandwidthModule {
     env: PolybarModuleEnv,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, serde::Serialize)]
 pub(crate) struct InternetBandwidthModuleState {
     mode: NetworkMode,
 }
 
 impl InternetBandwidthModule {
     pub(crate) fn new() -> Self {
-        let env = PolybarModuleEnv::new();
-        Self { env }
+        Self {
+            env: PolybarModuleEnv::new(),
+        }
     }
 }
 
-const ICON_NETWORK_NORMAL: &str = "󰲝";
-const ICON_NETWORK_LOW_BANDWIDTH: &str = "󰅛";
-
 impl RenderablePolybarModule for InternetBandwidthModule {
     type State = InternetBandwidthModuleState;
 
@@ -43,42 +43,35 @@ impl RenderablePolybarModule for InternetBandwidthModule {
     }
 
     fn render(&self, state: &Self::State) -> String {
+        let action = control::action("internet-bandwidth", &ControlCommand::toggle);
         match state.mode {
-            NetworkMode::Unrestricted => markup::Markup::new(ICON_NETWORK_NORMAL)
-                .action(
-                    markup::PolybarActionType::ClickLeft,
-                    format!("touch {}", self.env.low_bw_filepath.to_str().unwrap()),
-                )
+            NetworkMode::Unrestricted => markup::Markup::new(Icon::NetworkNormal)
+                .action(markup::PolybarActionType::ClickLeft, action)
                 .into_string(),
-            NetworkMode::LowBandwith => markup::Markup::new(ICON_NETWORK_LOW_BANDWIDTH)
+            NetworkMode::LowBandwith => markup::Markup::new(Icon::NetworkLowBandwidth)
                 .underline(theme::Color::Notice)
-                .action(
-                    markup::PolybarActionType::ClickLeft,
-                    format!(
-                        "rm {}",
-                        self.env
-                            .low_bw_filepath
-                            .as_os_str()
-                            .to_os_string()
-                            .into_string()
-                            .unwrap()
-                    ),
-                )
+                .action(markup::PolybarActionType::ClickLeft, action)
                 .into_string(),
         }
     }
+
+    fn control_handler(&self) -> Option<control::Handler> {
+        // File watch picks up the change
+        let low_bw_filepath = self.env.low_bw_filepath.clone();
+        Some(Box::new(move |command| match command {
+            ControlCommand::toggle => Ok(toggle_flag_file(&low_bw_filepath)?),
+            _ => Err(control::unsupported(command)),
+        }))
+    }
 }
 
 #[cfg(test)]
 #[expect(clippy::shadow_unrelated)]
 mod tests {
-    use std::env;
-
     use super::*;
 
     #[test]
     fn test_render() {
-        let home = env::var("HOME").unwrap();
         let module = InternetBandwidthModule::new();
 
         let state = InternetBandwidthModuleState {
@@ -86,7 +79,7 @@ mod tests {
         };
         assert_eq!(
             module.render(&state),
-            format!("%{{A1:touch {home}/.local/share/low_internet_bandwidth:}}󰲝%{{A}}")
+            "%{A1:polybar-modules ctl internet-bandwidth toggle:}󰲝%{A}"
         );
 
         let state = InternetBandwidthModuleState {
@@ -94,9 +87,7 @@ mod tests {
         };
         assert_eq!(
             module.render(&state),
-            format!(
-                "%{{A1:rm {home}/.local/share/low_internet_bandwidth:}}%{{u#ac8300}}%{{+u}}󰅛%{{-u}}%{{A}}"
-            )
+            "%{A1:polybar-modules ctl internet-bandwidth toggle:}%{u:notice}%{+u}󰅛%{-u}%{A}"
         );
     }
 }
@@ -0,0 +1,206 @@
+//! Automatic detection of metered connections, setting the flag file of [`super::PolybarModuleEnv::network_mode`]
+
+use std::{
+    fs, io,
+    path::Path,
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use anyhow::Context as _;
+
+use crate::{config::LowBandwidthConfig, polybar_module::spawn_flag_detection};
+
+/// Start detection in a background thread, once the process holds the detection lock
+pub(crate) fn start_detection(cfg: &LowBandwidthConfig, flag_filepath: &Path) {
+    if !cfg.detect {
+        return;
+    }
+    let cfg = cfg.clone();
+    spawn_flag_detection(
+        "low-bandwidth",
+        flag_filepath.to_owned(),
+        Duration::from_secs(cfg.interval_s.get()),
+        move || detect(&cfg),
+    );
+}
+
+/// Return why bandwidth is low, if it is
+fn detect(cfg: &LowBandwidthConfig) -> Option<String> {
+    if cfg.tethering {
+        match tethering_interface(Path::new("/sys/class/net"), &cfg.phone_ouis) {
+            Ok(Some(itf)) => return Some(format!("tethering interface {itf} is up")),
+            Ok(None) => {}
+            Err(err) => log::warn!("Failed to list network interfaces: {err}"),
+        }
+    }
+    if cfg.metered {
+        match is_metered() {
+            Ok(true) => return Some("connection is metered".to_owned()),
+            Ok(false) => {}
+            Err(err) => log::debug!("Failed to get NetworkManager metered state: {err:#}"),
+        }
+    }
+    if !cfg.ssids.is_empty() {
+        match active_ssid() {
+            Ok(Some(ssid)) if cfg.ssids.contains(&ssid) => {
+                return Some(format!("connected to Wi-Fi network {ssid:?}"));
+            }
+            Ok(_) => {}
+            Err(err) => log::debug!("Failed to get Wi-Fi network: {err:#}"),
+        }
+    }
+    None
+}
+
+/// Get name of the first USB tethering interface up in `net_dir`
+fn tethering_interface(net_dir: &Path, phone_ouis: &[String]) -> io::Result<Option<String>> {
+    for entry in fs::read_dir(net_dir)? {
+        let entry = entry?;
+        let Some(itf) = entry.file_name().to_str().map(ToOwned::to_owned) else {
+            continue;
+        };
+        let is_tethering = if itf.starts_with("rndis") || itf.starts_with("usb") {
+            true
+        } else if itf.strip_prefix("enp").is_some_and(|s| s.contains('u')) {
+            // USB Ethernet adapters are named the same, tell phones apart by their MAC address
+            let Ok(address) = fs::read_to_string(entry.path().join("address")) else {
+                continue;
+            };
+            let address = address.trim_end().to_ascii_lowercase();
+            phone_ouis
+                .iter()
+                .any(|o| address.starts_with(&o.to_ascii_lowercase()))
+        } else {
+            false
+        };
+        if !is_tethering {
+            continue;
+        }
+        let Ok(operstate) = fs::read_to_string(entry.path().join("operstate")) else {
+            continue;
+        };
+        if operstate.trim_end() == "up" {
+            return Ok(Some(itf));
+        }
+    }
+    Ok(None)
+}
+
+/// Return whether `NetworkManager` considers the primary connection metered
+fn is_metered() -> anyhow::Result<bool> {
+    let output = Command::new("busctl")
+        .args([
+            "get-property",
+            "org.freedesktop.NetworkManager",
+            "/org/freedesktop/NetworkManager",
+            "org.freedesktop.NetworkManager",
+            "Metered",
+        ])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()?;
+    output
+        .status
+        .exit_ok()
+        .context("busctl exited with error")?;
+    parse_metered(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse `busctl get-property` output of the `NMMetered` property, ie. `u 1`
+fn parse_metered(output: &str) -> anyhow::Result<bool> {
+    let value: u32 = output
+        .trim_end()
+        .strip_prefix("u ")
+        .ok_or_else(|| anyhow::anyhow!("Unexpected property value {output:?}"))?
+        .parse()?;
+    // NM_METERED_YES or NM_METERED_GUESS_YES
+    Ok(matches!(value, 1 | 3))
+}
+
+/// Get name of the active Wi-Fi network, if any
+fn active_ssid() -> anyhow::Result<Option<String>> {
+    let output = Command::new("nmcli")
+        .args([
+            "--terse",
+            "--fields",
+            "active,ssid",
+            "device",
+            "wifi",
+            "list",
+            "--rescan",
+            "no",
+        ])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()?;
+    output.status.exit_ok().context("nmcli exited with error")?;
+    Ok(parse_active_ssid(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parse `nmcli --terse --fields active,ssid device wifi list` output
+fn parse_active_ssid(output: &str) -> Option<String> {
+    let ssid = output.lines().find_map(|l| l.strip_prefix("yes:"))?;
+    // Terse mode escapes separators
+    Some(ssid.replace("\\:", ":").replace("\\\\", "\\"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tethering_interface() {
+        let net_dir = tempfile::tempdir().unwrap();
+        for (itf, address, operstate) in [
+            ("lo", "00:00:00:00:00:00", "unknown"),
+            ("wlan0", "3c:a9:f4:12:34:56", "up"),
+            ("usb0", "02:11:22:33:44:55", "down"),
+            ("enp0s20f0u1", "A4:C3:F0:12:34:56", "up"),
+        ] {
+            fs::create_dir(net_dir.path().join(itf)).unwrap();
+            fs::write(
+                net_dir.path().join(itf).join("address"),
+                format!("{address}\n"),
+            )
+            .unwrap();
+            fs::write(
+                net_dir.path().join(itf).join("operstate"),
+                format!("{operstate}\n"),
+            )
+            .unwrap();
+        }
+
+        assert_eq!(tethering_interface(net_dir.path(), &[]).unwrap(), None);
+        assert_eq!(
+            tethering_interface(net_dir.path(), &["a4:c3:f0".to_owned()]).unwrap(),
+            Some("enp0s20f0u1".to_owned())
+        );
+
+        fs::remove_dir_all(net_dir.path().join("enp0s20f0u1")).unwrap();
+        fs::write(net_dir.path().join("usb0/operstate"), "up\n").unwrap();
+        assert_eq!(
+            tethering_interface(net_dir.path(), &[]).unwrap(),
+            Some("usb0".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_parse_metered() {
+        assert!(parse_metered("u 1\n").unwrap());
+        assert!(parse_metered("u 3\n").unwrap());
+        assert!(!parse_metered("u 0\n").unwrap());
+        assert!(!parse_metered("u 4\n").unwrap());
+        assert!(parse_metered("s \"yes\"\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_active_ssid() {
+        assert_eq!(
+            parse_active_ssid("no:Neighbours\nyes:My phone\\: 5G\nno:\n"),
+            Some("My phone: 5G".to_owned())
+        );
+        assert_eq!(parse_active_ssid("no:Neighbours\n"), None);
+        assert_eq!(parse_active_ssid(""), None);
+    }
+}
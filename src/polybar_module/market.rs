@@ -5,16 +5,21 @@ use backon::BackoffBuilder as _;
 use chrono::Datelike as _;
 
 use crate::{
+    config::{self, MarketModuleConfig},
+    icons::Icon,
     markup,
     polybar_module::{
         NETWORK_ERROR_BACKOFF, NetworkMode, PolybarModuleEnv, RenderablePolybarModule,
-        TCP_REMOTE_TIMEOUT, sleep_suspend_aware, wait_network_ready,
+        http::HttpClient, sleep_suspend_aware, wait_network_ready,
     },
-    theme::{self, ICON_WARNING},
+    theme,
 };
 
+const HTTP_MIN_INTERVAL: Duration = Duration::from_mins(5);
+
 pub(crate) struct MarketModule {
-    client: ureq::Agent,
+    update_interval: Duration,
+    client: HttpClient,
     selector_val: scraper::Selector,
     selector_delta: scraper::Selector,
     selector_ma50: scraper::Selector,
@@ -22,7 +27,7 @@ pub(crate) struct MarketModule {
     env: PolybarModuleEnv,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
 pub(crate) struct MarketModuleState {
     val: f64,
     delta_prct: f64,
@@ -31,17 +36,8 @@ pub(crate) struct MarketModuleState {
 }
 
 impl MarketModule {
-    pub(crate) fn new() -> Self {
-        let client = ureq::Agent::new_with_config(
-            ureq::Agent::config_builder()
-                .tls_config(
-                    ureq::tls::TlsConfig::builder()
-                        .provider(ureq::tls::TlsProvider::NativeTls)
-                        .build(),
-                )
-                .timeout_global(Some(TCP_REMOTE_TIMEOUT))
-                .build(),
-        );
+    pub(crate) fn new(cfg: &MarketModuleConfig) -> Self {
+        let client = HttpClient::new(HTTP_MIN_INTERVAL);
 
         // TODO improve selectors?
         let selector_val = scraper::Selector::parse(
@@ -59,6 +55,7 @@ impl MarketModule {
         let env = PolybarModuleEnv::new();
 
         Self {
+            update_interval: Duration::from_secs(cfg.update_interval_s.get()),
             client,
             selector_val,
             selector_delta,
@@ -108,15 +105,10 @@ impl MarketModule {
     fn try_update(&mut self) -> anyhow::Result<MarketModuleState> {
         // Send request
         let url = "https://www.boursorama.com/bourse/indices/cours/1rPCAC/";
-        let response = self.client.get(url).call()?;
-        anyhow::ensure!(
-            response.status().is_success(),
-            "HTTP response {}",
-            response.status(),
-        );
+        let body = self.client.get(url, &self.env.network_mode())?;
 
         // Parse response
-        let page = scraper::Html::parse_document(&response.into_body().read_to_string()?);
+        let page = scraper::Html::parse_document(&body);
         let val =
             Self::extract_float(&page, &self.selector_val).context("Failed to extract value")?;
         let delta_prct =
@@ -135,9 +127,6 @@ impl MarketModule {
     }
 }
 
-const ICON_MARKET_UP: &str = "";
-const ICON_MARKET_DOWN: &str = "";
-
 impl RenderablePolybarModule for MarketModule {
     type State = Option<MarketModuleState>;
 
@@ -147,7 +136,7 @@ impl RenderablePolybarModule for MarketModule {
                 // Nominal
                 Some(_) => {
                     self.env.network_error_backoff = NETWORK_ERROR_BACKOFF.build();
-                    Duration::from_mins(30)
+                    self.update_interval
                 }
                 // Error occured
                 None => self.env.network_error_backoff.next().unwrap(),
@@ -187,9 +176,9 @@ impl RenderablePolybarModule for MarketModule {
                 format!(
                     "{} {:.0} {}",
                     markup::Markup::new(if state.ma50 >= state.ma100 {
-                        ICON_MARKET_UP
+                        Icon::MarketUp
                     } else {
-                        ICON_MARKET_DOWN
+                        Icon::MarketDown
                     })
                     .fg(theme::Color::MainIcon)
                     .into_string(),
@@ -207,11 +196,24 @@ impl RenderablePolybarModule for MarketModule {
                     },
                 )
             }
-            None => markup::Markup::new(ICON_WARNING)
+            None => markup::Markup::new(Icon::Warning)
                 .fg(theme::Color::Attention)
                 .into_string(),
         }
     }
+
+    fn reload_config(&mut self, cfg: &config::Config) -> anyhow::Result<bool> {
+        let cfg = cfg
+            .module
+            .as_ref()
+            .and_then(|m| m.market.clone())
+            .unwrap_or_default();
+        if Duration::from_secs(cfg.update_interval_s.get()) == self.update_interval {
+            return Ok(false);
+        }
+        *self = Self::new(&cfg);
+        Ok(true)
+    }
 }
 
 #[cfg(test)]
@@ -221,7 +223,7 @@ mod tests {
 
     #[test]
     fn test_render() {
-        let module = MarketModule::new();
+        let module = MarketModule::new(&MarketModuleConfig::default());
 
         let state = Some(MarketModuleState {
             val: 5000.6,
@@ -229,7 +231,7 @@ mod tests {
             ma50: 4501.0,
             ma100: 4500.0,
         });
-        assert_eq!(module.render(&state), "%{F#f1e9d2}%{F-} 5001 +0.10%");
+        assert_eq!(module.render(&state), "%{F:main_icon}%{F-} 5001 +0.10%");
 
         let state = Some(MarketModuleState {
             val: 5000.6,
@@ -237,7 +239,7 @@ mod tests {
             ma50: 4500.0,
             ma100: 4501.0,
         });
-        assert_eq!(module.render(&state), "%{F#f1e9d2}%{F-} 5001 +0.10%");
+        assert_eq!(module.render(&state), "%{F:main_icon}%{F-} 5001 +0.10%");
 
         let state = Some(MarketModuleState {
             val: 5000.6,
@@ -247,7 +249,7 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            "%{F#f1e9d2}%{F-} 5001 %{F#819500}+1.01%%{F-}"
+            "%{F:main_icon}%{F-} 5001 %{F:good}+1.01%%{F-}"
         );
 
         let state = Some(MarketModuleState {
@@ -258,7 +260,7 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            "%{F#f1e9d2}%{F-} 5001 %{F#d56500}-2.01%%{F-}"
+            "%{F:main_icon}%{F-} 5001 %{F:attention}-2.01%%{F-}"
         );
 
         let state = Some(MarketModuleState {
@@ -269,10 +271,10 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            "%{F#f1e9d2}%{F-} 5001 %{F#ac8300}-1.01%%{F-}"
+            "%{F:main_icon}%{F-} 5001 %{F:notice}-1.01%%{F-}"
         );
 
         let state = None;
-        assert_eq!(module.render(&state), "%{F#d56500}%{F-}");
+        assert_eq!(module.render(&state), "%{F:attention}%{F-}");
     }
 }
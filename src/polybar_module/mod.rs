@@ -1,11 +1,12 @@
 use std::{
-    fmt::Debug,
-    fs,
-    path::PathBuf,
+    env,
+    fmt::{Debug, Display},
+    fs, io,
+    path::{Path, PathBuf},
     process::{Command, Stdio},
-    sync::mpsc::channel,
-    thread::sleep,
-    time::Duration,
+    sync::{Arc, Condvar, Mutex, OnceLock, RwLock},
+    thread::{self, sleep},
+    time::{Duration, Instant},
 };
 
 use backon::BackoffBuilder as _;
@@ -16,6 +17,8 @@ use rustix::{
     time::clock_gettime,
 };
 
+use crate::{alert, config, control};
+
 pub(crate) mod arch_updates;
 pub(crate) mod autolock;
 pub(crate) mod battery_mouse;
@@ -25,21 +28,24 @@ pub(crate) mod cpu_top;
 pub(crate) mod debian_updates;
 pub(crate) mod gpu_nvidia;
 pub(crate) mod home_power;
+pub(crate) mod http;
 pub(crate) mod inference_usage;
 pub(crate) mod internet_bandwidth;
+pub(crate) mod low_bandwidth;
 pub(crate) mod market;
 pub(crate) mod network_status;
 pub(crate) mod notifications;
 pub(crate) mod player;
 pub(crate) mod progressbar_server;
+pub(crate) mod public_screen;
 pub(crate) mod pulseaudio;
+pub(crate) mod stale;
 pub(crate) mod syncthing;
 mod syncthing_rest;
 pub(crate) mod todotxt;
 pub(crate) mod weather;
 pub(crate) mod xmonad;
 
-#[expect(clippy::large_enum_variant)]
 pub(crate) enum PolybarModule {
     ArchUpdates(arch_updates::ArchUpdatesModule),
     Autolock(autolock::AutolockModule),
@@ -64,7 +70,7 @@ pub(crate) enum PolybarModule {
     Xmonad(xmonad::XmonadModule),
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, serde::Serialize)]
 pub(crate) enum NetworkMode {
     Unrestricted,
     LowBandwith,
@@ -82,18 +88,57 @@ pub(crate) const NETWORK_ERROR_BACKOFF: backon::ExponentialBuilder =
         .without_max_times();
 
 pub(crate) trait RenderablePolybarModule {
-    type State: Debug + PartialEq;
+    type State: Debug + PartialEq + serde::Serialize;
 
     fn wait_update(&mut self, prev_state: Option<&Self::State>);
 
     fn update(&mut self) -> Self::State;
 
     fn render(&self, state: &Self::State) -> String;
+
+    /// State to render before the first update completes, ie. one saved by a previous run
+    fn initial_state(&mut self) -> Option<Self::State> {
+        None
+    }
+
+    /// Render multi-line plain text detail, if the module has more to show than what fits in the bar
+    fn render_detail(&self, _state: &Self::State) -> Option<String> {
+        None
+    }
+
+    /// Delay after which the rendering of `state` changes by itself, ie. for scrolling text, to render it again
+    /// even if the state did not change
+    ///
+    /// [`Self::wait_update`] must then return within that delay.
+    fn rerender_interval(&self, _state: &Self::State) -> Option<Duration> {
+        None
+    }
+
+    /// Alerts for the significant changes from `prev_state` to `state`, sent as desktop notifications
+    fn alerts(&self, _prev_state: &Self::State, _state: &Self::State) -> Vec<alert::Alert> {
+        Vec::new()
+    }
+
+    /// Get handler of the control commands sent to the module, if it supports any
+    ///
+    /// The handler runs in its own thread, concurrently with updates.
+    fn control_handler(&self) -> Option<control::Handler> {
+        None
+    }
+
+    /// Apply changed config file, and return true if the module was rebuilt from it
+    ///
+    /// This is called before each update, so not until [`Self::wait_update`] returns. On error, the module
+    /// must be left unchanged.
+    fn reload_config(&mut self, _cfg: &config::Config) -> anyhow::Result<bool> {
+        Ok(false)
+    }
 }
 
 pub(crate) struct PolybarModuleEnv {
     pub low_bw_filepath: PathBuf,
     pub public_screen_filepath: PathBuf,
+    power_supply_dirpath: PathBuf,
     pub network_error_backoff: backon::ExponentialBackoff,
 }
 
@@ -108,6 +153,7 @@ impl PolybarModuleEnv {
         Self {
             low_bw_filepath,
             public_screen_filepath,
+            power_supply_dirpath: PathBuf::from("/sys/class/power_supply"),
             network_error_backoff: NETWORK_ERROR_BACKOFF.build(),
         }
     }
@@ -124,41 +170,169 @@ impl PolybarModuleEnv {
         self.public_screen_filepath.exists()
     }
 
+    /// Whether the system runs on battery
+    ///
+    /// Power supply sysfs attributes can not be watched, so this is read again at each call.
+    pub(crate) fn on_battery(&self) -> bool {
+        on_battery(&self.power_supply_dirpath)
+            .inspect_err(|err| log::warn!("Failed to read power supply state: {err}"))
+            .unwrap_or(false)
+    }
+
+    /// Scale polling `interval` for the current power state
+    pub(crate) fn poll_interval(&self, interval: Duration) -> Duration {
+        let cfg = POWER_CFG.read().unwrap();
+        scale_interval(interval, &cfg, self.on_battery(), screen_locked)
+    }
+
     pub(crate) fn wait_network_mode(&self, mode: &NetworkMode) -> bool {
-        let mut did_wait = false;
-        let (events_tx, events_rx) = channel();
-        let mut watcher = notify::recommended_watcher(events_tx).unwrap();
-        let parent_dir = self.low_bw_filepath.parent().unwrap();
-        watcher
-            .watch(parent_dir, notify::RecursiveMode::NonRecursive)
-            .unwrap();
-        log::debug!("Watching {parent_dir:?}");
-        while self.network_mode() != *mode {
-            let evt = events_rx.recv().unwrap();
-            did_wait = true;
+        self.watch().wait_until(|| self.network_mode() == *mode)
+    }
+
+    pub(crate) fn wait_public_screen(&self, public: bool) -> bool {
+        self.watch().wait_until(|| self.public_screen() == public)
+    }
+
+    /// Get the watch of the environment files, set up once for all the modules of the process
+    fn watch(&self) -> &'static EnvWatch {
+        static WATCH: OnceLock<EnvWatch> = OnceLock::new();
+        WATCH.get_or_init(|| {
+            EnvWatch::new(&[
+                self.low_bw_filepath.parent().unwrap(),
+                self.public_screen_filepath.parent().unwrap(),
+            ])
+        })
+    }
+}
+
+/// Filesystem watch notifying any number of waiting threads
+struct EnvWatch {
+    _watcher: Mutex<notify::RecommendedWatcher>,
+    changes: Arc<(Mutex<()>, Condvar)>,
+}
+
+impl EnvWatch {
+    fn new(dirs: &[&Path]) -> Self {
+        let changes = Arc::new((Mutex::new(()), Condvar::new()));
+        let watcher_changes = Arc::clone(&changes);
+        let mut watcher = notify::recommended_watcher(move |evt: notify::Result<notify::Event>| {
             log::trace!("{evt:?}");
+            let (lock, cond) = &*watcher_changes;
+            let _guard = lock.lock().unwrap();
+            cond.notify_all();
+        })
+        .unwrap();
+        for dir in dirs {
+            watcher
+                .watch(dir, notify::RecursiveMode::NonRecursive)
+                .unwrap();
+            log::debug!("Watching {dir:?}");
+        }
+        Self {
+            _watcher: Mutex::new(watcher),
+            changes,
         }
-        did_wait
     }
 
-    pub(crate) fn wait_public_screen(&self, public: bool) -> bool {
+    /// Block until `cond` is true, and return true if it was not initially
+    fn wait_until<F>(&self, cond: F) -> bool
+    where
+        F: Fn() -> bool,
+    {
+        let (lock, changed) = &*self.changes;
+        // Condition is evaluated with the lock held, so a change can not slip between check and wait
+        let mut guard = lock.lock().unwrap();
         let mut did_wait = false;
-        let (events_tx, events_rx) = channel();
-        let mut watcher = notify::recommended_watcher(events_tx).unwrap();
-        let parent_dir = self.public_screen_filepath.parent().unwrap();
-        watcher
-            .watch(parent_dir, notify::RecursiveMode::NonRecursive)
-            .unwrap();
-        log::debug!("Watching {parent_dir:?}");
-        while self.public_screen() != public {
-            let evt = events_rx.recv().unwrap();
+        while !cond() {
+            guard = changed.wait(guard).unwrap();
             did_wait = true;
-            log::trace!("{evt:?}");
         }
         did_wait
     }
 }
 
+static POWER_CFG: RwLock<config::PowerConfig> = RwLock::new(config::PowerConfig::DEFAULT);
+
+/// Make `cfg` the one module polling intervals are scaled with
+pub(crate) fn set_power_config(cfg: config::PowerConfig) {
+    log::debug!("{cfg:?}");
+    *POWER_CFG.write().unwrap() = cfg;
+}
+
+/// Scale polling `interval` with `cfg`, to the longest of the battery and screen lock intervals
+fn scale_interval(
+    interval: Duration,
+    cfg: &config::PowerConfig,
+    on_battery: bool,
+    screen_locked: impl FnOnce() -> bool,
+) -> Duration {
+    let interval = if on_battery {
+        interval.mul_f64(cfg.battery_interval_factor)
+    } else {
+        interval
+    };
+    // Lock state only matters if it lengthens the interval
+    if let Some(locked_interval) = cfg.locked_interval_s.map(Duration::from_secs)
+        && interval < locked_interval
+        && screen_locked()
+    {
+        locked_interval
+    } else {
+        interval
+    }
+}
+
+/// Return whether there are mains power supplies in `power_supply_dir`, and none is online
+fn on_battery(power_supply_dir: &Path) -> io::Result<bool> {
+    let mut has_mains = false;
+    for entry in fs::read_dir(power_supply_dir)? {
+        let entry = entry?;
+        let Ok(supply_type) = fs::read_to_string(entry.path().join("type")) else {
+            continue;
+        };
+        if supply_type.trim_end() != "Mains" {
+            continue;
+        }
+        has_mains = true;
+        if fs::read_to_string(entry.path().join("online"))?.trim_end() == "1" {
+            return Ok(false);
+        }
+    }
+    Ok(has_mains)
+}
+
+/// Delay the screen lock state is reused for, by all the modules of the process
+const SCREEN_LOCK_STATE_TTL: Duration = Duration::from_secs(10);
+
+/// Return whether the screen is locked, querying it at most once every [`SCREEN_LOCK_STATE_TTL`]
+fn screen_locked() -> bool {
+    static CACHE: Mutex<Option<(Instant, bool)>> = Mutex::new(None);
+    // Lock is held while querying, so concurrent callers wait for the result instead of querying too
+    let mut cache = CACHE.lock().unwrap();
+    if let Some((time, locked)) = *cache
+        && time.elapsed() < SCREEN_LOCK_STATE_TTL
+    {
+        return locked;
+    }
+    let locked = query_screen_locked()
+        .inspect_err(|err| log::debug!("Failed to get screen lock state: {err:#}"))
+        .unwrap_or(false);
+    *cache = Some((Instant::now(), locked));
+    locked
+}
+
+/// Return whether the screen locker reported the session as locked to systemd-logind
+fn query_screen_locked() -> anyhow::Result<bool> {
+    let session = env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".to_owned());
+    let output = Command::new("loginctl")
+        .args(["show-session", &session, "--property=LockedHint", "--value"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()?;
+    output.status.exit_ok()?;
+    Ok(output.stdout.trim_ascii() == b"yes")
+}
+
 /// Time since boot, including time spent suspended
 pub(crate) fn boottime() -> Duration {
     clock_gettime(ClockId::Boottime).try_into().unwrap()
@@ -200,6 +374,127 @@ pub(crate) fn wait_network_ready() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Serialize `value` as a string, for types not implementing [`serde::Serialize`] the way we want
+pub(crate) fn serialize_display<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Display,
+    S: serde::Serializer,
+{
+    serializer.collect_str(value)
+}
+
+/// Create empty flag file at `path`, or remove it if it exists
+pub(crate) fn toggle_flag_file(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            fs::File::create(path)?;
+            Ok(())
+        }
+        res => res,
+    }
+}
+
+/// Flag file set by automatic detection, only changed when the detected state changes, so that a manual
+/// toggle holds until then
+struct DetectedFlag {
+    filepath: PathBuf,
+    /// Whether the condition was detected at last update
+    detected: bool,
+    /// Whether the flag file was created by detection, and not manually
+    created: bool,
+}
+
+impl DetectedFlag {
+    fn new(filepath: PathBuf) -> Self {
+        Self {
+            filepath,
+            detected: false,
+            created: false,
+        }
+    }
+
+    /// Update flag file from the reason the condition is detected, if it is
+    fn update(&mut self, reason: Option<&str>) -> io::Result<()> {
+        let detected = reason.is_some();
+        if detected == self.detected {
+            return Ok(());
+        }
+        self.detected = detected;
+        if let Some(reason) = reason {
+            if !self.filepath.exists() {
+                log::info!("Setting {:?}: {reason}", self.filepath);
+                fs::File::create(&self.filepath)?;
+                self.created = true;
+            }
+        } else if self.created {
+            // A flag set manually is left alone
+            log::info!("Removing {:?}", self.filepath);
+            self.created = false;
+            match fs::remove_file(&self.filepath) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn run<F>(&mut self, interval: Duration, mut detect: F) -> !
+    where
+        F: FnMut() -> Option<String>,
+    {
+        loop {
+            if let Err(err) = self.update(detect().as_deref()) {
+                log::warn!("Failed to update flag file {:?}: {err}", self.filepath);
+            }
+            sleep(interval);
+        }
+    }
+}
+
+/// Run `detect` every `interval` in thread `name`, setting flag file at `filepath` while it returns a reason
+///
+/// Only one process of the user runs it, the others wait for its lock to take over if it exits, and meanwhile only
+/// read the flag file.
+pub(crate) fn spawn_flag_detection<F>(name: &str, filepath: PathBuf, interval: Duration, detect: F)
+where
+    F: FnMut() -> Option<String> + Send + 'static,
+{
+    let mut flag = DetectedFlag::new(filepath);
+    let xdg_dirs = xdg::BaseDirectories::with_prefix(env!("CARGO_PKG_NAME"));
+    let lock_filepath = match xdg_dirs.place_runtime_file(format!("{name}-detection.lock")) {
+        Ok(lock_filepath) => lock_filepath,
+        Err(err) => {
+            log::error!("Failed to get {name} detection lock file: {err}");
+            return;
+        }
+    };
+    let thread_name = name.to_owned();
+    if let Err(err) = thread::Builder::new().name(name.to_owned()).spawn(move || {
+        let _lock = match lock_file(&lock_filepath) {
+            Ok(lock) => lock,
+            Err(err) => {
+                log::error!("Failed to lock {lock_filepath:?}: {err}");
+                return;
+            }
+        };
+        log::info!("Starting {thread_name} detection");
+        flag.run(interval, detect)
+    }) {
+        log::error!("Failed to start {name} detection: {err}");
+    }
+}
+
+/// Wait for an exclusive lock on `filepath`, held until the returned file is closed, ie. when the process exits
+fn lock_file(filepath: &Path) -> io::Result<fs::File> {
+    let file = fs::File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(filepath)?;
+    rustix::fs::flock(&file, rustix::fs::FlockOperation::LockExclusive)?;
+    Ok(file)
+}
+
 pub(crate) fn is_systemd_user_unit_running(name: &str) -> bool {
     Command::new("systemctl")
         .args(["--user", "-q", "is-active", name])
@@ -212,8 +507,136 @@ pub(crate) fn is_systemd_user_unit_running(name: &str) -> bool {
 
 #[cfg(test)]
 mod tests {
+    use std::thread;
+
     use super::*;
 
+    #[test]
+    fn test_lock_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let filepath = dir.path().join("test.lock");
+        let lock = lock_file(&filepath).unwrap();
+
+        let waiter = thread::spawn({
+            let filepath = filepath.clone();
+            move || lock_file(&filepath).unwrap()
+        });
+        sleep(Duration::from_millis(100));
+        assert!(!waiter.is_finished());
+
+        // Lock is taken over once released
+        drop(lock);
+        waiter.join().unwrap();
+    }
+
+    #[test]
+    fn test_env_watch_wait_until() {
+        let dir = tempfile::tempdir().unwrap();
+        let filepath = dir.path().join("f");
+        let watch = EnvWatch::new(&[dir.path()]);
+
+        assert!(!watch.wait_until(|| !filepath.exists()));
+
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                sleep(Duration::from_millis(100));
+                fs::write(&filepath, "").unwrap();
+            });
+            assert!(watch.wait_until(|| filepath.exists()));
+        });
+    }
+
+    #[test]
+    fn test_toggle_flag_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let filepath = dir.path().join("f");
+
+        toggle_flag_file(&filepath).unwrap();
+        assert!(filepath.exists());
+        toggle_flag_file(&filepath).unwrap();
+        assert!(!filepath.exists());
+    }
+
+    #[test]
+    fn test_on_battery() {
+        let power_supply_dir = tempfile::tempdir().unwrap();
+        assert!(!on_battery(power_supply_dir.path()).unwrap());
+
+        for (supply, supply_type, online) in [("BAT0", "Battery", None), ("AC", "Mains", Some("0"))]
+        {
+            let supply_dir = power_supply_dir.path().join(supply);
+            fs::create_dir(&supply_dir).unwrap();
+            fs::write(supply_dir.join("type"), format!("{supply_type}\n")).unwrap();
+            if let Some(online) = online {
+                fs::write(supply_dir.join("online"), format!("{online}\n")).unwrap();
+            }
+        }
+        assert!(on_battery(power_supply_dir.path()).unwrap());
+
+        fs::write(power_supply_dir.path().join("AC/online"), "1\n").unwrap();
+        assert!(!on_battery(power_supply_dir.path()).unwrap());
+    }
+
+    #[test]
+    fn test_scale_interval() {
+        let cfg = config::PowerConfig {
+            battery_interval_factor: 2.0,
+            locked_interval_s: Some(30),
+        };
+        let interval = Duration::from_secs(10);
+        assert_eq!(scale_interval(interval, &cfg, false, || false), interval);
+        assert_eq!(
+            scale_interval(interval, &cfg, true, || false),
+            Duration::from_secs(20)
+        );
+        assert_eq!(
+            scale_interval(interval, &cfg, false, || true),
+            Duration::from_secs(30)
+        );
+
+        // Both apply, the longest wins
+        assert_eq!(
+            scale_interval(interval, &cfg, true, || true),
+            Duration::from_secs(30)
+        );
+        assert_eq!(
+            scale_interval(Duration::from_secs(20), &cfg, true, || true),
+            Duration::from_secs(40)
+        );
+        assert_eq!(
+            scale_interval(Duration::from_secs(20), &cfg, true, || {
+                panic!("lock state queried")
+            }),
+            Duration::from_secs(40)
+        );
+    }
+
+    #[test]
+    fn test_detected_flag() {
+        let dir = tempfile::tempdir().unwrap();
+        let filepath = dir.path().join("f");
+        let mut flag = DetectedFlag::new(filepath.clone());
+
+        flag.update(Some("detected")).unwrap();
+        assert!(filepath.exists());
+        flag.update(None).unwrap();
+        assert!(!filepath.exists());
+
+        // Manual toggle while detected holds
+        flag.update(Some("detected")).unwrap();
+        fs::remove_file(&filepath).unwrap();
+        flag.update(Some("detected")).unwrap();
+        assert!(!filepath.exists());
+        flag.update(None).unwrap();
+        assert!(!filepath.exists());
+
+        // Flag set manually is not removed
+        fs::write(&filepath, "").unwrap();
+        flag.update(Some("detected")).unwrap();
+        flag.update(None).unwrap();
+        assert!(filepath.exists());
+    }
+
     #[test]
     fn test_sleep_suspend_aware_waits_for_the_whole_delay() {
         const DELAY: Duration = Duration::from_millis(200);
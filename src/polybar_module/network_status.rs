@@ -8,15 +8,17 @@ use std::{
     time::{Duration, Instant},
 };
 
+use anyhow::Context as _;
 use sysinfo::Networks;
 
 use crate::{
-    config, markup,
+    alert, config,
+    icons::Icon,
+    markup,
     polybar_module::{NetworkMode, PolybarModuleEnv, RenderablePolybarModule},
-    theme::{self, ICON_WARNING},
+    theme,
 };
 
-const PING_AVG_COUNT: usize = 3;
 const AGGREGATE_DELAY: Duration = Duration::from_millis(200);
 
 pub(crate) struct NetworkStatusModule {
@@ -31,7 +33,7 @@ pub(crate) struct NetworkStatusModule {
     networks: Networks,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, serde::Serialize)]
 pub(crate) struct NetworkStatusModuleState {
     reachable_hosts: Vec<bool>,
     vpn: Vec<String>,
@@ -53,9 +55,10 @@ impl NetworkStatusModule {
         }
         let poller_events = mio::Events::with_capacity(ping_childs.len());
 
+        let ping_avg_count = cfg.ping_avg_count.get();
         let host_state_history =
             vec![
-                bounded_vec_deque::BoundedVecDeque::with_capacity(PING_AVG_COUNT, PING_AVG_COUNT);
+                bounded_vec_deque::BoundedVecDeque::with_capacity(ping_avg_count, ping_avg_count);
                 ping_childs.len()
             ];
         let ping_child_deaths = HashMap::new();
@@ -262,9 +265,6 @@ impl Drop for NetworkStatusModule {
     }
 }
 
-const ICON_NETWORK: &str = "";
-const ICON_NETWORK_VPN: &str = "󰒃";
-
 impl RenderablePolybarModule for NetworkStatusModule {
     type State = Option<NetworkStatusModuleState>;
 
@@ -301,7 +301,7 @@ impl RenderablePolybarModule for NetworkStatusModule {
         match state {
             Some(state) => {
                 let mut fragments: Vec<String> = vec![
-                    markup::Markup::new(ICON_NETWORK)
+                    markup::Markup::new(Icon::Network)
                         .fg(theme::Color::MainIcon)
                         .into_string(),
                 ];
@@ -318,7 +318,7 @@ impl RenderablePolybarModule for NetworkStatusModule {
                 if !state.vpn.is_empty() {
                     fragments.push(format!(
                         " {}",
-                        markup::Markup::new(ICON_NETWORK_VPN)
+                        markup::Markup::new(Icon::NetworkVpn)
                             .fg(theme::Color::MainIcon)
                             .into_string()
                     ));
@@ -332,11 +332,46 @@ impl RenderablePolybarModule for NetworkStatusModule {
                 }
                 fragments.join(" ")
             }
-            None => markup::Markup::new(ICON_WARNING)
+            None => markup::Markup::new(Icon::Warning)
                 .fg(theme::Color::Attention)
                 .into_string(),
         }
     }
+
+    fn alerts(&self, prev_state: &Self::State, state: &Self::State) -> Vec<alert::Alert> {
+        let (Some(prev_state), Some(state)) = (prev_state, state) else {
+            return Vec::new();
+        };
+        prev_state
+            .reachable_hosts
+            .iter()
+            .zip(&state.reachable_hosts)
+            .zip(&self.cfg.hosts)
+            .filter(|((prev_reachable, reachable), host_info)| {
+                host_info.warn_unreachable && **prev_reachable && !**reachable
+            })
+            .map(|(_, host_info)| alert::Alert {
+                rule: "host_unreachable",
+                urgency: alert::Urgency::Normal,
+                summary: format!("{} is unreachable", host_info.name),
+                body: host_info.host.clone(),
+            })
+            .collect()
+    }
+
+    fn reload_config(&mut self, cfg: &config::Config) -> anyhow::Result<bool> {
+        let cfg = cfg
+            .module
+            .as_ref()
+            .and_then(|m| m.network_status.as_ref())
+            .context("Missing 'module.network_status' config section")?;
+        if *cfg == self.cfg {
+            return Ok(false);
+        }
+        // Previous ping processes are killed when the old instance is dropped
+        *self = Self::new(cfg.clone())?;
+        Ok(true)
+    }
 }
 
 #[cfg(test)]
@@ -359,6 +394,7 @@ mod tests {
                     warn_unreachable: true,
                 },
             ],
+            ping_avg_count: 3.try_into().unwrap(),
         })
         .unwrap();
 
@@ -368,7 +404,7 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            "%{F#f1e9d2}%{F-} %{u#8faaab}%{+u}h1%{-u} %{u#8faaab}%{+u}h2%{-u}"
+            "%{F:main_icon}%{F-} %{u:foreground}%{+u}h1%{-u} %{u:foreground}%{+u}h2%{-u}"
         );
 
         let state = Some(NetworkStatusModuleState {
@@ -377,7 +413,7 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            "%{F#f1e9d2}%{F-} h1 %{u#8faaab}%{+u}h2%{-u}"
+            "%{F:main_icon}%{F-} h1 %{u:foreground}%{+u}h2%{-u}"
         );
 
         let state = Some(NetworkStatusModuleState {
@@ -386,7 +422,7 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            "%{F#f1e9d2}%{F-} %{u#8faaab}%{+u}h1%{-u} %{F#d56500}h2%{F-}"
+            "%{F:main_icon}%{F-} %{u:foreground}%{+u}h1%{-u} %{F:attention}h2%{F-}"
         );
 
         let state = Some(NetworkStatusModuleState {
@@ -395,10 +431,10 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            "%{F#f1e9d2}%{F-} %{u#8faaab}%{+u}h1%{-u} %{F#d56500}h2%{F-}  %{F#f1e9d2}󰒃%{F-} %{u#8faaab}%{+u}i1%{-u}"
+            "%{F:main_icon}%{F-} %{u:foreground}%{+u}h1%{-u} %{F:attention}h2%{F-}  %{F:main_icon}󰒃%{F-} %{u:foreground}%{+u}i1%{-u}"
         );
 
         let state = None;
-        assert_eq!(module.render(&state), "%{F#d56500}%{F-}");
+        assert_eq!(module.render(&state), "%{F:attention}%{F-}");
     }
 }
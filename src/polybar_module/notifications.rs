@@ -1,32 +1,61 @@
-use std::process::Command;
+use std::{
+    process::{Command, Stdio},
+    sync::mpsc::{Receiver, Sender, channel},
+};
 
-use crate::{markup, polybar_module::RenderablePolybarModule, theme};
+use crate::{
+    control::{self, ControlCommand},
+    icons::Icon,
+    markup,
+    polybar_module::RenderablePolybarModule,
+    theme,
+};
 
 pub(crate) struct NotificationsModule {
-    signals: signal_hook::iterator::Signals,
+    refresh_tx: Sender<()>,
+    refresh_rx: Receiver<()>,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, serde::Serialize)]
 pub(crate) struct NotificationsModuleState {
     enabled: bool,
 }
 
 impl NotificationsModule {
-    pub(crate) fn new() -> anyhow::Result<Self> {
-        let signals = signal_hook::iterator::Signals::new([signal_hook::consts::signal::SIGUSR1])?;
-        Ok(Self { signals })
+    pub(crate) fn new() -> Self {
+        let (refresh_tx, refresh_rx) = channel();
+        Self {
+            refresh_tx,
+            refresh_rx,
+        }
     }
-}
 
-const ICON_NOTIFICATIONS_ENABLED: &str = "󰍩";
-const ICON_NOTIFICATIONS_DISABLED: &str = "󰚣";
+    fn handle(refresh_tx: &Sender<()>, command: &ControlCommand) -> anyhow::Result<()> {
+        match command {
+            ControlCommand::refresh => {}
+            ControlCommand::toggle => {
+                Command::new("dunstctl")
+                    .args(["set-paused", "toggle"])
+                    // Standard output may be the bar protocol stream, so it must not be inherited
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .status()?
+                    .exit_ok()?;
+            }
+            _ => return Err(control::unsupported(command)),
+        }
+        refresh_tx.send(())?;
+        Ok(())
+    }
+}
 
 impl RenderablePolybarModule for NotificationsModule {
     type State = NotificationsModuleState;
 
     fn wait_update(&mut self, prev_state: Option<&Self::State>) {
         if let Some(_prev_state) = prev_state {
-            self.signals.forever().next();
+            // Sender is owned by the module too, so this never fails
+            let _ = self.refresh_rx.recv();
         }
     }
 
@@ -34,35 +63,31 @@ impl RenderablePolybarModule for NotificationsModule {
         Self::State {
             enabled: !Command::new("dunstctl")
                 .args(["is-paused", "-e"])
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
                 .status()
                 .map_or(true, |s| s.success()),
         }
     }
 
     fn render(&self, state: &Self::State) -> String {
+        let action = control::action("notifications", &ControlCommand::toggle);
         if state.enabled {
-            markup::Markup::new(ICON_NOTIFICATIONS_ENABLED)
-                .action(
-                    markup::PolybarActionType::ClickLeft,
-                    format!(
-                        "dunstctl set-paused true && pkill -USR1 -f '{} notifications$'",
-                        env!("CARGO_PKG_NAME")
-                    ),
-                )
+            markup::Markup::new(Icon::NotificationsEnabled)
+                .action(markup::PolybarActionType::ClickLeft, action)
                 .into_string()
         } else {
-            markup::Markup::new(ICON_NOTIFICATIONS_DISABLED)
+            markup::Markup::new(Icon::NotificationsDisabled)
                 .underline(theme::Color::Notice)
-                .action(
-                    markup::PolybarActionType::ClickLeft,
-                    format!(
-                        "dunstctl set-paused false && pkill -USR1 -f '{} notifications$'",
-                        env!("CARGO_PKG_NAME")
-                    ),
-                )
+                .action(markup::PolybarActionType::ClickLeft, action)
                 .into_string()
         }
     }
+
+    fn control_handler(&self) -> Option<control::Handler> {
+        let refresh_tx = self.refresh_tx.clone();
+        Some(Box::new(move |command| Self::handle(&refresh_tx, command)))
+    }
 }
 
 #[cfg(test)]
@@ -72,18 +97,18 @@ mod tests {
 
     #[test]
     fn test_render() {
-        let module = NotificationsModule::new().unwrap();
+        let module = NotificationsModule::new();
 
         let state = NotificationsModuleState { enabled: true };
         assert_eq!(
             module.render(&state),
-            "%{A1:dunstctl set-paused true && pkill -USR1 -f 'polybar-modules notifications$':}\u{f0369}%{A}",
+            "%{A1:polybar-modules ctl notifications toggle:}\u{f0369}%{A}",
         );
 
         let state = NotificationsModuleState { enabled: false };
         assert_eq!(
             module.render(&state),
-            "%{A1:dunstctl set-paused false && pkill -USR1 -f 'polybar-modules notifications$':}%{u#ac8300}%{+u}\u{f06a3}%{-u}%{A}"
+            "%{A1:polybar-modules ctl notifications toggle:}%{u:notice}%{+u}\u{f06a3}%{-u}%{A}"
         );
     }
 }
@@ -2,17 +2,29 @@ use std::{
     io::{BufRead as _, BufReader, ErrorKind},
     os::fd::AsRawFd as _,
     process::{Child, Command, Stdio},
+    time::Duration,
 };
 
-use crate::{markup, polybar_module::RenderablePolybarModule, theme};
+use crate::{
+    config::{self, MarqueeConfig},
+    icons::Icon,
+    layout, markup,
+    polybar_module::RenderablePolybarModule,
+    theme,
+};
 
 pub(crate) struct PlayerModule {
     playerctl: Child,
     poller: mio::Poll,
     max_len: usize,
+    marquee_cfg: Option<MarqueeConfig>,
+    marquee: Option<layout::Marquee>,
+    /// Whether playerctl has output to read, or the wait timed out to scroll the title
+    readable: bool,
+    last_state: Option<PlayerModuleState>,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, serde::Serialize)]
 pub(crate) struct PlayerModuleState {
     player: String,
     status: String,
@@ -22,14 +34,24 @@ pub(crate) struct PlayerModuleState {
 }
 
 impl PlayerModule {
-    pub(crate) fn new(max_len: usize) -> anyhow::Result<Self> {
-        let playerctl = Command::new("playerctl")
-            .args([
-                "metadata",
-                "--follow",
-                "--format",
-                "{{playerName}}│{{status}}│{{ artist }}│{{album}}│{{ title }}",
-            ])
+    pub(crate) fn new(max_len: usize, marquee_cfg: Option<&MarqueeConfig>) -> anyhow::Result<Self> {
+        let mut command = Command::new("playerctl");
+        command.args([
+            "metadata",
+            "--follow",
+            "--format",
+            "{{playerName}}│{{status}}│{{ artist }}│{{album}}│{{ title }}",
+        ]);
+        Self::with_command(command, max_len, marquee_cfg)
+    }
+
+    /// Build module reading metadata lines from `command`
+    fn with_command(
+        mut command: Command,
+        max_len: usize,
+        marquee_cfg: Option<&MarqueeConfig>,
+    ) -> anyhow::Result<Self> {
+        let playerctl = command
             .stdin(Stdio::null())
             .stdout(Stdio::piped())
             .stderr(Stdio::null())
@@ -48,8 +70,44 @@ impl PlayerModule {
             playerctl,
             poller,
             max_len,
+            marquee_cfg: marquee_cfg.cloned(),
+            marquee: marquee_cfg.and_then(layout::Marquee::new),
+            // playerctl outputs current metadata right away, so the first update reads it without waiting
+            readable: true,
+            last_state: None,
         })
     }
+
+    fn layout(state: &PlayerModuleState) -> layout::Layout {
+        let status = match state.status.as_str() {
+            "Playing" => Icon::PlayerPlaying.to_string(),
+            "Paused" => Icon::PlayerPaused.to_string(),
+            "Stopped" => Icon::PlayerStopped.to_string(),
+            _ => state.status.clone(),
+        };
+        let player = match state.player.as_str() {
+            "mpv" => Icon::Mpv.to_string(),
+            _ => state.player.clone(),
+        };
+        layout::Layout::default()
+            .group(" ")
+            .fragment(
+                markup::Markup::new(Icon::Player)
+                    .fg(theme::Color::MainIcon)
+                    .into_string(),
+                layout::REQUIRED,
+            )
+            .fragment(player, 0)
+            .fragment(status, layout::REQUIRED)
+            .group(
+                markup::Markup::new(" / ")
+                    .fg(theme::Color::Unfocused)
+                    .into_string(),
+            )
+            .fragment(state.artist.as_str(), 2)
+            .fragment(state.album.as_str(), 1)
+            .fragment(state.title.as_str(), layout::REQUIRED)
+    }
 }
 
 impl Drop for PlayerModule {
@@ -58,19 +116,15 @@ impl Drop for PlayerModule {
     }
 }
 
-const ICON_PLAYER: &str = "";
-const ICON_PLAYER_PLAYING: &str = "";
-const ICON_PLAYER_PAUSED: &str = "";
-const ICON_PLAYER_STOPPED: &str = "";
-
 impl RenderablePolybarModule for PlayerModule {
     type State = Option<PlayerModuleState>;
 
-    fn wait_update(&mut self, _prev_state: Option<&Self::State>) {
+    fn wait_update(&mut self, prev_state: Option<&Self::State>) {
         let mut poller_events = mio::Events::with_capacity(1);
+        let timeout = prev_state.and_then(|s| self.rerender_interval(s));
         log::trace!("Waiting for stdout data");
         loop {
-            let poll_res = self.poller.poll(&mut poller_events, None);
+            let poll_res = self.poller.poll(&mut poller_events, timeout);
             if let Err(e) = &poll_res
                 && e.kind() == ErrorKind::Interrupted
             {
@@ -79,16 +133,20 @@ impl RenderablePolybarModule for PlayerModule {
             }
             poll_res.unwrap();
             log::trace!("Poll returned with events {poller_events:?}");
-            if poller_events.iter().any(mio::event::Event::is_readable) {
+            self.readable = poller_events.iter().any(mio::event::Event::is_readable);
+            if self.readable || timeout.is_some() {
                 break;
             }
         }
     }
 
     fn update(&mut self) -> Self::State {
+        if !self.readable {
+            return self.last_state.clone();
+        }
         let stdout = self.playerctl.stdout.as_mut().unwrap();
         let output = BufReader::new(stdout).lines().next().unwrap().unwrap();
-        if output.is_empty() {
+        self.last_state = if output.is_empty() {
             None
         } else {
             let mut tokens = output.split('│');
@@ -99,69 +157,59 @@ impl RenderablePolybarModule for PlayerModule {
                 album: tokens.next().unwrap().to_owned(),
                 title: tokens.next().unwrap().to_owned(),
             })
-        }
+        };
+        self.last_state.clone()
     }
 
     fn render(&self, state: &Self::State) -> String {
         match state {
-            Some(state) => {
-                let status = match state.status.as_str() {
-                    "Playing" => ICON_PLAYER_PLAYING,
-                    "Paused" => ICON_PLAYER_PAUSED,
-                    "Stopped" => ICON_PLAYER_STOPPED,
-                    _ => state.status.as_str(),
-                };
-                let player = match state.player.as_str() {
-                    "mpv" => "",
-                    _ => state.player.as_str(),
-                };
-                let mut s = String::new();
-                let base_tokens_candidates = [
-                    (
-                        vec![
-                            player,
-                            status,
-                            state.artist.as_str(),
-                            state.album.as_str(),
-                            state.title.as_str(),
-                        ],
-                        2,
-                    ),
-                    (
-                        vec![
-                            status,
-                            state.artist.as_str(),
-                            state.album.as_str(),
-                            state.title.as_str(),
-                        ],
-                        1,
-                    ),
-                    (vec![status, state.artist.as_str(), state.title.as_str()], 1),
-                    (vec![status, state.title.as_str()], 1),
-                ];
-                for (base_tokens, sep_idx) in base_tokens_candidates {
-                    let tokens: Vec<_> =
-                        base_tokens.into_iter().filter(|t| !t.is_empty()).collect();
-                    let (first_tokens, other_tokens) = tokens.split_at(sep_idx);
-                    s = format!(
-                        "{} {} {}",
-                        markup::Markup::new(ICON_PLAYER)
-                            .fg(theme::Color::MainIcon)
-                            .into_string(),
-                        first_tokens.join(" "),
-                        other_tokens.join(
-                            &markup::Markup::new(" / ")
-                                .fg(theme::Color::Unfocused)
-                                .into_string(),
-                        )
-                    );
-                    if s.len() <= self.max_len {
-                        return s;
-                    }
+            Some(state) => match &self.marquee {
+                Some(marquee) => {
+                    Self::layout(state).render_with(Some(self.max_len), |s, w| marquee.window(s, w))
                 }
-                theme::ellipsis(&s, Some(self.max_len))
-            }
+                None => Self::layout(state).render(Some(self.max_len)),
+            },
             None => String::new(),
         }
     }
+
+    fn rerender_interval(&self, state: &Self::State) -> Option<Duration> {
+        let marquee = self.marquee.as_ref()?;
+        let state = state.as_ref()?;
+        Self::layout(state)
+            .overflows(self.max_len)
+            .then(|| marquee.step())
+    }
+
+    fn reload_config(&mut self, cfg: &config::Config) -> anyhow::Result<bool> {
+        if cfg.marquee != self.marquee_cfg {
+            self.marquee_cfg.clone_from(&cfg.marquee);
+            self.marquee = cfg.marquee.as_ref().and_then(layout::Marquee::new);
+        }
+        // Scrolling is not part of the state, so the module is kept
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_once() {
+        let mut command = Command::new("echo");
+        command.arg("mpv│Playing│Artist│Album│Title");
+        let module = PlayerModule::with_command(command, 50, None).unwrap();
+
+        let mut outputs = vec![];
+        crate::render_loop(module, &crate::RunOpts::once(), |output, _detail| {
+            outputs.push(output);
+        })
+        .unwrap();
+        assert_eq!(outputs.len(), 1);
+        assert!(
+            outputs[0].contains("Artist") && outputs[0].ends_with("Title"),
+            "{outputs:?}"
+        );
+    }
 }
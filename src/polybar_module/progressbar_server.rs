@@ -9,11 +9,7 @@ use std::{
     time::Duration,
 };
 
-use crate::{
-    markup,
-    polybar_module::RenderablePolybarModule,
-    theme::{self, ICON_WARNING},
-};
+use crate::{icons::Icon, markup, polybar_module::RenderablePolybarModule, theme};
 
 pub(crate) struct ProgressBarServerModule {
     listener: UnixListener,
@@ -24,7 +20,7 @@ pub(crate) struct ProgressBarServerModule {
     cur_progress: BTreeMap<usize, u32>,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, serde::Serialize)]
 pub(crate) struct ProgressBarServerModuleState {
     progress: Vec<u32>,
 }
@@ -136,20 +132,18 @@ impl ProgressBarServerModule {
     }
 }
 
-const PROGRESS_ICONS: [&str; 9] = [
-    "", // nf-fa-hourglass_start
-    "󰪞", // nf-md-circle_slice_1
-    "󰪟", // nf-md-circle_slice_2
-    "󰪠", // nf-md-circle_slice_3
-    "󰪡", // nf-md-circle_slice_4
-    "󰪢", // nf-md-circle_slice_5
-    "󰪣", // nf-md-circle_slice_6
-    "󰪤", // nf-md-circle_slice_7
-    "󰪥", // nf-md-circle_slice_8
+const PROGRESS_ICONS: [Icon; 9] = [
+    Icon::HourglassStart,
+    Icon::CircleSlice1,
+    Icon::CircleSlice2,
+    Icon::CircleSlice3,
+    Icon::CircleSlice4,
+    Icon::CircleSlice5,
+    Icon::CircleSlice6,
+    Icon::CircleSlice7,
+    Icon::CircleSlice8,
 ];
 
-const ICON_PROGRESSBAR_SERVER: &str = "";
-
 impl RenderablePolybarModule for ProgressBarServerModule {
     type State = Option<ProgressBarServerModuleState>;
 
@@ -189,14 +183,14 @@ impl RenderablePolybarModule for ProgressBarServerModule {
                         .collect();
                     format!(
                         "{} {}",
-                        markup::Markup::new(ICON_PROGRESSBAR_SERVER)
+                        markup::Markup::new(Icon::ProgressbarServer)
                             .fg(theme::Color::MainIcon)
                             .into_string(),
                         progress_chars,
                     )
                 }
             }
-            None => markup::Markup::new(ICON_WARNING)
+            None => markup::Markup::new(Icon::Warning)
                 .fg(theme::Color::Attention)
                 .into_string(),
         }
@@ -216,25 +210,37 @@ mod tests {
         assert_eq!(module.render(&state), "");
 
         let state = Some(ProgressBarServerModuleState { progress: vec![0] });
-        assert_eq!(module.render(&state), "%{F#f1e9d2}%{F-} %{F#8faaab}%{F-}");
+        assert_eq!(
+            module.render(&state),
+            "%{F:main_icon}%{F-} %{F:foreground}%{F-}"
+        );
 
         let state = Some(ProgressBarServerModuleState { progress: vec![1] });
-        assert_eq!(module.render(&state), "%{F#f1e9d2}%{F-} %{F#8faaab}󰪞%{F-}");
+        assert_eq!(
+            module.render(&state),
+            "%{F:main_icon}%{F-} %{F:foreground}󰪞%{F-}"
+        );
 
         let state = Some(ProgressBarServerModuleState { progress: vec![50] });
-        assert_eq!(module.render(&state), "%{F#f1e9d2}%{F-} %{F#8faaab}󰪡%{F-}");
+        assert_eq!(
+            module.render(&state),
+            "%{F:main_icon}%{F-} %{F:foreground}󰪡%{F-}"
+        );
 
         let state = Some(ProgressBarServerModuleState {
             progress: vec![100],
         });
-        assert_eq!(module.render(&state), "%{F#f1e9d2}%{F-} %{F#8faaab}󰪥%{F-}");
+        assert_eq!(
+            module.render(&state),
+            "%{F:main_icon}%{F-} %{F:foreground}󰪥%{F-}"
+        );
 
         let state = Some(ProgressBarServerModuleState {
             progress: vec![30, 100],
         });
         assert_eq!(
             module.render(&state),
-            "%{F#f1e9d2}%{F-} %{F#8faaab}󰪠%{F-}%{F#8faaab}󰪥%{F-}"
+            "%{F:main_icon}%{F-} %{F:foreground}󰪠%{F-}%{F:foreground}󰪥%{F-}"
         );
 
         let state = Some(ProgressBarServerModuleState {
@@ -242,10 +248,10 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            "%{F#f1e9d2}%{F-} %{F#8faaab}󰪠%{F-}%{F#8faaab}󰪡%{F-}%{F#8faaab}󰪥%{F-}"
+            "%{F:main_icon}%{F-} %{F:foreground}󰪠%{F-}%{F:foreground}󰪡%{F-}%{F:foreground}󰪥%{F-}"
         );
 
         let state = None;
-        assert_eq!(module.render(&state), "%{F#d56500}%{F-}");
+        assert_eq!(module.render(&state), "%{F:attention}%{F-}");
     }
 }
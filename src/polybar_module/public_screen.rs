@@ -0,0 +1,225 @@
+//! Automatic detection of screen sharing, setting the flag file of [`super::PolybarModuleEnv::public_screen`]
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::Path,
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use crate::{config::PublicScreenConfig, polybar_module::spawn_flag_detection};
+
+/// Maximum length of process names in `/proc/<pid>/comm`
+const PROC_COMM_MAX_LEN: usize = 15;
+
+/// Start detection in a background thread, once the process holds the detection lock
+pub(crate) fn start_detection(cfg: &PublicScreenConfig, flag_filepath: &Path) {
+    if !cfg.detect {
+        return;
+    }
+    let cfg = cfg.clone();
+    spawn_flag_detection(
+        "public-screen",
+        flag_filepath.to_owned(),
+        Duration::from_secs(cfg.interval_s.get()),
+        move || detect(&cfg),
+    );
+}
+
+/// Return why the screen is public, if it is
+fn detect(cfg: &PublicScreenConfig) -> Option<String> {
+    match connected_output(Path::new("/sys/class/drm"), &cfg.outputs) {
+        Ok(Some(output)) => return Some(format!("output {output} is connected")),
+        Ok(None) => {}
+        Err(err) => log::warn!("Failed to get output status: {err}"),
+    }
+    match running_process(Path::new("/proc"), &cfg.processes) {
+        Ok(Some(process)) => return Some(format!("process {process} is running")),
+        Ok(None) => {}
+        Err(err) => log::warn!("Failed to list processes: {err}"),
+    }
+    if cfg.pipewire_screencast {
+        match pipewire_screencast() {
+            Ok(true) => return Some("PipeWire screencast is running".to_owned()),
+            Ok(false) => {}
+            Err(err) => log::debug!("Failed to list PipeWire nodes: {err:#}"),
+        }
+    }
+    None
+}
+
+/// Get name of the first connected DRM connector in `drm_dir` matching `outputs`, by type or name
+fn connected_output(drm_dir: &Path, outputs: &[String]) -> io::Result<Option<String>> {
+    if outputs.is_empty() {
+        return Ok(None);
+    }
+    for entry in fs::read_dir(drm_dir)? {
+        let entry = entry?;
+        // Connectors are named like card0-HDMI-A-1
+        let filename = entry.file_name();
+        let Some((_card, connector)) = filename.to_str().and_then(|f| f.split_once('-')) else {
+            continue;
+        };
+        if !outputs
+            .iter()
+            .any(|o| connector == o || connector.starts_with(&format!("{o}-")))
+        {
+            continue;
+        }
+        let Ok(status) = fs::read_to_string(entry.path().join("status")) else {
+            continue;
+        };
+        if status.trim_end() == "connected" {
+            return Ok(Some(connector.to_owned()));
+        }
+    }
+    Ok(None)
+}
+
+/// Get name of the first running process in `proc_dir` among `names`
+fn running_process(proc_dir: &Path, names: &[String]) -> io::Result<Option<String>> {
+    if names.is_empty() {
+        return Ok(None);
+    }
+    for entry in fs::read_dir(proc_dir)? {
+        let entry = entry?;
+        if !entry
+            .file_name()
+            .to_str()
+            .is_some_and(|f| f.bytes().all(|b| b.is_ascii_digit()))
+        {
+            continue;
+        }
+        // Process may have exited since
+        let Ok(comm) = fs::read_to_string(entry.path().join("comm")) else {
+            continue;
+        };
+        let comm = comm.trim_end();
+        if let Some(name) = names
+            .iter()
+            .find(|n| n.get(..PROC_COMM_MAX_LEN).unwrap_or(n) == comm)
+        {
+            return Ok(Some(name.to_owned()));
+        }
+    }
+    Ok(None)
+}
+
+#[derive(serde::Deserialize)]
+struct PipeWireObject {
+    #[serde(rename = "type")]
+    type_: String,
+    info: Option<PipeWireObjectInfo>,
+}
+
+#[derive(serde::Deserialize)]
+struct PipeWireObjectInfo {
+    #[serde(default)]
+    props: HashMap<String, serde_json::Value>,
+}
+
+/// Return whether a `PipeWire` screencast stream exists
+fn pipewire_screencast() -> anyhow::Result<bool> {
+    let output = Command::new("pw-dump")
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()?;
+    output.status.exit_ok()?;
+    has_screencast_node(&output.stdout)
+}
+
+/// Return whether `pw-dump` output has a video source node not backed by a device (ie. a camera),
+/// which is how desktop portals expose screencasts
+fn has_screencast_node(dump: &[u8]) -> anyhow::Result<bool> {
+    let objects: Vec<PipeWireObject> = serde_json::from_slice(dump)?;
+    Ok(objects
+        .iter()
+        .filter(|o| o.type_ == "PipeWire:Interface:Node")
+        .filter_map(|o| o.info.as_ref())
+        .any(|i| {
+            i.props.get("media.class").and_then(|c| c.as_str()) == Some("Video/Source")
+                && !i.props.contains_key("device.id")
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connected_output() {
+        let drm_dir = tempfile::tempdir().unwrap();
+        for (connector, status) in [
+            ("card0-eDP-1", "connected"),
+            ("card0-DP-1", "disconnected"),
+            ("card0-HDMI-A-1", "disconnected"),
+        ] {
+            fs::create_dir(drm_dir.path().join(connector)).unwrap();
+            fs::write(
+                drm_dir.path().join(connector).join("status"),
+                format!("{status}\n"),
+            )
+            .unwrap();
+        }
+        fs::create_dir(drm_dir.path().join("card0")).unwrap();
+        let outputs = PublicScreenConfig::default().outputs;
+
+        assert_eq!(connected_output(drm_dir.path(), &outputs).unwrap(), None);
+
+        fs::write(drm_dir.path().join("card0-HDMI-A-1/status"), "connected\n").unwrap();
+        assert_eq!(
+            connected_output(drm_dir.path(), &outputs).unwrap(),
+            Some("HDMI-A-1".to_owned())
+        );
+        assert_eq!(
+            connected_output(drm_dir.path(), &["HDMI-A-2".to_owned()]).unwrap(),
+            None
+        );
+        assert_eq!(connected_output(drm_dir.path(), &[]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_running_process() {
+        let proc_dir = tempfile::tempdir().unwrap();
+        fs::create_dir(proc_dir.path().join("1")).unwrap();
+        fs::write(proc_dir.path().join("1/comm"), "systemd\n").unwrap();
+        fs::create_dir(proc_dir.path().join("42")).unwrap();
+        fs::write(proc_dir.path().join("42/comm"), "obs-browser-pag\n").unwrap();
+        fs::create_dir(proc_dir.path().join("self")).unwrap();
+
+        assert_eq!(
+            running_process(proc_dir.path(), &["zoom".to_owned()]).unwrap(),
+            None
+        );
+        assert_eq!(
+            running_process(
+                proc_dir.path(),
+                &["zoom".to_owned(), "obs-browser-page".to_owned()]
+            )
+            .unwrap(),
+            Some("obs-browser-page".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_has_screencast_node() {
+        let camera = br#"[
+            {"id": 30, "type": "PipeWire:Interface:Core", "info": null},
+            {"id": 52, "type": "PipeWire:Interface:Node", "info": {"props": {
+                "media.class": "Video/Source", "device.id": 48, "node.name": "v4l2_input.pci-0000_00_14.0"
+            }}},
+            {"id": 60, "type": "PipeWire:Interface:Node", "info": {"props": {
+                "media.class": "Audio/Sink", "node.name": "alsa_output.pci-0000_00_1f.3.analog-stereo"
+            }}}
+        ]"#;
+        assert!(!has_screencast_node(camera).unwrap());
+
+        let screencast = br#"[
+            {"id": 52, "type": "PipeWire:Interface:Node", "info": {"props": {
+                "media.class": "Video/Source", "node.name": "xdpw_stream"
+            }}}
+        ]"#;
+        assert!(has_screencast_node(screencast).unwrap());
+    }
+}
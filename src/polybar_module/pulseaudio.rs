@@ -11,9 +11,11 @@ use anyhow::Context as _;
 
 use super::is_systemd_user_unit_running;
 use crate::{
+    control::{self, ControlCommand},
+    icons::Icon,
     markup,
     polybar_module::RenderablePolybarModule,
-    theme::{self, ICON_WARNING},
+    theme,
 };
 
 pub(crate) struct PulseAudioModule {
@@ -21,27 +23,29 @@ pub(crate) struct PulseAudioModule {
     easyeffects_installed: bool,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, serde::Serialize)]
 struct PulseAudioSource {
     id: u32,
     name: String,
     running: bool,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, serde::Serialize)]
 struct PulseAudioSink {
     id: u32,
     name: String,
     running: bool,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, serde::Serialize)]
 pub(crate) struct PulseAudioModuleState {
     sources: Vec<PulseAudioSource>,
     sinks: Vec<PulseAudioSink>,
     easyeffects: Option<bool>,
 }
 
+const EASYEFFECTS_UNIT: &str = "easyeffects.service";
+
 fn easyeffects_installed() -> bool {
     fs::metadata("/usr/bin/easyeffects").is_ok_and(|p| (p.permissions().mode() & 0o001) != 0)
 }
@@ -215,7 +219,7 @@ impl PulseAudioModule {
         }
         let easyeffects = self
             .easyeffects_installed
-            .then(|| is_systemd_user_unit_running("easyeffects.service"));
+            .then(|| is_systemd_user_unit_running(EASYEFFECTS_UNIT));
 
         Ok(PulseAudioModuleState {
             sources,
@@ -247,9 +251,6 @@ impl Drop for PulseAudioModule {
     }
 }
 
-const ICON_AUDIO_EFFECTS: &str = "󰋍";
-const ICON_MICROPHONE: &str = "";
-
 impl RenderablePolybarModule for PulseAudioModule {
     type State = Option<PulseAudioModuleState>;
 
@@ -302,19 +303,13 @@ impl RenderablePolybarModule for PulseAudioModule {
             Some(state) => {
                 let mut fragments: Vec<String> = Vec::new();
                 if let Some(easyeffects) = state.easyeffects {
-                    let mut fragment = markup::Markup::new(ICON_AUDIO_EFFECTS);
+                    let mut fragment = markup::Markup::new(Icon::AudioEffects);
                     if easyeffects {
                         fragment = fragment.underline(theme::Color::Foreground);
                     }
                     fragment = fragment.action(
                         markup::PolybarActionType::ClickLeft,
-                        // Note: starting or stopping easyeffects will trigger a pactl subscribe event,
-                        // which will naturally update module to reflect service status
-                        if easyeffects {
-                            "systemctl --user -q --no-block stop easyeffects.service".to_owned()
-                        } else {
-                            "systemctl --user -q --no-block start easyeffects.service".to_owned()
-                        },
+                        control::action("pulseaudio", &ControlCommand::toggle),
                     );
                     let fragment = fragment.into_string();
                     fragments.push(fragment);
@@ -329,7 +324,10 @@ impl RenderablePolybarModule for PulseAudioModule {
                             markup::Markup::new(&sink.name)
                                 .action(
                                     markup::PolybarActionType::ClickLeft,
-                                    format!("pactl set-default-sink {}", sink.id),
+                                    control::action(
+                                        "pulseaudio",
+                                        &ControlCommand::select_sink { id: sink.id },
+                                    ),
                                 )
                                 .into_string()
                         });
@@ -340,7 +338,7 @@ impl RenderablePolybarModule for PulseAudioModule {
                 }
                 if state.sources.len() > 1 {
                     fragments.push(
-                        markup::Markup::new(ICON_MICROPHONE)
+                        markup::Markup::new(Icon::Microphone)
                             .fg(theme::Color::MainIcon)
                             .into_string(),
                     );
@@ -353,7 +351,10 @@ impl RenderablePolybarModule for PulseAudioModule {
                             markup::Markup::new(&source.name)
                                 .action(
                                     markup::PolybarActionType::ClickLeft,
-                                    format!("pactl set-default-source {}", source.id),
+                                    control::action(
+                                        "pulseaudio",
+                                        &ControlCommand::select_source { id: source.id },
+                                    ),
                                 )
                                 .into_string()
                         });
@@ -361,11 +362,51 @@ impl RenderablePolybarModule for PulseAudioModule {
                 }
                 fragments.join(" ").trim_end().to_owned()
             }
-            None => markup::Markup::new(ICON_WARNING)
+            None => markup::Markup::new(Icon::Warning)
                 .fg(theme::Color::Attention)
                 .into_string(),
         }
     }
+
+    fn control_handler(&self) -> Option<control::Handler> {
+        // Note: all these trigger a pactl subscribe event, which will naturally update module
+        Some(Box::new(|command| {
+            let mut cmd = match command {
+                ControlCommand::toggle => {
+                    let mut cmd = Command::new("systemctl");
+                    cmd.args([
+                        "--user",
+                        "-q",
+                        "--no-block",
+                        if is_systemd_user_unit_running(EASYEFFECTS_UNIT) {
+                            "stop"
+                        } else {
+                            "start"
+                        },
+                        EASYEFFECTS_UNIT,
+                    ]);
+                    cmd
+                }
+                ControlCommand::select_sink { id } => {
+                    let mut cmd = Command::new("pactl");
+                    cmd.args(["set-default-sink", &id.to_string()]);
+                    cmd
+                }
+                ControlCommand::select_source { id } => {
+                    let mut cmd = Command::new("pactl");
+                    cmd.args(["set-default-source", &id.to_string()]);
+                    cmd
+                }
+                _ => return Err(control::unsupported(command)),
+            };
+            // Standard output may be the bar protocol stream, so it must not be inherited
+            cmd.stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .status()?
+                .exit_ok()?;
+            Ok(())
+        }))
+    }
 }
 
 #[cfg(test)]
@@ -413,7 +454,7 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            "%{A1:pactl set-default-sink 1:}si1%{A} %{u#8faaab}%{+u}si2%{-u}  %{F#f1e9d2}%{F-} %{A1:pactl set-default-source 1:}so1%{A} %{u#8faaab}%{+u}so2%{-u}"
+            "%{A1:polybar-modules ctl pulseaudio select-sink 1:}si1%{A} %{u:foreground}%{+u}si2%{-u}  %{F:main_icon}%{F-} %{A1:polybar-modules ctl pulseaudio select-source 1:}so1%{A} %{u:foreground}%{+u}so2%{-u}"
         );
 
         let state = Some(PulseAudioModuleState {
@@ -434,7 +475,7 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            "  %{F#f1e9d2}%{F-} %{A1:pactl set-default-source 1:}so1%{A} %{u#8faaab}%{+u}so2%{-u}"
+            "  %{F:main_icon}%{F-} %{A1:polybar-modules ctl pulseaudio select-source 1:}so1%{A} %{u:foreground}%{+u}so2%{-u}"
         );
 
         let state = Some(PulseAudioModuleState {
@@ -455,7 +496,7 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            "%{A1:pactl set-default-sink 1:}si1%{A} %{u#8faaab}%{+u}si2%{-u}"
+            "%{A1:polybar-modules ctl pulseaudio select-sink 1:}si1%{A} %{u:foreground}%{+u}si2%{-u}"
         );
 
         let state = Some(PulseAudioModuleState {
@@ -480,7 +521,7 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            "%{A1:systemctl --user -q --no-block stop easyeffects.service:}%{u#8faaab}%{+u}󰋍%{-u}%{A}"
+            "%{A1:polybar-modules ctl pulseaudio toggle:}%{u:foreground}%{+u}󰋍%{-u}%{A}"
         );
 
         let state = Some(PulseAudioModuleState {
@@ -490,7 +531,7 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            "%{A1:systemctl --user -q --no-block start easyeffects.service:}󰋍%{A}"
+            "%{A1:polybar-modules ctl pulseaudio toggle:}󰋍%{A}"
         );
 
         let state = Some(PulseAudioModuleState {
@@ -511,7 +552,7 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            "%{A1:systemctl --user -q --no-block stop easyeffects.service:}%{u#8faaab}%{+u}󰋍%{-u}%{A}   %{F#f1e9d2}%{F-} %{A1:pactl set-default-source 1:}so1%{A} %{u#8faaab}%{+u}so2%{-u}"
+            "%{A1:polybar-modules ctl pulseaudio toggle:}%{u:foreground}%{+u}󰋍%{-u}%{A}   %{F:main_icon}%{F-} %{A1:polybar-modules ctl pulseaudio select-source 1:}so1%{A} %{u:foreground}%{+u}so2%{-u}"
         );
 
         let state = Some(PulseAudioModuleState {
@@ -532,7 +573,7 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            "%{A1:systemctl --user -q --no-block stop easyeffects.service:}%{u#8faaab}%{+u}󰋍%{-u}%{A} %{A1:pactl set-default-sink 1:}si1%{A} %{u#8faaab}%{+u}si2%{-u}"
+            "%{A1:polybar-modules ctl pulseaudio toggle:}%{u:foreground}%{+u}󰋍%{-u}%{A} %{A1:polybar-modules ctl pulseaudio select-sink 1:}si1%{A} %{u:foreground}%{+u}si2%{-u}"
         );
 
         let state = Some(PulseAudioModuleState {
@@ -564,10 +605,10 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            "%{A1:systemctl --user -q --no-block stop easyeffects.service:}%{u#8faaab}%{+u}󰋍%{-u}%{A} %{A1:pactl set-default-sink 1:}si1%{A} %{u#8faaab}%{+u}si2%{-u}  %{F#f1e9d2}%{F-} %{A1:pactl set-default-source 1:}so1%{A} %{u#8faaab}%{+u}so2%{-u}"
+            "%{A1:polybar-modules ctl pulseaudio toggle:}%{u:foreground}%{+u}󰋍%{-u}%{A} %{A1:polybar-modules ctl pulseaudio select-sink 1:}si1%{A} %{u:foreground}%{+u}si2%{-u}  %{F:main_icon}%{F-} %{A1:polybar-modules ctl pulseaudio select-source 1:}so1%{A} %{u:foreground}%{+u}so2%{-u}"
         );
 
         let state = None;
-        assert_eq!(module.render(&state), "%{F#d56500}%{F-}");
+        assert_eq!(module.render(&state), "%{F:attention}%{F-}");
     }
 }
@@ -0,0 +1,368 @@
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{alert, config, control, markup, polybar_module::RenderablePolybarModule, theme};
+
+/// Wrapper of a module whose `None` state means its update failed, to keep showing its last good
+/// state, dimmed and with its age, until it gets older than the staleness limit
+pub(crate) struct StaleHoldModule<M, S> {
+    module: M,
+    limit: Duration,
+    last_good: Option<(S, SystemTime)>,
+    /// File the last good state is saved to, to show it right away on next start
+    cache_filepath: Option<PathBuf>,
+    cache_written: Option<SystemTime>,
+}
+
+#[derive(Debug, PartialEq, serde::Serialize)]
+#[serde(untagged)]
+pub(crate) enum StaleHoldState<S> {
+    /// State from the last update, `None` if it failed
+    Fresh(Option<S>),
+    /// Last good state, held because the last update failed
+    Stale { state: S, age_s: u64 },
+}
+
+impl<S: Clone> StaleHoldState<S> {
+    /// State shown, whether it is fresh or held
+    fn shown(&self) -> Option<S> {
+        match self {
+            Self::Fresh(state) => state.clone(),
+            Self::Stale { state, .. } => Some(state.clone()),
+        }
+    }
+}
+
+/// Last good state, as saved to the cache file
+#[derive(serde::Deserialize, serde::Serialize)]
+struct CachedState<S> {
+    /// Unix time of the update
+    timestamp_s: u64,
+    state: S,
+}
+
+/// Minimum delay between cache file writes, for modules updating often
+const CACHE_WRITE_INTERVAL: Duration = Duration::from_mins(1);
+
+impl<M, S> StaleHoldModule<M, S> {
+    pub(crate) fn new(module: M, limit: Duration) -> Self {
+        Self {
+            module,
+            limit,
+            last_good: None,
+            cache_filepath: None,
+            cache_written: None,
+        }
+    }
+
+    /// Save last good state to the cache file of module `name`, and start with it
+    pub(crate) fn with_cache(mut self, name: &str) -> Self {
+        let xdg_dirs = xdg::BaseDirectories::with_prefix(env!("CARGO_PKG_NAME"));
+        match xdg_dirs.place_cache_file(format!("{name}.json")) {
+            Ok(cache_filepath) => self.cache_filepath = Some(cache_filepath),
+            Err(err) => log::warn!("Failed to get state cache file of module {name:?}: {err}"),
+        }
+        self
+    }
+}
+
+impl<M, S> StaleHoldModule<M, S>
+where
+    S: serde::Serialize + serde::de::DeserializeOwned,
+{
+    fn read_cache(&self) -> anyhow::Result<Option<(S, SystemTime)>> {
+        let Some(cache_filepath) = &self.cache_filepath else {
+            return Ok(None);
+        };
+        let data = match fs::read(cache_filepath) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let cached: CachedState<S> = serde_json::from_slice(&data)?;
+        Ok(Some((
+            cached.state,
+            UNIX_EPOCH + Duration::from_secs(cached.timestamp_s),
+        )))
+    }
+
+    fn write_cache(&self, state: &S, time: SystemTime) -> anyhow::Result<()> {
+        let Some(cache_filepath) = &self.cache_filepath else {
+            return Ok(());
+        };
+        let data = serde_json::to_vec(&CachedState {
+            timestamp_s: time.duration_since(UNIX_EPOCH)?.as_secs(),
+            state,
+        })?;
+        // Write then rename, to never leave a truncated file behind
+        let tmp_filepath = cache_filepath.with_extension("json.tmp");
+        fs::write(&tmp_filepath, data)?;
+        fs::rename(&tmp_filepath, cache_filepath)?;
+        Ok(())
+    }
+}
+
+/// Format age as a short suffix, ie. `12m`
+fn format_age(age_s: u64) -> String {
+    match age_s {
+        0..60 => format!("{age_s}s"),
+        60..3600 => format!("{}m", age_s / 60),
+        3600..86400 => format!("{}h", age_s / 3600),
+        _ => format!("{}d", age_s / 86400),
+    }
+}
+
+impl<M, S> RenderablePolybarModule for StaleHoldModule<M, S>
+where
+    M: RenderablePolybarModule<State = Option<S>>,
+    S: Clone + std::fmt::Debug + PartialEq + serde::Serialize + serde::de::DeserializeOwned,
+{
+    type State = StaleHoldState<S>;
+
+    fn wait_update(&mut self, prev_state: Option<&Self::State>) {
+        // Module sees a held state as the failed update it is
+        let failed = None;
+        self.module.wait_update(prev_state.map(|s| match s {
+            StaleHoldState::Fresh(state) => state,
+            StaleHoldState::Stale { .. } => &failed,
+        }));
+    }
+
+    fn update(&mut self) -> Self::State {
+        if let Some(state) = self.module.update() {
+            let now = SystemTime::now();
+            if self.cache_filepath.is_some()
+                && self
+                    .cache_written
+                    .and_then(|t| t.elapsed().ok())
+                    .is_none_or(|e| e >= CACHE_WRITE_INTERVAL)
+            {
+                if let Err(err) = self.write_cache(&state, now) {
+                    log::warn!("Failed to write state cache file: {err:#}");
+                }
+                self.cache_written = Some(now);
+            }
+            self.last_good = Some((state.clone(), now));
+            return StaleHoldState::Fresh(Some(state));
+        }
+        // Age is wall clock time, to account for suspend
+        match &self.last_good {
+            Some((state, time)) => match time.elapsed() {
+                Ok(age) if age < self.limit => StaleHoldState::Stale {
+                    state: state.clone(),
+                    age_s: age.as_secs(),
+                },
+                _ => StaleHoldState::Fresh(None),
+            },
+            None => StaleHoldState::Fresh(None),
+        }
+    }
+
+    fn initial_state(&mut self) -> Option<Self::State> {
+        let (state, time) = self
+            .read_cache()
+            .inspect_err(|err| log::warn!("Failed to read state cache file: {err:#}"))
+            .ok()??;
+        let age = time.elapsed().unwrap_or_default();
+        if age >= self.limit {
+            return None;
+        }
+        self.last_good = Some((state.clone(), time));
+        Some(StaleHoldState::Stale {
+            state,
+            age_s: age.as_secs(),
+        })
+    }
+
+    fn render(&self, state: &Self::State) -> String {
+        match state {
+            StaleHoldState::Fresh(state) => self.module.render(state),
+            StaleHoldState::Stale { state, age_s } => markup::recolor(
+                &format!(
+                    "{} {}",
+                    self.module.render(&Some(state.to_owned())),
+                    format_age(*age_s)
+                ),
+                theme::Color::Unfocused,
+            ),
+        }
+    }
+
+    fn render_detail(&self, state: &Self::State) -> Option<String> {
+        match state {
+            StaleHoldState::Fresh(state) => self.module.render_detail(state),
+            StaleHoldState::Stale { state, age_s } => {
+                let header = format!(
+                    "Update failed, showing state from {} ago",
+                    format_age(*age_s)
+                );
+                Some(match self.module.render_detail(&Some(state.to_owned())) {
+                    Some(detail) => format!("{header}\n{detail}"),
+                    None => header,
+                })
+            }
+        }
+    }
+
+    fn alerts(&self, prev_state: &Self::State, state: &Self::State) -> Vec<alert::Alert> {
+        // Failing updates are not changes
+        self.module.alerts(&prev_state.shown(), &state.shown())
+    }
+
+    fn rerender_interval(&self, state: &Self::State) -> Option<Duration> {
+        match state {
+            StaleHoldState::Fresh(state) => self.module.rerender_interval(state),
+            StaleHoldState::Stale { .. } => None,
+        }
+    }
+
+    fn control_handler(&self) -> Option<control::Handler> {
+        self.module.control_handler()
+    }
+
+    fn reload_config(&mut self, cfg: &config::Config) -> anyhow::Result<bool> {
+        let rebuilt = self.module.reload_config(cfg)?;
+        self.limit = cfg.stale_limit();
+        if rebuilt {
+            // Last good state may not match the new config, replace the cached one as soon as possible
+            self.last_good = None;
+            self.cache_written = None;
+        }
+        Ok(rebuilt)
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::shadow_unrelated)]
+mod tests {
+    use super::*;
+
+    /// Module returning queued states
+    struct TestModule {
+        states: Vec<Option<u32>>,
+    }
+
+    impl RenderablePolybarModule for TestModule {
+        type State = Option<u32>;
+
+        fn wait_update(&mut self, _prev_state: Option<&Self::State>) {}
+
+        fn update(&mut self) -> Self::State {
+            self.states.remove(0)
+        }
+
+        fn render(&self, state: &Self::State) -> String {
+            match state {
+                Some(v) => format!(
+                    "{} {v}",
+                    markup::Markup::new("I")
+                        .fg(theme::Color::MainIcon)
+                        .into_string()
+                ),
+                None => "!".to_owned(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_hold() {
+        let mut module = StaleHoldModule::new(
+            TestModule {
+                states: vec![None, Some(1), None, Some(2)],
+            },
+            Duration::from_hours(1),
+        );
+
+        let state = module.update();
+        assert_eq!(state, StaleHoldState::Fresh(None));
+        assert_eq!(module.render(&state), "!");
+
+        let state = module.update();
+        assert_eq!(state, StaleHoldState::Fresh(Some(1)));
+        assert_eq!(module.render(&state), "%{F:main_icon}I%{F-} 1");
+
+        let state = module.update();
+        assert_eq!(state, StaleHoldState::Stale { state: 1, age_s: 0 });
+
+        let state = module.update();
+        assert_eq!(state, StaleHoldState::Fresh(Some(2)));
+    }
+
+    #[test]
+    fn test_hold_limit() {
+        let mut module = StaleHoldModule::new(
+            TestModule {
+                states: vec![Some(1), None],
+            },
+            Duration::ZERO,
+        );
+        module.update();
+        assert_eq!(module.update(), StaleHoldState::Fresh(None));
+    }
+
+    #[test]
+    fn test_cache() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let cache_filepath = cache_dir.path().join("test.json");
+
+        let mut module = StaleHoldModule::new(
+            TestModule {
+                states: vec![Some(1), Some(2)],
+            },
+            Duration::from_hours(1),
+        );
+        module.cache_filepath = Some(cache_filepath.clone());
+        assert_eq!(module.initial_state(), None);
+        module.update();
+        // Not written again so soon
+        module.update();
+
+        let mut module =
+            StaleHoldModule::new(TestModule { states: vec![None] }, Duration::from_hours(1));
+        module.cache_filepath = Some(cache_filepath.clone());
+        assert_eq!(
+            module.initial_state(),
+            Some(StaleHoldState::Stale { state: 1, age_s: 0 })
+        );
+        assert_eq!(
+            module.update(),
+            StaleHoldState::Stale { state: 1, age_s: 0 }
+        );
+
+        // Too old to show
+        let mut module = StaleHoldModule::new(TestModule { states: vec![None] }, Duration::ZERO);
+        module.cache_filepath = Some(cache_filepath.clone());
+        assert_eq!(module.initial_state(), None);
+        assert_eq!(module.update(), StaleHoldState::Fresh(None));
+
+        fs::write(&cache_filepath, "{").unwrap();
+        assert_eq!(module.initial_state(), None);
+    }
+
+    #[test]
+    fn test_render_stale() {
+        let module = StaleHoldModule::new(TestModule { states: vec![] }, Duration::from_hours(1));
+        let state = StaleHoldState::Stale {
+            state: 1,
+            age_s: 12 * 60 + 5,
+        };
+        assert_eq!(module.render(&state), "%{F:unfocused}I 1 12m%{F-}");
+        assert_eq!(
+            module.render_detail(&state).unwrap(),
+            "Update failed, showing state from 12m ago"
+        );
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(0), "0s");
+        assert_eq!(format_age(59), "59s");
+        assert_eq!(format_age(60), "1m");
+        assert_eq!(format_age(3599), "59m");
+        assert_eq!(format_age(3600), "1h");
+        assert_eq!(format_age(2 * 86400), "2d");
+    }
+}
@@ -1,9 +1,10 @@
 use std::{cmp::max, collections::HashSet, fs, io, path::Path, thread::sleep, time::Duration};
 
 use crate::{
+    icons::Icon,
     markup,
-    polybar_module::{RenderablePolybarModule, TCP_LOCAL_TIMEOUT, syncthing_rest},
-    theme::{self, ICON_WARNING},
+    polybar_module::{RenderablePolybarModule, TCP_LOCAL_TIMEOUT, http, syncthing_rest},
+    theme,
 };
 
 pub(crate) struct SyncthingModule {
@@ -14,14 +15,22 @@ pub(crate) struct SyncthingModule {
     folders_syncing_down: HashSet<String>,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
-#[expect(clippy::struct_field_names)]
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
 pub(crate) struct SyncthingModuleState {
     folder_count: usize,
     device_connected_count: usize,
     device_syncing_to_count: usize,
     folders_syncing_down_count: usize,
     remote_device_count: usize,
+    /// Remote devices, sorted by name
+    devices: Vec<SyncthingDevice>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+struct SyncthingDevice {
+    name: String,
+    connected: bool,
+    syncing_to: bool,
 }
 
 #[derive(serde::Deserialize)]
@@ -56,12 +65,10 @@ impl SyncthingModule {
         let st_config: SyncthingXmlConfig = quick_xml::de::from_str(&st_config_xml)?;
 
         // Build session
+        // Set maximum timeout and override with lower one for non event requests otherwise the timeout only
+        // applies for connect
         let session = ureq::Agent::new_with_config(
-            ureq::Agent::config_builder()
-                // Set maximum timeout and override with lower one for non event requests otherwise the timeout only
-                // applies for connect
-                .timeout_global(Some(max(TCP_LOCAL_TIMEOUT, REST_EVENT_TIMEOUT)))
-                .build(),
+            http::local_agent_config(max(TCP_LOCAL_TIMEOUT, REST_EVENT_TIMEOUT)).build(),
         );
 
         Ok(Self {
@@ -85,7 +92,7 @@ impl SyncthingModule {
         let system_connections: syncthing_rest::SystemConnections =
             self.syncthing_rest_call("system/connections", &[])?;
 
-        let mut device_syncing_to_count = 0;
+        let mut devices_syncing_to = HashSet::new();
         for (device_id, device) in &system_connections.connections {
             if device.connected {
                 let db_completion: syncthing_rest::DbCompletion =
@@ -101,11 +108,26 @@ impl SyncthingModule {
                     || (db_completion.need_items > 0)
                     || (db_completion.need_deletes > 0)
                 {
-                    device_syncing_to_count += 1;
+                    devices_syncing_to.insert(device_id);
                 }
             }
         }
 
+        let mut devices: Vec<_> = system_config
+            .devices
+            .iter()
+            .filter_map(|device| {
+                // Local device has no connection
+                let connection = system_connections.connections.get(&device.device_id)?;
+                Some(SyncthingDevice {
+                    name: device.name.clone(),
+                    connected: connection.connected,
+                    syncing_to: devices_syncing_to.contains(&device.device_id),
+                })
+            })
+            .collect();
+        devices.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+
         Ok(SyncthingModuleState {
             folder_count: system_config.folders.len(),
             device_connected_count: system_connections
@@ -113,9 +135,10 @@ impl SyncthingModule {
                 .values()
                 .filter(|c| c.connected)
                 .count(),
-            device_syncing_to_count,
+            device_syncing_to_count: devices_syncing_to.len(),
             folders_syncing_down_count: self.folders_syncing_down.len(),
             remote_device_count: system_config.devices.len() - 1, // -1 to account for local device
+            devices,
         })
     }
 
@@ -180,12 +203,6 @@ impl SyncthingModule {
     }
 }
 
-const ICON_SYNCTHING: &str = "󱋖";
-const ICON_SYNCTHING_FOLDER: &str = "";
-const ICON_SYNCTHING_DEVICE: &str = "";
-const ICON_SYNCTHING_UPLOADING: &str = "";
-const ICON_SYNCTHING_DOWNLOADING: &str = "";
-
 #[expect(clippy::single_match)]
 impl RenderablePolybarModule for SyncthingModule {
     type State = Option<SyncthingModuleState>;
@@ -231,17 +248,17 @@ impl RenderablePolybarModule for SyncthingModule {
         match state {
             Some(state) => markup::Markup::new(format!(
                 "{} {} {} {} {}/{} {}{} {}{}",
-                markup::Markup::new(ICON_SYNCTHING)
+                markup::Markup::new(Icon::Syncthing)
                     .fg(theme::Color::MainIcon)
                     .into_string(),
-                ICON_SYNCTHING_FOLDER,
+                Icon::SyncthingFolder,
                 state.folder_count,
-                ICON_SYNCTHING_DEVICE,
+                Icon::SyncthingDevice,
                 state.device_connected_count,
                 state.remote_device_count,
-                ICON_SYNCTHING_DOWNLOADING,
+                Icon::SyncthingDownloading,
                 state.folders_syncing_down_count,
-                ICON_SYNCTHING_UPLOADING,
+                Icon::SyncthingUploading,
                 state.device_syncing_to_count
             ))
             .action(
@@ -249,11 +266,31 @@ impl RenderablePolybarModule for SyncthingModule {
                 "firefox --new-tab 'http://127.0.0.1:8384/'",
             )
             .into_string(),
-            None => markup::Markup::new(ICON_WARNING)
+            None => markup::Markup::new(Icon::Warning)
                 .fg(theme::Color::Attention)
                 .into_string(),
         }
     }
+
+    fn render_detail(&self, state: &Self::State) -> Option<String> {
+        let state = state.as_ref()?;
+        let mut lines = vec![format!(
+            "{} folders, {} syncing down",
+            state.folder_count, state.folders_syncing_down_count
+        )];
+        lines.extend(state.devices.iter().map(|device| {
+            format!(
+                "{}: {}",
+                device.name,
+                match (device.connected, device.syncing_to) {
+                    (true, true) => "connected, syncing",
+                    (true, false) => "connected",
+                    (false, _) => "disconnected",
+                }
+            )
+        }));
+        Some(lines.join("\n"))
+    }
 }
 
 #[cfg(test)]
@@ -276,13 +313,53 @@ mod tests {
             device_syncing_to_count: 3,
             folders_syncing_down_count: 4,
             remote_device_count: 5,
+            devices: vec![],
         });
         assert_eq!(
             module.render(&state),
-            "%{A1:firefox --new-tab 'http\\://127.0.0.1\\:8384/':}%{F#f1e9d2}󱋖%{F-}  1  2/5 4 3%{A}"
+            "%{A1:firefox --new-tab 'http\\://127.0.0.1\\:8384/':}%{F:main_icon}󱋖%{F-}  1  2/5 4 3%{A}"
         );
 
         let state = None;
-        assert_eq!(module.render(&state), "%{F#d56500}%{F-}");
+        assert_eq!(module.render(&state), "%{F:attention}%{F-}");
+    }
+
+    #[test]
+    fn test_render_detail() {
+        let mut st_config_file = tempfile::NamedTempFile::new().unwrap();
+        st_config_file.write_all("<configuration><gui><apikey>dummykeydummykeydummykeydummykey</apikey></gui></configuration>".as_bytes()).unwrap();
+
+        let module = SyncthingModule::new(st_config_file.path()).unwrap();
+
+        let state = Some(SyncthingModuleState {
+            folder_count: 3,
+            device_connected_count: 2,
+            device_syncing_to_count: 1,
+            folders_syncing_down_count: 1,
+            remote_device_count: 3,
+            devices: vec![
+                SyncthingDevice {
+                    name: "laptop".to_owned(),
+                    connected: true,
+                    syncing_to: true,
+                },
+                SyncthingDevice {
+                    name: "phone".to_owned(),
+                    connected: false,
+                    syncing_to: false,
+                },
+                SyncthingDevice {
+                    name: "server".to_owned(),
+                    connected: true,
+                    syncing_to: false,
+                },
+            ],
+        });
+        assert_eq!(
+            module.render_detail(&state).unwrap(),
+            "3 folders, 1 syncing down\nlaptop: connected, syncing\nphone: disconnected\nserver: connected"
+        );
+
+        assert_eq!(module.render_detail(&None), None);
     }
 }
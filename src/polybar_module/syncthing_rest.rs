@@ -435,6 +435,8 @@ pub(crate) struct SystemConfigFolder {
 
 #[derive(serde::Deserialize)]
 pub(crate) struct SystemConfigDevice {
+    #[serde(rename = "deviceID")]
+    pub device_id: DeviceID,
     pub name: String,
 }
 
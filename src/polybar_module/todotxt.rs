@@ -1,19 +1,23 @@
 use std::{
     env,
     fs::metadata,
-    path::PathBuf,
-    sync::mpsc::{RecvTimeoutError, channel},
+    path::{Path, PathBuf},
+    sync::mpsc::{Receiver, RecvTimeoutError, channel},
     thread::sleep,
     time::{Duration, SystemTime},
 };
 
 use notify::Watcher as _;
+use serde::Serialize as _;
 use tasks::{Task, TodoFile};
 
 use crate::{
-    markup,
-    polybar_module::{PolybarModuleEnv, RenderablePolybarModule, boottime},
-    theme::{self, ICON_WARNING},
+    config,
+    control::{self, ControlCommand},
+    icons::Icon,
+    layout, markup,
+    polybar_module::{PolybarModuleEnv, RenderablePolybarModule, boottime, toggle_flag_file},
+    theme,
 };
 
 pub(crate) struct TodoTxtModule {
@@ -21,44 +25,124 @@ pub(crate) struct TodoTxtModule {
     todotxt_filepath: PathBuf,
     done_filepath: PathBuf,
     env: PolybarModuleEnv,
+    marquee_cfg: Option<config::MarqueeConfig>,
+    marquee: Option<layout::Marquee>,
+    /// Watch of the Todo.txt and public screen flag file directories, kept across updates
+    watch: Option<(
+        notify::RecommendedWatcher,
+        Receiver<notify::Result<notify::Event>>,
+    )>,
+    /// Last state parsed from the Todo.txt files, keyed by the day it was parsed on and the
+    /// Todo.txt and done.txt mtimes
+    last_state: Option<(
+        (chrono::NaiveDate, SystemTime, SystemTime),
+        TodoTxtModuleState,
+    )>,
 }
 
 #[expect(clippy::large_enum_variant)]
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
 pub(crate) enum TodoTxtModuleState {
     Active {
         pending_count: usize,
+        #[serde(serialize_with = "serialize_task")]
         next_task: Option<Task>,
         last_fs_change: Option<SystemTime>,
     },
     Paused,
 }
 
+/// Serialize the task fields the module renders, [`Task`] does not implement [`serde::Serialize`]
+#[expect(clippy::ref_option)] // signature imposed by serde
+fn serialize_task<S>(task: &Option<Task>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    #[derive(serde::Serialize)]
+    struct RenderedTask<'a> {
+        text: &'a str,
+        priority: Option<char>,
+        due_date: Option<chrono::NaiveDate>,
+    }
+
+    task.as_ref()
+        .map(|task| RenderedTask {
+            text: &task.text,
+            priority: task.priority,
+            due_date: task.due_date(),
+        })
+        .serialize(serializer)
+}
+
 impl TodoTxtModule {
-    pub(crate) fn new(max_len: Option<usize>) -> anyhow::Result<Self> {
+    pub(crate) fn new(
+        max_len: Option<usize>,
+        marquee_cfg: Option<&config::MarqueeConfig>,
+    ) -> anyhow::Result<Self> {
         let todotxt_str = env::var_os("TODO_FILE")
             .ok_or_else(|| anyhow::anyhow!("TODO_FILE environment variable is not set"))?;
         let todotxt_filepath = PathBuf::from(todotxt_str);
         let done_str = env::var_os("DONE_FILE")
             .ok_or_else(|| anyhow::anyhow!("DONE_FILE environment variable is not set"))?;
         let done_filepath = PathBuf::from(done_str);
-        let env = PolybarModuleEnv::new();
 
         Ok(Self {
             max_len,
             todotxt_filepath,
             done_filepath,
-            env,
+            env: PolybarModuleEnv::new(),
+            marquee_cfg: marquee_cfg.cloned(),
+            marquee: marquee_cfg.and_then(layout::Marquee::new),
+            watch: None,
+            last_state: None,
         })
     }
 
+    /// Watch directories of `filepaths`, to also get notified when files are replaced
+    fn watch(
+        filepaths: &[&Path],
+    ) -> (
+        notify::RecommendedWatcher,
+        Receiver<notify::Result<notify::Event>>,
+    ) {
+        let (events_tx, events_rx) = channel();
+        let mut watcher = notify::recommended_watcher(events_tx).unwrap();
+        for filepath in filepaths {
+            let dirpath = filepath
+                .parent()
+                .filter(|p| !p.as_os_str().is_empty())
+                .unwrap_or(Path::new("."));
+            log::debug!("Watching {dirpath:?}");
+            watcher
+                .watch(dirpath, notify::RecursiveMode::NonRecursive)
+                .unwrap();
+        }
+        (watcher, events_rx)
+    }
+
+    /// Width left for the task text, after its pending count
+    fn max_task_len(&self, pending_count: usize) -> Option<usize> {
+        self.max_len
+            .map(|max_len| max_len.saturating_sub(layout::width(&format!("{pending_count} "))))
+    }
+
     fn try_update(&mut self) -> anyhow::Result<TodoTxtModuleState> {
         if self.env.public_screen() {
             Ok(TodoTxtModuleState::Paused)
         } else {
-            let last_fs_change = self.get_todotxt_file_mtime();
-
+            let last_fs_change = Self::file_mtime(&self.todotxt_filepath);
             let today = chrono::Local::now().date_naive();
+            // Marquee scrolling updates often, only parse the files again if they or the day
+            // changed, or if their mtimes can't be read
+            let cache_key = last_fs_change
+                .zip(Self::file_mtime(&self.done_filepath))
+                .map(|(todo_mtime, done_mtime)| (today, todo_mtime, done_mtime));
+            if let Some((last_key, last_state)) = &self.last_state
+                && Some(*last_key) == cache_key
+            {
+                return Ok(last_state.clone());
+            }
+
             let task_file = TodoFile::new(&self.todotxt_filepath, &self.done_filepath)?;
             let tasks = task_file.load_tasks()?;
             let next_task = tasks
@@ -69,24 +153,21 @@ impl TodoTxtModule {
 
             let pending_count = tasks.iter().filter(|t| t.is_ready(today, &tasks)).count();
 
-            Ok(TodoTxtModuleState::Active {
+            let state = TodoTxtModuleState::Active {
                 pending_count,
                 next_task,
                 last_fs_change,
-            })
+            };
+            self.last_state = cache_key.map(|key| (key, state.clone()));
+            Ok(state)
         }
     }
 
-    fn get_todotxt_file_mtime(&self) -> Option<SystemTime> {
-        metadata(&self.todotxt_filepath)
-            .ok()
-            .map(|m| m.modified().unwrap())
+    fn file_mtime(filepath: &Path) -> Option<SystemTime> {
+        metadata(filepath).ok()?.modified().ok()
     }
 }
 
-const ICON_TODOTXT: &str = "";
-const ICON_TODOTXT_HIDDEN: &str = "";
-
 impl RenderablePolybarModule for TodoTxtModule {
     type State = Option<TodoTxtModuleState>;
 
@@ -98,22 +179,15 @@ impl RenderablePolybarModule for TodoTxtModule {
             match prev_state {
                 // Nominal
                 Some(TodoTxtModuleState::Active { last_fs_change, .. }) => {
-                    let (events_tx, events_rx) = channel();
-                    let mut watcher = notify::recommended_watcher(events_tx).unwrap();
-                    let to_watch_filepaths = [
-                        &self.todotxt_filepath,
-                        self.env.public_screen_filepath.parent().unwrap(),
-                    ];
-
-                    log::debug!("Watching {to_watch_filepaths:?}");
-                    for to_watch_filepath in to_watch_filepaths {
-                        watcher
-                            .watch(to_watch_filepath, notify::RecursiveMode::NonRecursive)
-                            .unwrap();
-                    }
-                    let deadline = boottime() + MAX_WAIT;
+                    let deadline = boottime()
+                        + self
+                            .rerender_interval(prev_state)
+                            .map_or(MAX_WAIT, |i| i.min(MAX_WAIT));
+                    let (_watcher, events_rx) = self.watch.get_or_insert_with(|| {
+                        Self::watch(&[&self.todotxt_filepath, &self.env.public_screen_filepath])
+                    });
                     while !self.env.public_screen() && boottime() < deadline {
-                        let max_mtime = self.get_todotxt_file_mtime();
+                        let max_mtime = Self::file_mtime(&self.todotxt_filepath);
                         if max_mtime != *last_fs_change {
                             break;
                         }
@@ -159,14 +233,19 @@ impl RenderablePolybarModule for TodoTxtModule {
             }) => {
                 let s1 = format!(
                     "{} ",
-                    markup::Markup::new(ICON_TODOTXT)
+                    markup::Markup::new(Icon::Todotxt)
                         .fg(theme::Color::MainIcon)
                         .into_string()
                 );
                 let s2 = format!("{pending_count} ");
-                let max_task_len = self.max_len.map(|max_len| max_len - s2.len());
+                let max_task_len = self.max_task_len(*pending_count);
                 let s3 = if let Some(task) = next_task {
-                    theme::ellipsis(&task.text, max_task_len)
+                    match (&self.marquee, max_task_len) {
+                        (Some(marquee), Some(max_task_len)) => {
+                            marquee.window(&task.text, max_task_len)
+                        }
+                        _ => layout::ellipsis(&task.text, max_task_len),
+                    }
                 } else {
                     "😌".to_owned()
                 };
@@ -193,10 +272,7 @@ impl RenderablePolybarModule for TodoTxtModule {
                     }))
                     .action(
                         markup::PolybarActionType::ClickLeft,
-                        format!(
-                            "touch {}",
-                            self.env.public_screen_filepath.to_str().unwrap()
-                        ),
+                        control::action("todotxt", &ControlCommand::toggle),
                     )
                     .into_string(),
                 )
@@ -204,22 +280,54 @@ impl RenderablePolybarModule for TodoTxtModule {
             Some(TodoTxtModuleState::Paused) => {
                 format!(
                     "{} {}",
-                    markup::Markup::new(ICON_TODOTXT)
+                    markup::Markup::new(Icon::Todotxt)
                         .fg(theme::Color::MainIcon)
                         .into_string(),
-                    markup::Markup::new(ICON_TODOTXT_HIDDEN)
+                    markup::Markup::new(Icon::TodotxtHidden)
                         .action(
                             markup::PolybarActionType::ClickLeft,
-                            format!("rm {}", self.env.public_screen_filepath.to_str().unwrap()),
+                            control::action("todotxt", &ControlCommand::toggle),
                         )
                         .into_string(),
                 )
             }
-            None => markup::Markup::new(ICON_WARNING)
+            None => markup::Markup::new(Icon::Warning)
                 .fg(theme::Color::Attention)
                 .into_string(),
         }
     }
+
+    fn rerender_interval(&self, state: &Self::State) -> Option<Duration> {
+        let marquee = self.marquee.as_ref()?;
+        let Some(TodoTxtModuleState::Active {
+            pending_count,
+            next_task: Some(task),
+            ..
+        }) = state
+        else {
+            return None;
+        };
+        let max_task_len = self.max_task_len(*pending_count)?;
+        (layout::width(&task.text) > max_task_len).then(|| marquee.step())
+    }
+
+    fn control_handler(&self) -> Option<control::Handler> {
+        // File watch picks up the change
+        let public_screen_filepath = self.env.public_screen_filepath.clone();
+        Some(Box::new(move |command| match command {
+            ControlCommand::toggle => Ok(toggle_flag_file(&public_screen_filepath)?),
+            _ => Err(control::unsupported(command)),
+        }))
+    }
+
+    fn reload_config(&mut self, cfg: &config::Config) -> anyhow::Result<bool> {
+        if cfg.marquee != self.marquee_cfg {
+            self.marquee_cfg.clone_from(&cfg.marquee);
+            self.marquee = cfg.marquee.as_ref().and_then(layout::Marquee::new);
+        }
+        // Scrolling is not part of the state, so the module is kept
+        Ok(false)
+    }
 }
 
 #[cfg(test)]
@@ -234,9 +342,7 @@ mod tests {
             env::set_var("TODO_FILE", "/dev/null");
             env::set_var("DONE_FILE", "/dev/null");
         }
-        let xdg_dirs = xdg::BaseDirectories::new();
-        let runtime_dir = xdg_dirs.get_runtime_directory().unwrap();
-        let module = TodoTxtModule::new(None).unwrap();
+        let module = TodoTxtModule::new(None, None).unwrap();
 
         let state = Some(TodoTxtModuleState::Active {
             pending_count: 10,
@@ -245,10 +351,7 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            format!(
-                "%{{F#f1e9d2}}%{{F-}} %{{A1:touch {}/public_screen:}}10 😌%{{A}}",
-                runtime_dir.to_str().unwrap()
-            )
+            "%{F:main_icon}%{F-} %{A1:polybar-modules ctl todotxt toggle:}10 😌%{A}"
         );
 
         let state = Some(TodoTxtModuleState::Active {
@@ -262,10 +365,7 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            format!(
-                "%{{F#f1e9d2}}%{{F-}} %{{A1:touch {}/public_screen:}}10 todo%{{A}}",
-                runtime_dir.to_str().unwrap()
-            )
+            "%{F:main_icon}%{F-} %{A1:polybar-modules ctl todotxt toggle:}10 todo%{A}"
         );
 
         let state = Some(TodoTxtModuleState::Active {
@@ -279,10 +379,7 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            format!(
-                "%{{F#f1e9d2}}%{{F-}} %{{A1:touch {}/public_screen:}}10 todo%{{A}}",
-                runtime_dir.to_str().unwrap()
-            )
+            "%{F:main_icon}%{F-} %{A1:polybar-modules ctl todotxt toggle:}10 todo%{A}"
         );
 
         let state = Some(TodoTxtModuleState::Active {
@@ -303,10 +400,7 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            format!(
-                "%{{F#f1e9d2}}%{{F-}} %{{A1:touch {}/public_screen:}}10 %{{u#d56500}}%{{+u}}todo%{{-u}}%{{A}}",
-                runtime_dir.to_str().unwrap()
-            )
+            "%{F:main_icon}%{F-} %{A1:polybar-modules ctl todotxt toggle:}10 %{u:attention}%{+u}todo%{-u}%{A}"
         );
 
         let state = Some(TodoTxtModuleState::Active {
@@ -320,10 +414,7 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            format!(
-                "%{{F#f1e9d2}}%{{F-}} %{{A1:touch {}/public_screen:}}10 %{{u#8faaab}}%{{+u}}todo%{{-u}}%{{A}}",
-                runtime_dir.to_str().unwrap()
-            )
+            "%{F:main_icon}%{F-} %{A1:polybar-modules ctl todotxt toggle:}10 %{u:foreground}%{+u}todo%{-u}%{A}"
         );
 
         let state = Some(TodoTxtModuleState::Active {
@@ -337,13 +428,10 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            format!(
-                "%{{F#f1e9d2}}%{{F-}} %{{A1:touch {}/public_screen:}}10 %{{u#d56500}}%{{+u}}todo%{{-u}}%{{A}}",
-                runtime_dir.to_str().unwrap()
-            )
+            "%{F:main_icon}%{F-} %{A1:polybar-modules ctl todotxt toggle:}10 %{u:attention}%{+u}todo%{-u}%{A}"
         );
 
-        let module = TodoTxtModule::new(Some(7)).unwrap();
+        let module = TodoTxtModule::new(Some(7), None).unwrap();
 
         let state = Some(TodoTxtModuleState::Active {
             pending_count: 10,
@@ -356,10 +444,7 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            format!(
-                "%{{F#f1e9d2}}%{{F-}} %{{A1:touch {}/public_screen:}}10 todo%{{A}}",
-                runtime_dir.to_str().unwrap()
-            )
+            "%{F:main_icon}%{F-} %{A1:polybar-modules ctl todotxt toggle:}10 todo%{A}"
         );
 
         let state = Some(TodoTxtModuleState::Active {
@@ -373,10 +458,7 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            format!(
-                "%{{F#f1e9d2}}%{{F-}} %{{A1:touch {}/public_screen:}}101 to…%{{A}}",
-                runtime_dir.to_str().unwrap()
-            )
+            "%{F:main_icon}%{F-} %{A1:polybar-modules ctl todotxt toggle:}101 to…%{A}"
         );
 
         let state = Some(TodoTxtModuleState::Active {
@@ -390,10 +472,7 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            format!(
-                "%{{F#f1e9d2}}%{{F-}} %{{A1:touch {}/public_screen:}}1011 t…%{{A}}",
-                runtime_dir.to_str().unwrap()
-            )
+            "%{F:main_icon}%{F-} %{A1:polybar-modules ctl todotxt toggle:}1011 t…%{A}"
         );
 
         let state = Some(TodoTxtModuleState::Active {
@@ -407,19 +486,88 @@ mod tests {
         });
         assert_eq!(
             module.render(&state),
-            format!(
-                "%{{F#f1e9d2}}%{{F-}} %{{A1:touch {}/public_screen:}}10 tod…%{{A}}",
-                runtime_dir.to_str().unwrap()
-            )
+            "%{F:main_icon}%{F-} %{A1:polybar-modules ctl todotxt toggle:}10 tod…%{A}"
         );
 
         let state = Some(TodoTxtModuleState::Paused);
         assert_eq!(
             module.render(&state),
-            format!(
-                "%{{F#f1e9d2}}%{{F-}} %{{A1:rm {}/public_screen:}}%{{A}}",
-                runtime_dir.to_str().unwrap()
-            )
+            "%{F:main_icon}%{F-} %{A1:polybar-modules ctl todotxt toggle:}%{A}"
+        );
+    }
+
+    #[test]
+    fn test_serialize() {
+        let state = TodoTxtModuleState::Active {
+            pending_count: 2,
+            next_task: Some(Task {
+                priority: Some('A'),
+                text: "todo".to_owned(),
+                attributes: vec![("due".to_owned(), "2025-01-31".to_owned())],
+                ..Task::default()
+            }),
+            last_fs_change: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&state).unwrap(),
+            r#"{"Active":{"pending_count":2,"next_task":{"text":"todo","priority":"A","due_date":"2025-01-31"},"last_fs_change":null}}"#
         );
+
+        let state = TodoTxtModuleState::Paused;
+        assert_eq!(serde_json::to_string(&state).unwrap(), r#""Paused""#);
+    }
+
+    #[test]
+    fn test_update_unchanged() {
+        // SAFETY: actually NOT safe, but this is for tests only, and we are feeling lucky
+        unsafe {
+            env::set_var("TODO_FILE", "/dev/null");
+            env::set_var("DONE_FILE", "/dev/null");
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let mut module = TodoTxtModule::new(None, None).unwrap();
+        module.todotxt_filepath = dir.path().join("todo.txt");
+        module.done_filepath = dir.path().join("done.txt");
+        module.env.public_screen_filepath = dir.path().join("public_screen");
+        std::fs::write(&module.todotxt_filepath, "").unwrap();
+        std::fs::write(&module.done_filepath, "").unwrap();
+
+        let Some(TodoTxtModuleState::Active { pending_count, .. }) = module.update() else {
+            panic!();
+        };
+        assert_eq!(pending_count, 0);
+
+        for (i, filepath) in [
+            module.todotxt_filepath.clone(),
+            module.done_filepath.clone(),
+        ]
+        .iter()
+        .enumerate()
+        {
+            // Files are not parsed again while their mtimes are unchanged
+            if let Some((_key, TodoTxtModuleState::Active { pending_count, .. })) =
+                &mut module.last_state
+            {
+                *pending_count = 42;
+            }
+            assert!(matches!(
+                module.update(),
+                Some(TodoTxtModuleState::Active {
+                    pending_count: 42,
+                    ..
+                })
+            ));
+
+            let file = std::fs::File::options().write(true).open(filepath).unwrap();
+            file.set_modified(SystemTime::now() + Duration::from_secs(i as u64 + 1))
+                .unwrap();
+            assert!(matches!(
+                module.update(),
+                Some(TodoTxtModuleState::Active {
+                    pending_count: 0,
+                    ..
+                })
+            ));
+        }
     }
 }
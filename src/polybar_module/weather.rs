@@ -1,29 +1,32 @@
 use std::time::Duration;
 
+use anyhow::Context as _;
 use backon::BackoffBuilder as _;
 
 use crate::{
-    config::WeatherModuleConfig,
+    config::{self, WeatherModuleConfig},
+    icons::Icon,
     markup,
     polybar_module::{
         NETWORK_ERROR_BACKOFF, NetworkMode, PolybarModuleEnv, RenderablePolybarModule,
-        TCP_REMOTE_TIMEOUT, sleep_suspend_aware, wait_network_ready,
+        http::HttpClient, sleep_suspend_aware, wait_network_ready,
     },
-    theme::{self, ICON_WARNING},
+    theme,
 };
 
-const WIND_STRONG_KMH: f64 = 40.0;
-const WIND_GUST_STRONG_KMH: f64 = 60.0;
+/// Minimum delay between Open-Meteo requests, whatever the update interval
+const HTTP_MIN_INTERVAL: Duration = Duration::from_mins(1);
 
 pub(crate) struct WeatherModule {
-    client: ureq::Agent,
+    cfg: WeatherModuleConfig,
+    client: HttpClient,
     url: String,
     env: PolybarModuleEnv,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
 pub(crate) struct WeatherModuleState {
-    icon: &'static str,
+    icon: Icon,
     temp: i16,
 }
 
@@ -42,35 +45,29 @@ struct OpenMeteoCurrent {
 }
 
 #[expect(clippy::match_same_arms)]
-fn weather_icon(
-    code: u8,
-    is_day: bool,
-    wind_speed: f64,
-    wind_gusts: f64,
-) -> anyhow::Result<&'static str> {
-    let windy = wind_speed >= WIND_STRONG_KMH || wind_gusts >= WIND_GUST_STRONG_KMH;
+fn weather_icon(code: u8, is_day: bool, windy: bool) -> anyhow::Result<Icon> {
     Ok(match (code, is_day, windy) {
-        (0 | 1, _, true) => "󰖝",      // nf-md-weather_windy
-        (0 | 1, true, false) => "󰖙",  // nf-md-weather_sunny
-        (0 | 1, false, false) => "󰖔", // nf-md-weather_night
-        (2, true, _) => "󰖕",          // nf-md-weather_partly_cloudy
-        (2, false, _) => "󰼱",         // nf-md-weather_night_partly_cloudy
-        (3, ..) => "󰖐",               // nf-md-weather_cloudy
-        (45 | 48, ..) => "󰖑",         // nf-md-weather_fog
-        (51 | 53 | 55, ..) => "󰖗",    // nf-md-weather_rainy
-        (56 | 57, ..) => "󰙿",         // nf-md-weather_snowy_rainy
-        (61 | 63, ..) => "󰖗",         // nf-md-weather_rainy
-        (65, ..) => "󰖖",              // nf-md-weather_pouring
-        (66 | 67, ..) => "󰙿",         // nf-md-weather_snowy_rainy
-        (71 | 73, ..) => "󰖘",         // nf-md-weather_snowy
-        (75, ..) => "󰼶",              // nf-md-weather_snowy_heavy
-        (77, ..) => "󰖘",              // nf-md-weather_snowy
-        (80 | 81, ..) => "󰖗",         // nf-md-weather_rainy
-        (82, ..) => "󰖖",              // nf-md-weather_pouring
-        (85, ..) => "󰖘",              // nf-md-weather_snowy
-        (86, ..) => "󰼶",              // nf-md-weather_snowy_heavy
-        (95, ..) => "󰙾",              // nf-md-weather_lightning_rainy
-        (96 | 99, ..) => "󰖒",         // nf-md-weather_hail
+        (0 | 1, _, true) => Icon::WeatherWindy,
+        (0 | 1, true, false) => Icon::WeatherSunny,
+        (0 | 1, false, false) => Icon::WeatherNight,
+        (2, true, _) => Icon::WeatherPartlyCloudy,
+        (2, false, _) => Icon::WeatherNightPartlyCloudy,
+        (3, ..) => Icon::WeatherCloudy,
+        (45 | 48, ..) => Icon::WeatherFog,
+        (51 | 53 | 55, ..) => Icon::WeatherRainy,
+        (56 | 57, ..) => Icon::WeatherSnowyRainy,
+        (61 | 63, ..) => Icon::WeatherRainy,
+        (65, ..) => Icon::WeatherPouring,
+        (66 | 67, ..) => Icon::WeatherSnowyRainy,
+        (71 | 73, ..) => Icon::WeatherSnowy,
+        (75, ..) => Icon::WeatherSnowyHeavy,
+        (77, ..) => Icon::WeatherSnowy,
+        (80 | 81, ..) => Icon::WeatherRainy,
+        (82, ..) => Icon::WeatherPouring,
+        (85, ..) => Icon::WeatherSnowy,
+        (86, ..) => Icon::WeatherSnowyHeavy,
+        (95, ..) => Icon::WeatherLightningRainy,
+        (96 | 99, ..) => Icon::WeatherHail,
         _ => anyhow::bail!("Unknown WMO weather code: {code}"),
     })
 }
@@ -78,41 +75,35 @@ fn weather_icon(
 impl WeatherModule {
     pub(crate) fn new(cfg: &WeatherModuleConfig) -> Self {
         let env = PolybarModuleEnv::new();
-        let client = ureq::Agent::new_with_config(
-            ureq::Agent::config_builder()
-                .tls_config(
-                    ureq::tls::TlsConfig::builder()
-                        .provider(ureq::tls::TlsProvider::NativeTls)
-                        .build(),
-                )
-                .timeout_global(Some(TCP_REMOTE_TIMEOUT))
-                .build(),
-        );
+        let client = HttpClient::new(HTTP_MIN_INTERVAL);
         let url = format!(
             "https://api.open-meteo.com/v1/forecast?latitude={}&longitude={}&current=temperature_2m,weather_code,is_day,wind_speed_10m,wind_gusts_10m",
             cfg.latitude, cfg.longitude,
         );
-        Self { client, url, env }
+        Self {
+            cfg: cfg.to_owned(),
+            client,
+            url,
+            env,
+        }
     }
 
     fn try_update(&mut self) -> anyhow::Result<WeatherModuleState> {
-        let response = self.client.get(&self.url).call()?;
-        anyhow::ensure!(
-            response.status().is_success(),
-            "HTTP response {}",
-            response.status(),
-        );
-        let body: OpenMeteoResponse = response.into_body().read_json()?;
-        Self::build_state(body)
+        let body = self.client.get(&self.url, &self.env.network_mode())?;
+        let response: OpenMeteoResponse = serde_json::from_str(&body)?;
+        self.build_state(response)
+    }
+
+    fn is_windy(&self, wind_speed: f64, wind_gusts: f64) -> bool {
+        wind_speed >= self.cfg.wind_strong_kmh || wind_gusts >= self.cfg.wind_gust_strong_kmh
     }
 
-    fn build_state(response: OpenMeteoResponse) -> anyhow::Result<WeatherModuleState> {
+    fn build_state(&self, response: OpenMeteoResponse) -> anyhow::Result<WeatherModuleState> {
         let current = response.current;
         let icon = weather_icon(
             current.weather_code,
             current.is_day != 0,
-            current.wind_speed_10m,
-            current.wind_gusts_10m,
+            self.is_windy(current.wind_speed_10m, current.wind_gusts_10m),
         )?;
         #[expect(clippy::cast_possible_truncation)]
         let temp = current.temperature_2m.round() as i16;
@@ -129,7 +120,7 @@ impl RenderablePolybarModule for WeatherModule {
                 // Nominal
                 Some(_) => {
                     self.env.network_error_backoff = NETWORK_ERROR_BACKOFF.build();
-                    Duration::from_mins(5)
+                    Duration::from_secs(self.cfg.update_interval_s.get())
                 }
                 // Error occured
                 None => self.env.network_error_backoff.next().unwrap(),
@@ -162,11 +153,24 @@ impl RenderablePolybarModule for WeatherModule {
                     state.temp
                 )
             }
-            None => markup::Markup::new(ICON_WARNING)
+            None => markup::Markup::new(Icon::Warning)
                 .fg(theme::Color::Attention)
                 .into_string(),
         }
     }
+
+    fn reload_config(&mut self, cfg: &config::Config) -> anyhow::Result<bool> {
+        let cfg = cfg
+            .module
+            .as_ref()
+            .and_then(|m| m.weather.as_ref())
+            .context("Missing 'module.weather' config section")?;
+        if *cfg == self.cfg {
+            return Ok(false);
+        }
+        *self = Self::new(cfg);
+        Ok(true)
+    }
 }
 
 #[cfg(test)]
@@ -175,20 +179,18 @@ mod tests {
     use super::*;
 
     fn test_cfg() -> WeatherModuleConfig {
-        WeatherModuleConfig {
-            latitude: 48.8566,
-            longitude: 2.3522,
-        }
+        toml::from_str("latitude = 48.8566\nlongitude = 2.3522").unwrap()
     }
 
     #[test]
+    #[expect(clippy::float_cmp)] // values parsed from the same literals
     fn test_config_deserialization() {
         let toml = "
 [module.weather]
 latitude = 48.8566
 longitude = 2.3522
 ";
-        let config: crate::config::Config = toml::from_str(toml).unwrap();
+        let config: config::Config = toml::from_str(toml).unwrap();
         let weather_cfg = config.module.unwrap().weather.unwrap();
         assert_eq!(weather_cfg.latitude, 48.8566);
         assert_eq!(weather_cfg.longitude, 2.3522);
@@ -210,11 +212,12 @@ longitude = 2.3522
             }
         }"#;
         let response: OpenMeteoResponse = serde_json::from_str(json).unwrap();
-        let state = WeatherModule::build_state(response).unwrap();
+        let module = WeatherModule::new(&test_cfg());
+        let state = module.build_state(response).unwrap();
         assert_eq!(
             state,
             WeatherModuleState {
-                icon: "󰖐",
+                icon: Icon::WeatherCloudy,
                 temp: 15,
             }
         );
@@ -222,6 +225,8 @@ longitude = 2.3522
 
     #[test]
     fn test_parse_temperature_rounding() {
+        let module = WeatherModule::new(&test_cfg());
+
         let json = r#"{
             "current": {
                 "temperature_2m": 14.4,
@@ -232,7 +237,7 @@ longitude = 2.3522
             }
         }"#;
         let response: OpenMeteoResponse = serde_json::from_str(json).unwrap();
-        assert_eq!(WeatherModule::build_state(response).unwrap().temp, 14);
+        assert_eq!(module.build_state(response).unwrap().temp, 14);
 
         let json = r#"{
             "current": {
@@ -244,64 +249,87 @@ longitude = 2.3522
             }
         }"#;
         let response: OpenMeteoResponse = serde_json::from_str(json).unwrap();
-        assert_eq!(WeatherModule::build_state(response).unwrap().temp, -4);
+        assert_eq!(module.build_state(response).unwrap().temp, -4);
     }
 
     #[test]
     fn test_weather_icon_day_night() {
-        assert_eq!(weather_icon(0, true, 0.0, 0.0).unwrap(), "󰖙");
-        assert_eq!(weather_icon(0, false, 0.0, 0.0).unwrap(), "󰖔");
-        assert_eq!(weather_icon(1, true, 0.0, 0.0).unwrap(), "󰖙");
-        assert_eq!(weather_icon(1, false, 0.0, 0.0).unwrap(), "󰖔");
-        assert_eq!(weather_icon(2, true, 0.0, 0.0).unwrap(), "󰖕");
-        assert_eq!(weather_icon(2, false, 0.0, 0.0).unwrap(), "󰼱");
+        assert_eq!(weather_icon(0, true, false).unwrap(), Icon::WeatherSunny);
+        assert_eq!(weather_icon(0, false, false).unwrap(), Icon::WeatherNight);
+        assert_eq!(weather_icon(1, true, false).unwrap(), Icon::WeatherSunny);
+        assert_eq!(weather_icon(1, false, false).unwrap(), Icon::WeatherNight);
+        assert_eq!(
+            weather_icon(2, true, false).unwrap(),
+            Icon::WeatherPartlyCloudy
+        );
+        assert_eq!(
+            weather_icon(2, false, false).unwrap(),
+            Icon::WeatherNightPartlyCloudy
+        );
     }
 
     #[test]
     fn test_weather_icon_wind_override() {
-        assert_eq!(weather_icon(0, true, 50.0, 0.0).unwrap(), "󰖝");
-        assert_eq!(weather_icon(0, false, 0.0, 70.0).unwrap(), "󰖝");
-        assert_eq!(weather_icon(0, true, 39.0, 59.0).unwrap(), "󰖙");
-        assert_eq!(weather_icon(3, true, 100.0, 100.0).unwrap(), "󰖐");
+        assert_eq!(weather_icon(0, true, true).unwrap(), Icon::WeatherWindy);
+        assert_eq!(weather_icon(0, false, true).unwrap(), Icon::WeatherWindy);
+        assert_eq!(weather_icon(3, true, true).unwrap(), Icon::WeatherCloudy);
+    }
+
+    #[test]
+    fn test_is_windy() {
+        let module = WeatherModule::new(&test_cfg());
+        assert!(module.is_windy(50.0, 0.0));
+        assert!(module.is_windy(0.0, 70.0));
+        assert!(!module.is_windy(39.0, 59.0));
+
+        let module = WeatherModule::new(
+            &toml::from_str(
+                "latitude = 48.8566\nlongitude = 2.3522\nwind_strong_kmh = 30.0\nwind_gust_strong_kmh = 50.0",
+            )
+            .unwrap(),
+        );
+        assert!(module.is_windy(35.0, 0.0));
+        assert!(module.is_windy(0.0, 55.0));
+        assert!(!module.is_windy(29.0, 49.0));
     }
 
     #[test]
     fn test_weather_icon_all_codes() {
         let codes = [
-            (3_u8, "󰖐"),
-            (45, "󰖑"),
-            (48, "󰖑"),
-            (51, "󰖗"),
-            (53, "󰖗"),
-            (55, "󰖗"),
-            (56, "󰙿"),
-            (57, "󰙿"),
-            (61, "󰖗"),
-            (63, "󰖗"),
-            (65, "󰖖"),
-            (66, "󰙿"),
-            (67, "󰙿"),
-            (71, "󰖘"),
-            (73, "󰖘"),
-            (75, "󰼶"),
-            (77, "󰖘"),
-            (80, "󰖗"),
-            (81, "󰖗"),
-            (82, "󰖖"),
-            (85, "󰖘"),
-            (86, "󰼶"),
-            (95, "󰙾"),
-            (96, "󰖒"),
-            (99, "󰖒"),
+            (3_u8, Icon::WeatherCloudy),
+            (45, Icon::WeatherFog),
+            (48, Icon::WeatherFog),
+            (51, Icon::WeatherRainy),
+            (53, Icon::WeatherRainy),
+            (55, Icon::WeatherRainy),
+            (56, Icon::WeatherSnowyRainy),
+            (57, Icon::WeatherSnowyRainy),
+            (61, Icon::WeatherRainy),
+            (63, Icon::WeatherRainy),
+            (65, Icon::WeatherPouring),
+            (66, Icon::WeatherSnowyRainy),
+            (67, Icon::WeatherSnowyRainy),
+            (71, Icon::WeatherSnowy),
+            (73, Icon::WeatherSnowy),
+            (75, Icon::WeatherSnowyHeavy),
+            (77, Icon::WeatherSnowy),
+            (80, Icon::WeatherRainy),
+            (81, Icon::WeatherRainy),
+            (82, Icon::WeatherPouring),
+            (85, Icon::WeatherSnowy),
+            (86, Icon::WeatherSnowyHeavy),
+            (95, Icon::WeatherLightningRainy),
+            (96, Icon::WeatherHail),
+            (99, Icon::WeatherHail),
         ];
         for (code, expected) in codes {
-            assert_eq!(weather_icon(code, true, 0.0, 0.0).unwrap(), expected);
+            assert_eq!(weather_icon(code, true, false).unwrap(), expected);
         }
     }
 
     #[test]
     fn test_weather_icon_unknown_code() {
-        assert!(weather_icon(123, true, 0.0, 0.0).is_err());
+        assert!(weather_icon(123, true, false).is_err());
     }
 
     #[test]
@@ -309,12 +337,12 @@ longitude = 2.3522
         let module = WeatherModule::new(&test_cfg());
 
         let state = Some(WeatherModuleState {
-            icon: "󰖙",
+            icon: Icon::WeatherSunny,
             temp: 15,
         });
-        assert_eq!(module.render(&state), "%{F#f1e9d2}󰖙%{F-} 15°C");
+        assert_eq!(module.render(&state), "%{F:main_icon}󰖙%{F-} 15°C");
 
         let state = None;
-        assert_eq!(module.render(&state), "%{F#d56500}%{F-}");
+        assert_eq!(module.render(&state), "%{F:attention}%{F-}");
     }
 }
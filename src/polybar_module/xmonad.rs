@@ -3,24 +3,22 @@ use std::{
     io::{ErrorKind, Read as _},
     mem,
     os::unix::io::AsRawFd as _,
+    path::PathBuf,
     thread::sleep,
     time::Duration,
 };
 
-use crate::{
-    markup,
-    polybar_module::RenderablePolybarModule,
-    theme::{self, ICON_WARNING},
-};
+use crate::{icons::Icon, markup, polybar_module::RenderablePolybarModule, theme};
 
 pub(crate) struct XmonadModule {
-    xdg_dirs: xdg::BaseDirectories,
+    /// Status pipe, in the runtime directory
+    pipe_filepath: Option<PathBuf>,
     pipe: Option<File>,
     poller: mio::Poll,
     pending_data: String,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, serde::Serialize)]
 pub(crate) struct XmonadModuleState {
     layout: String,
 }
@@ -29,7 +27,10 @@ impl XmonadModule {
     pub(crate) fn new() -> anyhow::Result<Self> {
         let xdg_dirs = xdg::BaseDirectories::new();
         Ok(Self {
-            xdg_dirs,
+            pipe_filepath: xdg_dirs
+                .get_runtime_directory()
+                .ok()
+                .map(|d| d.join("xmonad/status.pipe")),
             pipe: None,
             poller: mio::Poll::new()?,
             pending_data: String::new(),
@@ -40,8 +41,9 @@ impl XmonadModule {
         self.pipe = None;
 
         let path = self
-            .xdg_dirs
-            .find_runtime_file("xmonad/status.pipe")
+            .pipe_filepath
+            .as_ref()
+            .filter(|p| p.exists())
             .ok_or_else(|| anyhow::anyhow!("No status pipe"))?;
         let pipe = File::open(path)?;
 
@@ -55,23 +57,9 @@ impl XmonadModule {
         self.pipe = Some(pipe);
         Ok(())
     }
-}
-
-impl RenderablePolybarModule for XmonadModule {
-    type State = Option<XmonadModuleState>;
-
-    fn wait_update(&mut self, prev_state: Option<&Self::State>) {
-        let prev_state_err = prev_state.as_ref().is_some_and(|o| o.is_none());
-        if self.pipe.is_none() || prev_state_err {
-            if prev_state_err {
-                sleep(Duration::from_secs(1));
-            }
-            if let Err(e) = self.open_pipe() {
-                log::debug!("{e:?}");
-                return;
-            }
-        }
 
+    /// Block until the pipe has data, and read it
+    fn read_pipe(&mut self) {
         let mut poller_events = mio::Events::with_capacity(1);
         log::trace!("Waiting for pipe data");
         loop {
@@ -96,16 +84,39 @@ impl RenderablePolybarModule for XmonadModule {
             }
         }
     }
+}
+
+impl RenderablePolybarModule for XmonadModule {
+    type State = Option<XmonadModuleState>;
+
+    fn wait_update(&mut self, prev_state: Option<&Self::State>) {
+        let prev_state_err = prev_state.as_ref().is_some_and(|o| o.is_none());
+        if self.pipe.is_none() || prev_state_err {
+            if prev_state_err {
+                sleep(Duration::from_secs(1));
+            }
+            if let Err(e) = self.open_pipe() {
+                log::debug!("{e:?}");
+                return;
+            }
+        }
+        self.read_pipe();
+    }
 
     fn update(&mut self) -> Self::State {
-        if self.pipe.is_none() {
-            None
-        } else {
-            assert_ne!(self.pending_data, "");
-            Some(XmonadModuleState {
-                layout: mem::take(&mut self.pending_data),
-            })
+        // Not waited for with --once
+        if self.pipe.is_none()
+            && let Err(e) = self.open_pipe()
+        {
+            log::debug!("{e:?}");
+            return None;
+        }
+        if self.pending_data.is_empty() {
+            self.read_pipe();
         }
+        Some(XmonadModuleState {
+            layout: mem::take(&mut self.pending_data),
+        })
     }
 
     fn render(&self, state: &Self::State) -> String {
@@ -121,7 +132,7 @@ impl RenderablePolybarModule for XmonadModule {
                 .collect::<Vec<String>>()
                 .join(" ")
         } else {
-            markup::Markup::new(ICON_WARNING)
+            markup::Markup::new(Icon::Warning)
                 .fg(theme::Color::Attention)
                 .into_string()
         }
@@ -131,6 +142,8 @@ impl RenderablePolybarModule for XmonadModule {
 #[cfg(test)]
 #[expect(clippy::shadow_unrelated)]
 mod tests {
+    use std::{fs, thread};
+
     use super::*;
 
     #[test]
@@ -148,6 +161,27 @@ mod tests {
         assert_eq!(module.render(&state), "Tabb Simp");
 
         let state = None;
-        assert_eq!(module.render(&state), "%{F#d56500}%{F-}");
+        assert_eq!(module.render(&state), "%{F:attention}%{F-}");
+    }
+
+    #[test]
+    fn test_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let pipe_filepath = dir.path().join("status.pipe");
+        crate::daemon::create_fifo(&pipe_filepath).unwrap();
+        let writer = thread::spawn({
+            let pipe_filepath = pipe_filepath.clone();
+            move || fs::write(pipe_filepath, "Spacing Tall").unwrap()
+        });
+        let mut module = XmonadModule::new().unwrap();
+        module.pipe_filepath = Some(pipe_filepath);
+
+        let mut outputs = vec![];
+        crate::render_loop(module, &crate::RunOpts::once(), |output, _detail| {
+            outputs.push(output);
+        })
+        .unwrap();
+        writer.join().unwrap();
+        assert_eq!(outputs, vec!["Spac Tall".to_owned()]);
     }
 }
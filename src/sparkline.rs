@@ -0,0 +1,101 @@
+//! Trend of the last values of a series, as a line of vertical bars
+
+use itertools::Itertools as _;
+
+use crate::{markup, theme};
+
+/// Sample to render, as its fraction of the maximum and its color
+pub(crate) type Sample = (f64, Option<theme::Color>);
+
+/// Braille dots of the left and right columns of a character, from bottom to top
+const BRAILLE_DOTS: [[u32; 4]; 2] = [[0x40, 0x04, 0x02, 0x01], [0x80, 0x20, 0x10, 0x08]];
+
+/// First Braille character, with no dots
+const BRAILLE_BASE: u32 = 0x2800;
+
+/// Render `samples`, oldest first, as one vertical bar each, or in `braille` mode as two per
+/// character, colored like the highest one
+pub(crate) fn render(samples: &[Sample], braille: bool) -> String {
+    let glyphs: Vec<(String, Option<theme::Color>)> = if braille {
+        // Keep the newest sample in the right column of the last character
+        let padding = [None].into_iter().take(samples.len() % 2);
+        padding
+            .chain(samples.iter().map(Some))
+            .tuples()
+            .map(|(left, right)| {
+                let mut c = BRAILLE_BASE;
+                for (dots, sample) in BRAILLE_DOTS.iter().zip([left, right]) {
+                    if let Some((frac, _color)) = sample {
+                        let level = markup::ramp_level(*frac, dots.len());
+                        c |= dots.iter().take(level).fold(0, |acc, d| acc | d);
+                    }
+                }
+                let color = [left, right]
+                    .into_iter()
+                    .flatten()
+                    .max_by(|a, b| a.0.total_cmp(&b.0))
+                    .and_then(|(_frac, color)| *color);
+                (
+                    char::from_u32(c).map(String::from).unwrap_or_default(),
+                    color,
+                )
+            })
+            .collect()
+    } else {
+        samples
+            .iter()
+            .map(|(frac, color)| (markup::ramp_icon(*frac).to_owned(), *color))
+            .collect()
+    };
+    // Merge markup of consecutive characters of the same color
+    glyphs
+        .into_iter()
+        .chunk_by(|(_glyph, color)| *color)
+        .into_iter()
+        .map(|(color, chunk)| {
+            let s: String = chunk.map(|(glyph, _color)| glyph).collect();
+            match color {
+                Some(color) => markup::Markup::new(s).fg(color).into_string(),
+                None => s,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        assert_eq!(render(&[], false), "");
+        assert_eq!(
+            render(
+                &[
+                    (0.0, Some(theme::Color::Good)),
+                    (0.5, Some(theme::Color::Good)),
+                    (1.0, Some(theme::Color::Attention)),
+                    (0.3, None),
+                ],
+                false
+            ),
+            "%{F:good}▁▄%{F-}%{F:attention}█%{F-}▃"
+        );
+    }
+
+    #[test]
+    fn test_render_braille() {
+        assert_eq!(render(&[(0.0, None), (1.0, None)], true), "⣸");
+        assert_eq!(
+            render(
+                &[
+                    (1.0, Some(theme::Color::Good)),
+                    (0.5, Some(theme::Color::Good)),
+                    (0.75, Some(theme::Color::Notice)),
+                ],
+                true
+            ),
+            "%{F:good}⢸%{F-}%{F:notice}⣴%{F-}"
+        );
+    }
+}
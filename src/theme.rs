@@ -1,58 +1,236 @@
-#[expect(dead_code, clippy::unreadable_literal)]
-#[derive(Clone, Copy)]
+use std::{
+    collections::HashMap,
+    process::{Command, Stdio},
+    sync::RwLock,
+};
+
+use anyhow::Context as _;
+
+use crate::config::{ThemeConfig, ThemePreset};
+
+/// Semantic colors, their RGB values come from the active [`Palette`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub(crate) enum Color {
-    // // Solarized Dark
-    // Foreground = 0x93a1a1,
-    // MainIcon = 0xeee8d5,
-    // Focused = 0x2aa198,
-    // Unfocused = 0x657b83,
-    // Good = 0x859900,
-    // Notice = 0xb58900,
-    // Attention = 0xcb4b16,
-    // Critical = 0xdc322f,
-    // OKSolar
-    Foreground = 0x8faaab,
-    MainIcon = 0xf1e9d2,
-    Focused = 0x259d94,
-    Unfocused = 0x657377,
-    Good = 0x819500,
-    Notice = 0xac8300,
-    Attention = 0xd56500,
-    Critical = 0xf23749,
+    Foreground,
+    MainIcon,
+    Focused,
+    Unfocused,
+    Good,
+    Notice,
+    Attention,
+    Critical,
 }
 
-pub(crate) const ICON_WARNING: &str = "";
-
-pub(crate) fn ellipsis(s: &str, max_len: Option<usize>) -> String {
-    match max_len {
-        Some(max_len) => {
-            if s.len() > max_len {
-                let mut s2: String = s.trim_end().to_owned();
-                if s2.len() > max_len {
-                    s2.chars()
-                        .take(max_len - 1)
-                        .collect::<String>()
-                        .trim_end()
-                        .clone_into(&mut s2);
-                    s2.push('…');
-                }
-                s2
-            } else {
-                s.to_owned()
-            }
+impl Color {
+    const ALL: [Self; 8] = [
+        Self::Foreground,
+        Self::MainIcon,
+        Self::Focused,
+        Self::Unfocused,
+        Self::Good,
+        Self::Notice,
+        Self::Attention,
+        Self::Critical,
+    ];
+
+    /// RGB value in the active palette
+    pub(crate) fn rgb(self) -> u32 {
+        PALETTE.read().unwrap().get(self)
+    }
+
+    /// Name of the color, as in the theme config
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Foreground => "foreground",
+            Self::MainIcon => "main_icon",
+            Self::Focused => "focused",
+            Self::Unfocused => "unfocused",
+            Self::Good => "good",
+            Self::Notice => "notice",
+            Self::Attention => "attention",
+            Self::Critical => "critical",
         }
-        None => s.to_owned(),
+    }
+
+    /// Color named `name`, as in the theme config
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.name() == name)
     }
 }
 
-pub(crate) fn pad(s: &str, min_len: Option<usize>) -> String {
-    match min_len {
-        Some(min_len) if min_len > s.len() => {
-            let pad_count = min_len - s.len();
-            format!("{}{}", " ".repeat(pad_count), s)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Palette {
+    foreground: u32,
+    main_icon: u32,
+    focused: u32,
+    unfocused: u32,
+    good: u32,
+    notice: u32,
+    attention: u32,
+    critical: u32,
+}
+
+#[expect(clippy::unreadable_literal)]
+impl Palette {
+    const SOLARIZED_DARK: Self = Self {
+        foreground: 0x93a1a1,
+        main_icon: 0xeee8d5,
+        focused: 0x2aa198,
+        unfocused: 0x657b83,
+        good: 0x859900,
+        notice: 0xb58900,
+        attention: 0xcb4b16,
+        critical: 0xdc322f,
+    };
+
+    const OKSOLAR: Self = Self {
+        foreground: 0x8faaab,
+        main_icon: 0xf1e9d2,
+        focused: 0x259d94,
+        unfocused: 0x657377,
+        good: 0x819500,
+        notice: 0xac8300,
+        attention: 0xd56500,
+        critical: 0xf23749,
+    };
+
+    const GRUVBOX: Self = Self {
+        foreground: 0xebdbb2,
+        main_icon: 0xfbf1c7,
+        focused: 0x8ec07c,
+        unfocused: 0x928374,
+        good: 0xb8bb26,
+        notice: 0xfabd2f,
+        attention: 0xfe8019,
+        critical: 0xfb4934,
+    };
+
+    const NORD: Self = Self {
+        foreground: 0xd8dee9,
+        main_icon: 0xeceff4,
+        focused: 0x88c0d0,
+        unfocused: 0x4c566a,
+        good: 0xa3be8c,
+        notice: 0xebcb8b,
+        attention: 0xd08770,
+        critical: 0xbf616a,
+    };
+
+    fn preset(preset: &ThemePreset) -> Self {
+        match preset {
+            ThemePreset::SolarizedDark => Self::SOLARIZED_DARK,
+            ThemePreset::Oksolar => Self::OKSOLAR,
+            ThemePreset::Gruvbox => Self::GRUVBOX,
+            ThemePreset::Nord => Self::NORD,
+        }
+    }
+
+    fn get(&self, color: Color) -> u32 {
+        match color {
+            Color::Foreground => self.foreground,
+            Color::MainIcon => self.main_icon,
+            Color::Focused => self.focused,
+            Color::Unfocused => self.unfocused,
+            Color::Good => self.good,
+            Color::Notice => self.notice,
+            Color::Attention => self.attention,
+            Color::Critical => self.critical,
+        }
+    }
+
+    fn get_mut(&mut self, color: Color) -> &mut u32 {
+        match color {
+            Color::Foreground => &mut self.foreground,
+            Color::MainIcon => &mut self.main_icon,
+            Color::Focused => &mut self.focused,
+            Color::Unfocused => &mut self.unfocused,
+            Color::Good => &mut self.good,
+            Color::Notice => &mut self.notice,
+            Color::Attention => &mut self.attention,
+            Color::Critical => &mut self.critical,
         }
-        _ => s.to_owned(),
     }
+
+    /// Build palette from theme config, querying Xresources only if some color references it
+    pub(crate) fn from_config(cfg: &ThemeConfig) -> anyhow::Result<Self> {
+        let needs_xresources = Color::ALL
+            .into_iter()
+            .filter_map(|c| cfg.entry(c))
+            .any(|v| !v.starts_with('#'));
+        let xresources = if needs_xresources {
+            query_xresources()?
+        } else {
+            HashMap::new()
+        };
+        Self::from_config_with_xresources(cfg, &xresources)
+    }
+
+    fn from_config_with_xresources(
+        cfg: &ThemeConfig,
+        xresources: &HashMap<String, String>,
+    ) -> anyhow::Result<Self> {
+        let mut palette = Self::preset(&cfg.preset.clone().unwrap_or_default());
+        for color in Color::ALL {
+            let Some(value) = cfg.entry(color) else {
+                continue;
+            };
+            let hex = if value.starts_with('#') {
+                value
+            } else {
+                xresources
+                    .get(value.trim_start_matches(['*', '.']))
+                    .ok_or_else(|| anyhow::anyhow!("Xresources color {value:?} not found"))?
+            };
+            *palette.get_mut(color) = parse_hex_color(hex)
+                .ok_or_else(|| anyhow::anyhow!("Invalid color {hex:?} for {color:?}"))?;
+        }
+        Ok(palette)
+    }
+}
+
+/// Palette colors are resolved against, can be changed at runtime
+static PALETTE: RwLock<Palette> = RwLock::new(Palette::OKSOLAR);
+
+/// Make `palette` the one colors are rendered with
+pub(crate) fn set_palette(palette: Palette) {
+    log::debug!("{palette:?}");
+    *PALETTE.write().unwrap() = palette;
+}
+
+fn parse_hex_color(s: &str) -> Option<u32> {
+    let hex = s.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok()
+}
+
+/// Get X resources from the running X server, with keys stripped of their wildcard prefix (ie. `color1` for
+/// `*.color1`)
+fn query_xresources() -> anyhow::Result<HashMap<String, String>> {
+    let output = Command::new("xrdb")
+        .arg("-query")
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .context("Failed to run xrdb")?;
+    output
+        .status
+        .exit_ok()
+        .context("Failed to query Xresources")?;
+    Ok(parse_xresources(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_xresources(s: &str) -> HashMap<String, String> {
+    s.lines()
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| {
+            (
+                k.trim().trim_start_matches(['*', '.']).to_owned(),
+                v.trim().to_owned(),
+            )
+        })
+        .collect()
 }
 
 // Shorten device model name (mouse, headset...)
@@ -80,18 +258,42 @@ pub(crate) fn shorten_model_name(s: &str) -> String {
 }
 
 #[cfg(test)]
+#[expect(clippy::shadow_unrelated)]
 mod tests {
     use super::*;
 
     #[test]
-    fn test_ellipsis() {
-        assert_eq!(ellipsis("blah blah blah", None), "blah blah blah");
-        assert_eq!(ellipsis("blah blah blah", Some(14)), "blah blah blah");
-        assert_eq!(ellipsis("blah blah blah!", Some(14)), "blah blah bla…");
-        assert_eq!(ellipsis("blah blah blah ", Some(14)), "blah blah blah");
-        assert_eq!(ellipsis("blah blah bla h", Some(14)), "blah blah bla…");
-        assert_eq!(ellipsis("blah blah bla ha", Some(15)), "blah blah bla…");
-        assert_eq!(ellipsis("éééé", Some(2)), "é…");
+    fn test_palette_from_config() {
+        let xresources = parse_xresources(
+            "*.background:\t#002b36\n*color1:\t#dc322f\nURxvt.font:\txft:Mono:size=10\n",
+        );
+        assert_eq!(xresources.get("color1").unwrap(), "#dc322f");
+        assert_eq!(xresources.get("URxvt.font").unwrap(), "xft:Mono:size=10");
+
+        let cfg: ThemeConfig = toml::from_str("").unwrap();
+        assert_eq!(
+            Palette::from_config_with_xresources(&cfg, &xresources).unwrap(),
+            Palette::OKSOLAR
+        );
+
+        let cfg: ThemeConfig = toml::from_str(
+            "preset = \"nord\"\nforeground = \"#123456\"\ncritical = \"*.color1\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            Palette::from_config_with_xresources(&cfg, &xresources).unwrap(),
+            Palette {
+                foreground: 0x0012_3456,
+                critical: 0x00dc_322f,
+                ..Palette::NORD
+            }
+        );
+
+        let cfg: ThemeConfig = toml::from_str("good = \"color2\"").unwrap();
+        assert!(Palette::from_config_with_xresources(&cfg, &xresources).is_err());
+
+        let cfg: ThemeConfig = toml::from_str("good = \"#12345\"").unwrap();
+        assert!(Palette::from_config_with_xresources(&cfg, &xresources).is_err());
     }
 
     #[test]